@@ -0,0 +1,22 @@
+//! Feeds arbitrary frames to every adapter's `parse_message`.
+//!
+//! A frame must produce some `ParseResult` – never a panic, which
+//! would take down the connection task of the exchange.
+
+#![no_main]
+
+use ftsobest_websocket_multi_collector::exchanges::{SUPPORTED, get_adapter};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // WS text frames are always valid UTF-8
+    let Ok(raw) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    for name in SUPPORTED {
+        if let Some(adapter) = get_adapter(name) {
+            let _ = adapter.parse_message(raw, name);
+        }
+    }
+});
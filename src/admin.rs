@@ -0,0 +1,279 @@
+//! Admin HTTP endpoint
+//!
+//! Small operator API bound to `admin.bind` (e.g. "127.0.0.1:9100").
+//!
+//! Routes:
+//! - POST /exchanges/{name}/disable → stop all collectors of an exchange
+//! - POST /exchanges/{name}/enable  → start them again
+//! - GET  /metrics                  → runtime counters, master queue depths,
+//!   per-exchange totals (JSON; Prometheus text with `Accept: text/plain`)
+//! - GET  /metrics.json             → the same, always JSON
+//! - GET  /status                   → state of every exchange
+//! - GET  /connections              → live WS connections and their counters
+//! - GET  /exchanges/{name}/connections → the same, for one exchange
+//!
+//! SECURITY:
+//! - There is no authentication; bind to localhost or a private
+//!   interface only.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use http_body_util::Full;
+use hyper::{
+    Method, Request, Response, StatusCode,
+    body::{Bytes, Incoming},
+    server::conn::http1,
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+
+use crate::collector::connections::CONNECTIONS;
+use crate::collector::registry::ExchangeRegistry;
+use crate::master_sender::MasterPool;
+use crate::metrics::METRICS;
+
+/// Runs the admin HTTP server until the process exits.
+///
+/// Each connection is served in its own task; a failing connection
+/// never affects the server loop.
+pub async fn serve(
+    bind: String,
+    registry: Arc<ExchangeRegistry>,
+    master: MasterPool,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(&bind).await?;
+    tracing::info!("Admin endpoint listening on {}", bind);
+
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::warn!("[ADMIN] accept failed: {}", e);
+                continue;
+            }
+        };
+
+        let registry = registry.clone();
+        let master = master.clone();
+
+        tokio::spawn(async move {
+            let service = service_fn(move |req| handle(req, registry.clone(), master.clone()));
+
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::warn!("[ADMIN] connection error: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle(
+    req: Request<Incoming>,
+    registry: Arc<ExchangeRegistry>,
+    master: MasterPool,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let path = req.uri().path().trim_matches('/').to_string();
+    let segments: Vec<&str> = path.split('/').collect();
+
+    let response = match (req.method(), segments.as_slice()) {
+        (&Method::POST, ["exchanges", name, "disable"]) => {
+            match registry.disable(name).await {
+                Ok(changed) => json_response(
+                    StatusCode::OK,
+                    json!({ "exchange": name, "state": "disabled", "changed": changed }),
+                ),
+                Err(e) => json_response(StatusCode::NOT_FOUND, json!({ "error": e.to_string() })),
+            }
+        }
+
+        (&Method::POST, ["exchanges", name, "enable"]) => {
+            match registry.enable(name).await {
+                Ok(changed) => json_response(
+                    StatusCode::OK,
+                    json!({ "exchange": name, "state": "enabled", "changed": changed }),
+                ),
+                Err(e) => json_response(StatusCode::NOT_FOUND, json!({ "error": e.to_string() })),
+            }
+        }
+
+        (&Method::GET, ["connections"]) => {
+            json_response(StatusCode::OK, json!({ "connections": CONNECTIONS.list() }))
+        }
+
+        (&Method::GET, ["exchanges", name, "connections"]) => {
+            json_response(StatusCode::OK, json!({ "connections": CONNECTIONS.for_exchange(name) }))
+        }
+
+        (&Method::GET, ["status"]) => {
+            json_response(StatusCode::OK, json!({ "exchanges": registry.status().await }))
+        }
+
+        (&Method::GET, ["metrics"]) if wants_prometheus(&req) => {
+            text_response(StatusCode::OK, metrics_text(&master, &registry).await)
+        }
+
+        (&Method::GET, ["metrics"]) | (&Method::GET, ["metrics.json"]) => {
+            json_response(StatusCode::OK, metrics_json(&master, &registry).await)
+        }
+
+        _ => json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" })),
+    };
+
+    Ok(response)
+}
+
+/// Prometheus text: runtime counters, stream gauges and the queue
+/// depth of every master connection.
+///
+/// Dedicated pools (`master_url`) carry their URL as `master` label.
+async fn metrics_text(master: &MasterPool, registry: &ExchangeRegistry) -> String {
+    let mut metrics = METRICS.prometheus();
+    metrics.push_str(&CONNECTIONS.prometheus());
+
+    for (conn, depth) in master.queue_depths().await.into_iter().enumerate() {
+        metrics.push_str(&format!("collector_master_queue_depth{{connection=\"{conn}\"}} {depth}\n"));
+    }
+
+    for (url, depths) in registry.dedicated_queue_depths().await {
+        for (conn, depth) in depths.into_iter().enumerate() {
+            metrics.push_str(&format!(
+                "collector_master_queue_depth{{master=\"{url}\",connection=\"{conn}\"}} {depth}\n"
+            ));
+        }
+    }
+
+    metrics
+}
+
+/// All runtime counters, the master queue depths and per-exchange
+/// connection totals as one JSON object.
+async fn metrics_json(master: &MasterPool, registry: &ExchangeRegistry) -> Value {
+    let snapshot = METRICS.snapshot();
+    let mut exchanges: BTreeMap<String, Value> = BTreeMap::new();
+
+    for conn in CONNECTIONS.list() {
+        let entry = exchanges.entry(conn.exchange.clone()).or_insert_with(|| json!({
+            "connections": 0,
+            "streams_up": 0,
+            "messages": 0,
+            "reconnects": 0,
+            "clock_skew_ms": snapshot.clock_skew_ms.get(&conn.exchange),
+        }));
+
+        let add = |v: &mut Value, n: u64| *v = json!(v.as_u64().unwrap_or(0) + n);
+        add(&mut entry["connections"], 1);
+        add(&mut entry["streams_up"], u64::from(conn.stream_up));
+        add(&mut entry["messages"], conn.messages);
+        add(&mut entry["reconnects"], conn.reconnects);
+    }
+
+    let mut metrics = json!(snapshot);
+    metrics["master_queue_depth"] = json!(master.queue_depths().await);
+    metrics["dedicated_master_queue_depth"] = json!(registry.dedicated_queue_depths().await);
+    metrics["exchanges"] = json!(exchanges);
+    metrics
+}
+
+/// Prometheus scrapers ask for `text/plain` (or OpenMetrics);
+/// everything else gets JSON.
+fn wants_prometheus(req: &Request<Incoming>) -> bool {
+    req.headers()
+        .get(hyper::header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/plain") || accept.contains("openmetrics"))
+}
+
+fn text_response(status: StatusCode, body: String) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+    response
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body.to_string())));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::runner::ConnectionBudget;
+    use crate::output::{MasterRoutes, SendMeta, recording::RecordingSink};
+
+    fn registry() -> ExchangeRegistry {
+        let template = serde_json::from_value(json!({
+            "url": "wss://master.example", "connections": 1, "key": "k", "demo": true
+        }))
+        .unwrap();
+
+        ExchangeRegistry::new(
+            MasterRoutes::new(Arc::new(RecordingSink::default()), template, false, None, false),
+            ConnectionBudget::new(None),
+        )
+    }
+
+    #[tokio::test]
+    async fn prometheus_metrics_include_master_queue_depths() {
+        let (master, _receivers) = MasterPool::detached(2);
+        for conn_id in [0, 0, 1] {
+            master.send(json!({ "type": "trade" }), SendMeta::live(conn_id)).await.unwrap();
+        }
+
+        let text = metrics_text(&master, &registry()).await;
+
+        assert!(text.contains("collector_master_queue_depth{connection=\"0\"} 2\n"), "{text}");
+        assert!(text.contains("collector_master_queue_depth{connection=\"1\"} 1\n"), "{text}");
+    }
+
+    #[tokio::test]
+    async fn json_metrics_include_every_counter_and_the_queue_depths() {
+        let (master, _receivers) = MasterPool::detached(2);
+        master.send(json!({ "type": "trade" }), SendMeta::live(1)).await.unwrap();
+
+        let metrics = metrics_json(&master, &registry()).await;
+
+        let Value::Object(counters) = serde_json::to_value(METRICS.snapshot()).unwrap() else {
+            panic!("expected an object");
+        };
+        for name in counters.keys() {
+            assert!(metrics.get(name).is_some(), "{name} missing");
+        }
+        assert_eq!(metrics["master_queue_depth"], json!([0, 1]));
+        assert_eq!(metrics["dedicated_master_queue_depth"], json!({}));
+        assert!(metrics["exchanges"].is_object());
+    }
+
+    #[tokio::test]
+    async fn status_describes_each_exchange_adapter() {
+        let registry = registry();
+        let exchange = serde_json::from_value(json!({
+            "name": "binance", "enabled": false, "pairs": { "trades": [], "orderbooks": [] }
+        }))
+        .unwrap();
+        assert!(registry.register(exchange).await);
+
+        let status = json!({ "exchanges": registry.status().await });
+        let adapter = &status["exchanges"][0]["adapter"];
+
+        assert_eq!(adapter["name"], "binance");
+        assert_eq!(adapter["ws_url"], "wss://stream.binance.com:9443/ws");
+        assert_eq!(adapter["channels"], json!(["trades", "orderbooks", "funding"]));
+        assert_eq!(adapter["auth_required"], false);
+        assert_eq!(adapter["max_streams_per_connection"], 1024);
+    }
+}
@@ -0,0 +1,131 @@
+//! Reconnect delay policies.
+//!
+//! Every place that retries a connection (WS loop supervisor,
+//! master connections) asks a `ReconnectPolicy` for the next delay
+//! instead of keeping its own backoff arithmetic.
+
+use std::time::Duration;
+
+use rand::random_range;
+
+/// Result of the attempt that just ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Failed right away (connect error, early panic)
+    Failed,
+
+    /// Ran stably for a while before it ended; restarts the sequence
+    Stable,
+}
+
+/// Decides how long to wait before the next attempt.
+pub trait ReconnectPolicy: Send {
+    /// Delay before the next attempt, given how the last one ended.
+    fn next_delay(&mut self, outcome: Outcome) -> Duration;
+
+    /// Restarts the sequence at the initial delay.
+    fn reset(&mut self);
+}
+
+/// Doubling delay between `initial` and `max`.
+///
+/// With jitter, each delay is shortened by a random share of up to
+/// `jitter` (0.0–1.0), so connections failing together do not all
+/// retry in the same instant.
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    initial: Duration,
+    max: Duration,
+    jitter: f64,
+    current: Duration,
+}
+
+impl ExponentialBackoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max: max.max(initial),
+            jitter: 0.0,
+            current: initial,
+        }
+    }
+
+    /// Upper bound of the delay.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Sets the jitter share (clamped to 0.0–1.0).
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+}
+
+impl ReconnectPolicy for ExponentialBackoff {
+    fn next_delay(&mut self, outcome: Outcome) -> Duration {
+        if outcome == Outcome::Stable {
+            self.reset();
+        }
+
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+
+        if self.jitter > 0.0 {
+            delay.mul_f64(1.0 - random_range(0.0..=self.jitter))
+        } else {
+            delay
+        }
+    }
+
+    fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(delays: &[u64]) -> Vec<Duration> {
+        delays.iter().copied().map(Duration::from_secs).collect()
+    }
+
+    #[test]
+    fn delays_double_up_to_the_cap() {
+        let mut backoff = ExponentialBackoff::new(Duration::from_secs(1), Duration::from_secs(30));
+        let delays: Vec<_> = (0..7).map(|_| backoff.next_delay(Outcome::Failed)).collect();
+
+        assert_eq!(delays, secs(&[1, 2, 4, 8, 16, 30, 30]));
+    }
+
+    #[test]
+    fn stable_runs_and_reset_restart_the_sequence() {
+        let mut backoff = ExponentialBackoff::new(Duration::from_secs(1), Duration::from_secs(30));
+        for _ in 0..4 {
+            backoff.next_delay(Outcome::Failed);
+        }
+
+        assert_eq!(backoff.next_delay(Outcome::Stable), Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(Outcome::Failed), Duration::from_secs(2));
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(Outcome::Failed), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn jitter_only_shortens_delays() {
+        let mut backoff = ExponentialBackoff::new(Duration::from_secs(8), Duration::from_secs(8))
+            .with_jitter(0.5);
+
+        for _ in 0..100 {
+            let delay = backoff.next_delay(Outcome::Failed);
+            assert!(delay >= Duration::from_secs(4) && delay <= Duration::from_secs(8), "{delay:?}");
+        }
+
+        // Out of range shares are clamped
+        let mut full = ExponentialBackoff::new(Duration::from_secs(1), Duration::from_secs(1))
+            .with_jitter(3.0);
+        assert!(full.next_delay(Outcome::Failed) <= Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,232 @@
+//! Trade backfill after reconnects.
+//!
+//! Trades executed while a connection was down are lost on the WS.
+//! With `backfill_trades`, the runner fetches the recent trades of
+//! every pair via REST after re-subscribing and forwards those newer
+//! than the last live trade. Trade ids dedup the overlap between the
+//! REST response and the live stream.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use super::runner::handle_parsed;
+use crate::config::ExchangeConfig;
+use crate::exchanges::adapter::{ExchangeAdapter, ParseResult};
+use crate::metrics::METRICS;
+use crate::output::{OutputSink, SendMeta};
+use crate::schema::{MarketMessage, TradeData};
+
+/// Trade ids remembered per pair for dedup
+const RECENT_IDS: usize = 2_000;
+
+/// Backfill state of one WS connection.
+#[derive(Default)]
+pub(super) struct TradeBackfill {
+    pairs: HashMap<String, RecentTrades>,
+}
+
+#[derive(Default)]
+struct RecentTrades {
+    /// Timestamp of the newest trade seen
+    last_ts: i64,
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl RecentTrades {
+    /// Records a trade; false if its id was already seen.
+    fn insert(&mut self, trade: &TradeData) -> bool {
+        let Some(id) = &trade.trade_id else {
+            return true;
+        };
+
+        if !self.ids.insert(id.clone()) {
+            return false;
+        }
+
+        self.order.push_back(id.clone());
+        if self.order.len() > RECENT_IDS
+            && let Some(old) = self.order.pop_front()
+        {
+            self.ids.remove(&old);
+        }
+
+        self.last_ts = self.last_ts.max(trade.timestamp);
+        true
+    }
+}
+
+impl TradeBackfill {
+    /// Records a live parse result.
+    ///
+    /// Returns false for trades already forwarded by a backfill.
+    /// Such trades are removed from batches; an emptied batch
+    /// returns false.
+    pub fn observe(&mut self, result: &mut ParseResult) -> bool {
+        match result {
+            ParseResult::Market(mm) => self.observe_message(mm),
+            ParseResult::Batch(batch) => {
+                batch.retain(|mm| self.observe_message(mm));
+                !batch.is_empty()
+            }
+            _ => true,
+        }
+    }
+
+    fn observe_message(&mut self, mm: &MarketMessage) -> bool {
+        match mm {
+            MarketMessage::Trade(trade) => self
+                .pairs
+                .entry(trade.symbol.clone())
+                .or_default()
+                .insert(trade),
+            _ => true,
+        }
+    }
+
+    /// Fetches and forwards the trades missed since the last live
+    /// trade of each pair.
+    ///
+    /// Pairs without any live trade yet (first connect) are skipped.
+    /// Trades are forwarded on behalf of WS connection `conn_id`.
+    pub async fn run(
+        &mut self,
+        adapter: &dyn ExchangeAdapter,
+        cfg: &ExchangeConfig,
+        pairs: &[String],
+        timeout: Duration,
+        output: &dyn OutputSink,
+        conn_id: usize,
+    ) {
+        for pair in pairs {
+            let Some(recent) = self.pairs.get_mut(pair) else {
+                continue;
+            };
+
+            let mut trades = match adapter.recent_trades(pair, timeout).await {
+                Ok(trades) => trades,
+                Err(e) => {
+                    tracing::warn!("[BACKFILL][{}] {pair} failed: {e}", adapter.name());
+                    continue;
+                }
+            };
+
+            let meta = SendMeta::rest(Some(conn_id));
+            trades.sort_by_key(|t| t.timestamp);
+
+            let since = recent.last_ts;
+            let mut count = 0;
+
+            for trade in trades {
+                if trade.timestamp < since || !recent.insert(&trade) {
+                    continue;
+                }
+
+                handle_parsed(
+                    ParseResult::Market(MarketMessage::Trade(trade)),
+                    cfg,
+                    output,
+                    meta,
+                )
+                .await;
+
+                count += 1;
+            }
+
+            METRICS.trades_backfilled.fetch_add(count, Ordering::Relaxed);
+
+            if count > 0 {
+                tracing::info!("[BACKFILL][{}] {pair}: {count} missed trades", adapter.name());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::adapter::{AdapterMetadata, ChannelType};
+    use crate::output::recording::RecordingSink;
+
+    fn trade(id: &str, timestamp: i64) -> TradeData {
+        serde_json::from_value(serde_json::json!({
+            "exchange": "rest", "symbol": "BTC/USDT", "timestamp": timestamp,
+            "price": "1", "amount": "1", "side": "buy", "trade_id": id,
+        }))
+        .unwrap()
+    }
+
+    /// Serves the same recent trades for every pair.
+    struct Rest(Vec<TradeData>);
+
+    #[async_trait::async_trait]
+    impl ExchangeAdapter for Rest {
+        fn name(&self) -> &'static str {
+            "rest"
+        }
+
+        fn ws_url(&self) -> &'static str {
+            "ws://unused"
+        }
+
+        fn metadata(&self) -> AdapterMetadata {
+            AdapterMetadata {
+                name: self.name(),
+                ws_url: self.ws_url(),
+                channels: &[ChannelType::Trades],
+                auth_required: false,
+                max_streams_per_connection: None,
+            }
+        }
+
+        fn build_subscribe_message(&self, _: ChannelType, _: &[String], _: &ExchangeConfig) -> serde_json::Value {
+            serde_json::json!({})
+        }
+
+        fn parse_message(&self, _: &str, _: &str) -> ParseResult {
+            ParseResult::Ignored
+        }
+
+        async fn recent_trades(&self, _pair: &str, _timeout: Duration) -> anyhow::Result<Vec<TradeData>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn only_trades_missed_since_the_last_live_trade_are_forwarded() {
+        let mut backfill = TradeBackfill::default();
+        for live in [trade("1", 100), trade("2", 200)] {
+            assert!(backfill.observe(&mut ParseResult::Market(MarketMessage::Trade(live))));
+        }
+
+        let rest = Rest(vec![
+            trade("3", 250),
+            trade("0", 50),
+            trade("1", 100),
+            trade("2", 200),
+            trade("4", 200),
+        ]);
+        let cfg: ExchangeConfig = serde_json::from_value(serde_json::json!({
+            "name": "rest", "enabled": true, "pairs": { "trades": ["BTC/USDT"], "orderbooks": [] }
+        }))
+        .unwrap();
+        let output = RecordingSink::default();
+
+        backfill.run(&rest, &cfg, &["BTC/USDT".into(), "ETH/USDT".into()], Duration::from_secs(1), &output, 7).await;
+
+        let ids: Vec<_> = output
+            .messages()
+            .into_iter()
+            .map(|mm| match mm {
+                MarketMessage::Trade(t) => t.trade_id.unwrap(),
+                _ => panic!("expected trades"),
+            })
+            .collect();
+        assert_eq!(ids, ["4", "3"]);
+        assert!(output.metas().iter().all(|m| m.conn_id == Some(7) && !m.live));
+
+        // Backfilled trades arriving late on the WS are dropped
+        assert!(!backfill.observe(&mut ParseResult::Market(MarketMessage::Trade(trade("3", 250)))));
+    }
+}
@@ -0,0 +1,109 @@
+//! Dedup of unchanged orderbooks.
+//!
+//! Some exchanges (and REST polling) resend an unchanged book over and
+//! over. With `orderbook.dedup`, a book whose levels hash equal to the
+//! previous book of the same symbol is dropped before any output.
+//! Timestamps are not compared.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::Ordering;
+
+use crate::exchanges::adapter::ParseResult;
+use crate::metrics::METRICS;
+use crate::schema::{BookData, MarketMessage};
+
+/// Book dedup state of one connection / poller.
+#[derive(Default)]
+pub(super) struct BookDedup {
+    /// Level hash of the last book forwarded per symbol
+    last: HashMap<String, u64>,
+}
+
+impl BookDedup {
+    /// Records a parse result.
+    ///
+    /// Returns false for books identical to the previous one of the
+    /// same symbol. Such books are removed from batches; an emptied
+    /// batch returns false.
+    pub fn observe(&mut self, result: &mut ParseResult) -> bool {
+        match result {
+            ParseResult::Market(mm) => self.observe_message(mm),
+            ParseResult::Batch(batch) => {
+                batch.retain(|mm| self.observe_message(mm));
+                !batch.is_empty()
+            }
+            _ => true,
+        }
+    }
+
+    fn observe_message(&mut self, mm: &MarketMessage) -> bool {
+        let MarketMessage::Book(book) = mm else {
+            return true;
+        };
+
+        let hash = levels_hash(book);
+
+        if self.last.insert(book.symbol.clone(), hash) == Some(hash) {
+            METRICS.books_deduped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        true
+    }
+}
+
+fn levels_hash(book: &BookData) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    book.asks.hash(&mut hasher);
+    book.bids.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(symbol: &str, timestamp: i64, bid: &str) -> ParseResult {
+        ParseResult::Market(
+            serde_json::from_value(serde_json::json!({
+                "type": "book", "exchange": "okx", "symbol": symbol, "timestamp": timestamp,
+                "asks": [["101", "1"]], "bids": [[bid, "1"]],
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn unchanged_books_are_dropped() {
+        let mut dedup = BookDedup::default();
+
+        assert!(dedup.observe(&mut book("BTC/USDT", 1, "100")));
+        // Same levels, newer timestamp
+        assert!(!dedup.observe(&mut book("BTC/USDT", 2, "100")));
+        assert!(dedup.observe(&mut book("BTC/USDT", 3, "99")));
+        // Tracked per symbol
+        assert!(dedup.observe(&mut book("ETH/USDT", 3, "99")));
+    }
+
+    #[test]
+    fn unchanged_books_are_removed_from_batches() {
+        let mut dedup = BookDedup::default();
+        assert!(dedup.observe(&mut book("BTC/USDT", 1, "100")));
+
+        let market = |result| match result {
+            ParseResult::Market(mm) => mm,
+            _ => unreachable!(),
+        };
+        let mut batch = ParseResult::Batch(vec![
+            market(book("BTC/USDT", 2, "100")),
+            market(book("ETH/USDT", 2, "100")),
+        ]);
+
+        assert!(dedup.observe(&mut batch));
+        let ParseResult::Batch(kept) = batch else { unreachable!() };
+        let symbols: Vec<_> = kept.iter().map(|mm| mm.symbol()).collect();
+        assert_eq!(symbols, ["ETH/USDT"]);
+    }
+}
@@ -0,0 +1,366 @@
+//! Registry of live WS connections.
+//!
+//! Every `run_ws_loop` registers itself for its whole lifetime and
+//! keeps its state and counters up to date. The registry is read by
+//! the admin endpoint (`GET /connections`) and by embedders that want
+//! to inspect what the collector is connected to.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU8, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::exchanges::adapter::ChannelType;
+use crate::util;
+
+/// Lifecycle state of one WS loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionState {
+    /// Fetching the URL / dialing, not yet connected
+    Connecting,
+
+    /// WebSocket open (subscriptions are sent right after)
+    Connected,
+
+    /// Connection lost, waiting for the reconnect delay
+    Reconnecting,
+}
+
+impl ConnectionState {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => Self::Connected,
+            2 => Self::Reconnecting,
+            _ => Self::Connecting,
+        }
+    }
+}
+
+/// Snapshot of one connection, as returned by `ConnectionRegistry::list`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionInfo {
+    pub id: usize,
+    pub exchange: String,
+    pub channels: Vec<ChannelType>,
+    pub symbols: Vec<String>,
+    pub state: ConnectionState,
+
+    /// Frames received over all connects
+    pub messages: u64,
+
+    /// Established connections that were lost
+    pub reconnects: u64,
+
+    /// Time of the current connect (ms), None while not connected
+    pub connected_since: Option<i64>,
+
+    /// Market data arrived since the current connect
+    pub stream_up: bool,
+
+    /// Start of the current run of failed connect attempts (ms),
+    /// None once connected
+    pub failing_since: Option<i64>,
+
+    /// Error of the last failed connect attempt
+    pub last_error: Option<String>,
+}
+
+impl ConnectionInfo {
+    /// True if connect attempts have been failing for longer than
+    /// `UNHEALTHY_AFTER_MS`.
+    pub fn is_unhealthy(&self) -> bool {
+        self.failing_since
+            .is_some_and(|since| util::now_ms() - since > UNHEALTHY_AFTER_MS)
+    }
+}
+
+/// Retrying longer than this marks a connection unhealthy in `/status`
+pub const UNHEALTHY_AFTER_MS: i64 = 60_000;
+
+/// Live state of one registered connection, updated by its WS loop.
+pub struct ConnectionEntry {
+    id: usize,
+    exchange: String,
+    channels: Vec<ChannelType>,
+    symbols: Vec<String>,
+    state: AtomicU8,
+    messages: AtomicU64,
+    reconnects: AtomicU64,
+    connected_since: AtomicI64,
+    stream_up: AtomicBool,
+    failing_since: AtomicI64,
+    last_error: Mutex<Option<String>>,
+}
+
+impl ConnectionEntry {
+    /// Registry id, as listed in `/connections`
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn connecting(&self) {
+        self.state.store(ConnectionState::Connecting as u8, Ordering::Relaxed);
+    }
+
+    pub fn connected(&self) {
+        self.failing_since.store(0, Ordering::Relaxed);
+        *self.lock_error() = None;
+        self.connected_since.store(util::now_ms(), Ordering::Relaxed);
+        self.state.store(ConnectionState::Connected as u8, Ordering::Relaxed);
+    }
+
+    /// Marks the connection lost; `established` counts a reconnect.
+    pub fn disconnected(&self, established: bool) {
+        if established {
+            self.reconnects.fetch_add(1, Ordering::Relaxed);
+        }
+        self.connected_since.store(0, Ordering::Relaxed);
+        self.stream_up.store(false, Ordering::Relaxed);
+        self.state.store(ConnectionState::Reconnecting as u8, Ordering::Relaxed);
+    }
+
+    /// Records a failed connect attempt (URL fetch or dial).
+    pub fn connect_failed(&self, error: &str) {
+        let _ = self.failing_since.compare_exchange(
+            0,
+            util::now_ms(),
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+        *self.lock_error() = Some(error.to_string());
+    }
+
+    pub fn frame(&self) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Marks market data as flowing.
+    ///
+    /// Returns true only for the first market data of a connect.
+    pub fn market_data(&self) -> bool {
+        !self.stream_up.swap(true, Ordering::Relaxed)
+    }
+
+    /// Milliseconds since the current connect, 0 while not connected.
+    pub fn connected_for_ms(&self) -> i64 {
+        match self.connected_since.load(Ordering::Relaxed) {
+            0 => 0,
+            since => util::now_ms() - since,
+        }
+    }
+
+    fn info(&self) -> ConnectionInfo {
+        let since = self.connected_since.load(Ordering::Relaxed);
+        let failing_since = self.failing_since.load(Ordering::Relaxed);
+
+        ConnectionInfo {
+            id: self.id,
+            exchange: self.exchange.clone(),
+            channels: self.channels.clone(),
+            symbols: self.symbols.clone(),
+            state: ConnectionState::from_u8(self.state.load(Ordering::Relaxed)),
+            messages: self.messages.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            connected_since: (since > 0).then_some(since),
+            stream_up: self.stream_up.load(Ordering::Relaxed),
+            failing_since: (failing_since > 0).then_some(failing_since),
+            last_error: self.lock_error().clone(),
+        }
+    }
+
+    fn lock_error(&self) -> std::sync::MutexGuard<'_, Option<String>> {
+        self.last_error.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Concurrent map of all live WS loops.
+#[derive(Default)]
+pub struct ConnectionRegistry {
+    next_id: AtomicUsize,
+    entries: Mutex<BTreeMap<usize, Arc<ConnectionEntry>>>,
+}
+
+impl ConnectionRegistry {
+    /// Registers a connection; it is removed when the guard drops
+    /// (WS loop ended or aborted).
+    pub fn register(
+        &'static self,
+        exchange: &str,
+        channels: Vec<ChannelType>,
+        symbols: Vec<String>,
+    ) -> ConnectionGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        let entry = Arc::new(ConnectionEntry {
+            id,
+            exchange: exchange.to_string(),
+            channels,
+            symbols,
+            state: AtomicU8::new(ConnectionState::Connecting as u8),
+            messages: AtomicU64::new(0),
+            reconnects: AtomicU64::new(0),
+            connected_since: AtomicI64::new(0),
+            stream_up: AtomicBool::new(false),
+            failing_since: AtomicI64::new(0),
+            last_error: Mutex::new(None),
+        });
+
+        self.lock().insert(id, entry.clone());
+
+        ConnectionGuard { registry: self, entry }
+    }
+
+    /// Returns all live connections, ordered by registration.
+    pub fn list(&self) -> Vec<ConnectionInfo> {
+        self.lock().values().map(|e| e.info()).collect()
+    }
+
+    /// Renders `collector_stream_up` (1 while market data flows) per
+    /// connection in the Prometheus text format.
+    pub fn prometheus(&self) -> String {
+        let mut out = String::from("# TYPE collector_stream_up gauge\n");
+
+        for info in self.list() {
+            out.push_str(&format!(
+                "collector_stream_up{{exchange=\"{}\",connection=\"{}\"}} {}\n",
+                info.exchange,
+                info.id,
+                u8::from(info.stream_up)
+            ));
+        }
+
+        out
+    }
+
+    /// Returns the live connections of one exchange.
+    pub fn for_exchange(&self, exchange: &str) -> Vec<ConnectionInfo> {
+        self.lock()
+            .values()
+            .filter(|e| e.exchange == exchange)
+            .map(|e| e.info())
+            .collect()
+    }
+
+    /// Returns the connections of one exchange that have been
+    /// failing to connect for longer than `UNHEALTHY_AFTER_MS`.
+    pub fn unhealthy(&self, exchange: &str) -> Vec<ConnectionInfo> {
+        self.for_exchange(exchange)
+            .into_iter()
+            .filter(ConnectionInfo::is_unhealthy)
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<usize, Arc<ConnectionEntry>>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Registration of one WS loop; derefs to its entry for updates.
+pub struct ConnectionGuard {
+    registry: &'static ConnectionRegistry,
+    entry: Arc<ConnectionEntry>,
+}
+
+impl std::ops::Deref for ConnectionGuard {
+    type Target = ConnectionEntry;
+
+    fn deref(&self) -> &ConnectionEntry {
+        &self.entry
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.registry.lock().remove(&self.entry.id);
+    }
+}
+
+/// Global connection registry (singleton)
+pub static CONNECTIONS: Lazy<ConnectionRegistry> = Lazy::new(ConnectionRegistry::default);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> &'static ConnectionRegistry {
+        Box::leak(Box::default())
+    }
+
+    #[test]
+    fn connections_failing_too_long_are_unhealthy() {
+        let registry = registry();
+
+        util::set_test_clock(Some(1_000_000));
+        let conn = registry.register("binance", vec![ChannelType::Trades], vec!["BTC/USDT".into()]);
+        conn.connect_failed("dns error: no such host");
+
+        util::set_test_clock(Some(1_000_000 + UNHEALTHY_AFTER_MS));
+        let at_limit = registry.unhealthy("binance");
+
+        util::set_test_clock(Some(1_000_001 + UNHEALTHY_AFTER_MS));
+        let unhealthy = registry.unhealthy("binance");
+        util::set_test_clock(None);
+
+        assert!(at_limit.is_empty());
+        assert_eq!(unhealthy.len(), 1);
+        assert_eq!(unhealthy[0].id, conn.id());
+        assert_eq!(unhealthy[0].last_error.as_deref(), Some("dns error: no such host"));
+        assert!(registry.unhealthy("okx").is_empty());
+    }
+
+    #[test]
+    fn connections_are_tracked_until_their_guard_drops() {
+        let registry = registry();
+        let conn = registry.register("okx", vec![ChannelType::Trades, ChannelType::OrderBooks], vec!["ETH/USDT".into()]);
+
+        let info = &registry.list()[0];
+        assert_eq!((info.exchange.as_str(), info.state), ("okx", ConnectionState::Connecting));
+        assert_eq!(info.symbols, ["ETH/USDT"]);
+
+        util::set_test_clock(Some(5_000));
+        conn.connected();
+        util::set_test_clock(None);
+        conn.frame();
+        assert!(conn.market_data());
+        assert!(!conn.market_data());
+
+        let info = &registry.for_exchange("okx")[0];
+        assert_eq!(info.state, ConnectionState::Connected);
+        assert_eq!(info.connected_since, Some(5_000));
+        assert_eq!(info.messages, 1);
+        assert!(info.stream_up);
+
+        conn.disconnected(true);
+        let info = &registry.list()[0];
+        assert_eq!(info.state, ConnectionState::Reconnecting);
+        assert_eq!((info.reconnects, info.connected_since, info.stream_up), (1, None, false));
+
+        drop(conn);
+        assert!(registry.list().is_empty());
+    }
+
+    #[test]
+    fn stream_up_is_reported_once_per_connect() {
+        let registry = registry();
+        let conn = registry.register("bybit", vec![ChannelType::Trades], vec!["BTC/USDT".into()]);
+        let gauge = |up: u8| {
+            format!("collector_stream_up{{exchange=\"bybit\",connection=\"{}\"}} {up}\n", conn.id())
+        };
+
+        conn.connected();
+        assert!(registry.prometheus().ends_with(&gauge(0)));
+
+        let firsts: Vec<bool> = (0..3).map(|_| conn.market_data()).collect();
+        assert_eq!(firsts, [true, false, false]);
+        assert!(registry.prometheus().ends_with(&gauge(1)));
+
+        // A new connect reports its first data again
+        conn.disconnected(true);
+        assert!(registry.prometheus().ends_with(&gauge(0)));
+        conn.connected();
+        assert!(conn.market_data());
+    }
+}
@@ -0,0 +1,240 @@
+//! Local orderbooks for `orderbook.forward = false`.
+//!
+//! Books keep being subscribed and applied to a per-symbol book on
+//! the connection, but are not forwarded. With `orderbook.bbo`, a
+//! ticker carrying the best bid / ask is forwarded instead whenever
+//! the top of the book changes.
+
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+
+use crate::exchanges::adapter::ParseResult;
+use crate::schema::{BookData, MarketMessage, TickerData};
+
+/// One side of a local book: price → amount as sent
+type Side = BTreeMap<Decimal, (String, String)>;
+
+#[derive(Default)]
+struct LocalBook {
+    asks: Side,
+    bids: Side,
+
+    /// Last forwarded best bid / ask prices
+    last_bbo: (Option<String>, Option<String>),
+}
+
+/// Local books of one connection / poller.
+///
+/// Snapshot books (`BookData::snapshot`) replace the local book,
+/// deltas patch it: a zero amount removes the level. Deltas for a
+/// symbol without a snapshot yet (after `reset`) are dropped, they
+/// would patch an empty book.
+pub(super) struct LocalBooks {
+    books: HashMap<String, LocalBook>,
+
+    /// Forward a ticker when the best bid / ask changes
+    bbo: bool,
+}
+
+impl LocalBooks {
+    pub fn new(bbo: bool) -> Self {
+        Self { books: HashMap::new(), bbo }
+    }
+
+    /// Drops all books, e.g. on reconnect: levels of the previous
+    /// connection must not leak into the new one. Every symbol then
+    /// waits for a fresh snapshot.
+    pub fn reset(&mut self) {
+        self.books.clear();
+    }
+
+    /// Applies the books of a parse result to the local books.
+    ///
+    /// Books are removed from the result or, with `bbo`, replaced by
+    /// a ticker if the top of the book changed. Returns false if
+    /// nothing is left to forward.
+    pub fn observe(&mut self, result: &mut ParseResult) -> bool {
+        match result {
+            ParseResult::Market(mm) => match self.absorb(mm) {
+                Absorbed::Keep => true,
+                Absorbed::Replace(ticker) => {
+                    *mm = ticker;
+                    true
+                }
+                Absorbed::Drop => false,
+            },
+            ParseResult::Batch(batch) => {
+                let messages = std::mem::take(batch);
+
+                *batch = messages
+                    .into_iter()
+                    .filter_map(|mut mm| match self.absorb(&mut mm) {
+                        Absorbed::Keep => Some(mm),
+                        Absorbed::Replace(ticker) => Some(ticker),
+                        Absorbed::Drop => None,
+                    })
+                    .collect();
+
+                !batch.is_empty()
+            }
+            _ => true,
+        }
+    }
+
+    fn absorb(&mut self, mm: &mut MarketMessage) -> Absorbed {
+        let MarketMessage::Book(update) = mm else {
+            return Absorbed::Keep;
+        };
+
+        let book = if update.snapshot {
+            let book = self.books.entry(update.symbol.clone()).or_default();
+            book.asks.clear();
+            book.bids.clear();
+            book
+        } else {
+            match self.books.get_mut(&update.symbol) {
+                Some(book) => book,
+                // Not snapshotted yet
+                None => return Absorbed::Drop,
+            }
+        };
+
+        apply(&mut book.asks, &update.asks);
+        apply(&mut book.bids, &update.bids);
+
+        let bbo = (
+            book.bids.last_key_value().map(|(_, (price, _))| price.clone()),
+            book.asks.first_key_value().map(|(_, (price, _))| price.clone()),
+        );
+
+        if !self.bbo || bbo == book.last_bbo {
+            return Absorbed::Drop;
+        }
+
+        book.last_bbo = bbo.clone();
+        Absorbed::Replace(ticker(update, bbo))
+    }
+}
+
+enum Absorbed {
+    /// Not a book
+    Keep,
+
+    /// Book absorbed, forward this ticker instead
+    Replace(MarketMessage),
+
+    /// Book absorbed, nothing to forward
+    Drop,
+}
+
+/// Upserts levels; a zero amount removes the level, unparsable
+/// prices are skipped.
+fn apply(side: &mut Side, levels: &[[String; 2]]) {
+    for [price, amount] in levels {
+        let Ok(key) = Decimal::from_str(price) else {
+            continue;
+        };
+
+        if Decimal::from_str(amount).is_ok_and(|a| a.is_zero()) {
+            side.remove(&key);
+        } else {
+            side.insert(key, (price.clone(), amount.clone()));
+        }
+    }
+}
+
+fn ticker(book: &BookData, (bid, ask): (Option<String>, Option<String>)) -> MarketMessage {
+    MarketMessage::Ticker(TickerData {
+        exchange: book.exchange.clone(),
+        symbol: book.symbol.clone(),
+        timestamp: book.timestamp,
+        bid,
+        ask,
+        last: None,
+        vol_24h: None,
+        source: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(snapshot: bool, asks: &[(&str, &str)], bids: &[(&str, &str)]) -> ParseResult {
+        let levels = |l: &[(&str, &str)]| l.iter().map(|(p, a)| [p.to_string(), a.to_string()]).collect();
+
+        ParseResult::Market(MarketMessage::Book(BookData {
+            exchange: "binance".into(),
+            symbol: "BTC/USDT".into(),
+            timestamp: 1,
+            asks: levels(asks),
+            bids: levels(bids),
+            source: None,
+            snapshot,
+        }))
+    }
+
+    fn levels(books: &LocalBooks) -> (Vec<String>, Vec<String>) {
+        let book = &books.books["BTC/USDT"];
+        let prices = |side: &Side| side.values().map(|(p, _)| p.clone()).collect();
+        (prices(&book.asks), prices(&book.bids))
+    }
+
+    #[test]
+    fn deltas_update_the_local_book_without_forwarding() {
+        let mut books = LocalBooks::new(false);
+
+        assert!(!books.observe(&mut book(true, &[("101", "1")], &[("99", "1")])));
+        assert!(!books.observe(&mut book(false, &[("102", "2")], &[("99", "0"), ("98", "3")])));
+
+        assert_eq!(levels(&books), (vec!["101".into(), "102".into()], vec!["98".into()]));
+    }
+
+    #[test]
+    fn reconnect_resets_and_waits_for_a_fresh_snapshot() {
+        let mut books = LocalBooks::new(false);
+
+        books.observe(&mut book(true, &[("101", "1")], &[("99", "1")]));
+        books.observe(&mut book(false, &[("102", "1")], &[]));
+
+        books.reset();
+        assert!(books.books.is_empty());
+
+        // Delta before the new snapshot: dropped
+        books.observe(&mut book(false, &[("103", "1")], &[]));
+        assert!(books.books.is_empty());
+
+        books.observe(&mut book(true, &[("104", "1")], &[("98", "1")]));
+        books.observe(&mut book(false, &[("105", "1")], &[]));
+
+        assert_eq!(levels(&books), (vec!["104".into(), "105".into()], vec!["98".into()]));
+    }
+
+    #[test]
+    fn snapshots_replace_the_local_book() {
+        let mut books = LocalBooks::new(false);
+
+        books.observe(&mut book(true, &[("101", "1"), ("102", "1")], &[("99", "1")]));
+        books.observe(&mut book(true, &[("103", "1")], &[("98", "1")]));
+
+        assert_eq!(levels(&books), (vec!["103".into()], vec!["98".into()]));
+    }
+
+    #[test]
+    fn bbo_ticker_only_when_top_of_book_changes() {
+        let mut books = LocalBooks::new(true);
+
+        let mut first = book(true, &[("101", "1")], &[("99", "1")]);
+        assert!(books.observe(&mut first));
+        assert!(matches!(
+            first,
+            ParseResult::Market(MarketMessage::Ticker(TickerData { ref bid, ref ask, .. }))
+                if bid.as_deref() == Some("99") && ask.as_deref() == Some("101")
+        ));
+
+        // Deeper level only: top unchanged
+        assert!(!books.observe(&mut book(false, &[("105", "1")], &[])));
+    }
+}
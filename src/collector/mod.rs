@@ -17,4 +17,13 @@
 /// TODO:
 /// - Add shared collector metrics (connections, reconnects, errors)
 /// - Add optional rate-limit / backoff coordination across collectors
+mod backfill;
+mod book_dedup;
+mod local_book;
+pub mod connections;
+pub mod registry;
+mod rate_cap;
+mod rest_poll;
 pub mod runner;
+pub mod stream;
+mod wildcard;
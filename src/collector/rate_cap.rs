@@ -0,0 +1,83 @@
+//! Per-symbol message rate cap (`max_msgs_per_sec`).
+//!
+//! A token bucket per (exchange, symbol): it holds up to one second
+//! of messages and refills continuously. Messages arriving with an
+//! empty bucket are dropped, so a single runaway market cannot flood
+//! the master. Buckets are shared by all connections of an exchange.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use once_cell::sync::Lazy;
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Token buckets of all capped symbols.
+#[derive(Default)]
+pub(super) struct RateCaps {
+    /// exchange → symbol → bucket
+    buckets: Mutex<HashMap<String, HashMap<String, Bucket>>>,
+}
+
+impl RateCaps {
+    /// Takes one token for `symbol` of `exchange`.
+    ///
+    /// Returns false if the symbol exceeds `max_per_sec`.
+    pub fn allow(&self, exchange: &str, symbol: &str, max_per_sec: u32) -> bool {
+        let max = f64::from(max_per_sec.max(1));
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Lookups first: no allocation on the hot path
+        if !buckets.contains_key(exchange) {
+            buckets.insert(exchange.to_string(), HashMap::new());
+        }
+        let symbols = buckets.get_mut(exchange).expect("inserted above");
+
+        if !symbols.contains_key(symbol) {
+            symbols.insert(symbol.to_string(), Bucket { tokens: max, refilled: now });
+        }
+        let bucket = symbols.get_mut(symbol).expect("inserted above");
+
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * max).min(max);
+        bucket.refilled = now;
+
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+/// Global rate caps (singleton)
+pub(super) static RATE_CAPS: Lazy<RateCaps> = Lazy::new(RateCaps::default);
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn bursts_are_capped_per_symbol_and_refill() {
+        let caps = RateCaps::default();
+        let burst = |symbol| (0..15).filter(|_| caps.allow("binance", symbol, 10)).count();
+
+        // One second worth of messages, the rest is dropped
+        assert_eq!(burst("BTC/USDT"), 10);
+        assert_eq!(burst("ETH/USDT"), 10);
+        assert!(caps.allow("okx", "BTC/USDT", 10));
+
+        // 10/s refills one token per 100ms
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(caps.allow("binance", "BTC/USDT", 10));
+    }
+}
@@ -0,0 +1,350 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::{
+    config::{ExchangeConfig, ExchangeState},
+    exchanges::{adapter::AdapterMetadata, get_adapter},
+    output::MasterRoutes,
+    metrics::METRICS,
+};
+
+use super::connections::{ConnectionInfo, CONNECTIONS};
+use super::runner::{run_exchange, ConnectionBudget, ExchangeHandle};
+use super::wildcard::expand_wildcards;
+
+/// Registry of all configured exchanges and their running collectors.
+///
+/// Purpose:
+/// - Start / stop exchanges at runtime (admin endpoint)
+/// - Keep `exchanges_active` consistent with what is running
+///
+/// Every configured exchange with a supported adapter is registered,
+/// regardless of its `enabled` flag, so it can be enabled later.
+pub struct ExchangeRegistry {
+    outputs: MasterRoutes,
+    budget: Arc<ConnectionBudget>,
+    exchanges: Mutex<Vec<RegisteredExchange>>,
+}
+
+/// Entry of `ExchangeRegistry::status`
+#[derive(Serialize)]
+pub struct ExchangeStatus {
+    pub name: String,
+    pub state: &'static str,
+    pub adapter: Option<AdapterMetadata>,
+
+    /// Connections retrying to connect for longer than
+    /// `UNHEALTHY_AFTER_MS`, with their last error
+    pub unhealthy: Vec<ConnectionInfo>,
+}
+
+struct RegisteredExchange {
+    cfg: ExchangeConfig,
+    running: Option<ExchangeHandle>,
+}
+
+impl ExchangeRegistry {
+    pub fn new(outputs: MasterRoutes, budget: ConnectionBudget) -> Self {
+        Self {
+            outputs,
+            budget: Arc::new(budget),
+            exchanges: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Registers an exchange without starting it.
+    ///
+    /// Returns false if the exchange is not supported.
+    pub async fn register(&self, cfg: ExchangeConfig) -> bool {
+        if get_adapter(&cfg.name).is_none() {
+            return false;
+        }
+
+        self.exchanges.lock().await.push(RegisteredExchange {
+            cfg,
+            running: None,
+        });
+
+        true
+    }
+
+    /// Starts every registered exchange with `enabled: true` that
+    /// is not paused.
+    ///
+    /// Returns the number of started exchange instances. Warns if
+    /// that is none: the process would otherwise look hung.
+    pub async fn start_enabled(&self) -> anyhow::Result<usize> {
+        let resolved = self
+            .resolve(|cfg| cfg.enabled && cfg.state != ExchangeState::Paused)
+            .await;
+        let started = self.start_resolved(resolved).await?;
+
+        if started == 0 {
+            tracing::warn!(
+                "WARNING: no enabled exchanges configured – no collectors started \
+                 (exchanges can still be enabled via the admin endpoint)"
+            );
+        }
+
+        Ok(started)
+    }
+
+    /// Queue depths of the dedicated master pools (`master_url`),
+    /// by master URL.
+    pub async fn dedicated_queue_depths(&self) -> std::collections::BTreeMap<String, Vec<usize>> {
+        self.outputs.dedicated_queue_depths().await
+    }
+
+    /// Returns the state of every registered exchange instance.
+    ///
+    /// STATES:
+    /// - "running"  collectors are active
+    /// - "paused"   `state: "paused"` in config, not connected
+    /// - "disabled" not running (config or admin endpoint)
+    pub async fn status(&self) -> Vec<ExchangeStatus> {
+        self.exchanges
+            .lock()
+            .await
+            .iter()
+            .map(|e| ExchangeStatus {
+                name: e.cfg.name.clone(),
+                state: if e.running.is_some() {
+                    "running"
+                } else if e.cfg.state == ExchangeState::Paused {
+                    "paused"
+                } else {
+                    "disabled"
+                },
+                adapter: get_adapter(&e.cfg.name).map(|a| a.metadata()),
+                unhealthy: CONNECTIONS.unhealthy(&e.cfg.name),
+            })
+            .collect()
+    }
+
+    /// Starts all collectors of the named exchange.
+    ///
+    /// RETURNS:
+    /// - Ok(true)  if at least one instance was started
+    /// - Ok(false) if all instances were already running
+    /// - Err       if the exchange is not registered
+    pub async fn enable(&self, name: &str) -> anyhow::Result<bool> {
+        let found = self.exchanges.lock().await.iter().any(|e| e.cfg.name == name);
+
+        if !found {
+            return Err(anyhow::anyhow!("Exchange '{}' is not registered", name));
+        }
+
+        let resolved = self.resolve(|cfg| cfg.name == name).await;
+
+        Ok(self.start_resolved(resolved).await? > 0)
+    }
+
+    /// Stops all collectors of the named exchange.
+    ///
+    /// RETURNS:
+    /// - Ok(true)  if at least one instance was stopped
+    /// - Ok(false) if nothing was running
+    /// - Err       if the exchange is not registered
+    pub async fn disable(&self, name: &str) -> anyhow::Result<bool> {
+        let mut exchanges = self.exchanges.lock().await;
+        let mut found = false;
+        let mut stopped = false;
+
+        for entry in exchanges.iter_mut().filter(|e| e.cfg.name == name) {
+            found = true;
+
+            if let Some(handle) = entry.running.take() {
+                tracing::info!("Stopping {} collector", name);
+
+                handle.stop();
+                METRICS.exchanges_active.fetch_sub(1, Ordering::Relaxed);
+                stopped = true;
+            }
+        }
+
+        if !found {
+            return Err(anyhow::anyhow!("Exchange '{}' is not registered", name));
+        }
+
+        Ok(stopped)
+    }
+
+    /// Stops every collector, then drains all outputs (shared master,
+    /// dedicated `master_url` pools, `output.sinks` queues).
+    pub async fn shutdown(&self) {
+        for entry in self.exchanges.lock().await.iter_mut() {
+            if let Some(handle) = entry.running.take() {
+                handle.stop();
+                METRICS.exchanges_active.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        self.outputs.shutdown().await;
+    }
+
+    /// Copies the configs of the matching instances that are not
+    /// running and resolves their wildcard pairs.
+    ///
+    /// The wildcard fetch is a REST call; it runs without holding the
+    /// registry lock, so /status and other admin calls don't wait on it.
+    /// Entries are never removed, so the returned indices stay valid.
+    async fn resolve(
+        &self,
+        filter: impl Fn(&ExchangeConfig) -> bool,
+    ) -> Vec<(usize, ExchangeConfig)> {
+        let pending: Vec<(usize, ExchangeConfig)> = self
+            .exchanges
+            .lock()
+            .await
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.running.is_none() && filter(&e.cfg))
+            .map(|(index, e)| (index, e.cfg.clone()))
+            .collect();
+
+        let mut resolved = Vec::with_capacity(pending.len());
+
+        for (index, mut cfg) in pending {
+            if let Some(adapter) = get_adapter(&cfg.name) {
+                expand_wildcards(adapter.as_ref(), &mut cfg).await;
+            }
+
+            resolved.push((index, cfg));
+        }
+
+        resolved
+    }
+
+    /// Starts the resolved instances that are still not running.
+    ///
+    /// Returns the number of started instances.
+    async fn start_resolved(
+        &self,
+        resolved: Vec<(usize, ExchangeConfig)>,
+    ) -> anyhow::Result<usize> {
+        let mut exchanges = self.exchanges.lock().await;
+        let mut started = 0;
+
+        for (index, cfg) in resolved {
+            if self.start(&mut exchanges[index], cfg).await? {
+                started += 1;
+            }
+        }
+
+        Ok(started)
+    }
+
+    /// Starts one registered exchange instance with its resolved
+    /// config if it is not running.
+    ///
+    /// Returns true if it was started.
+    async fn start(
+        &self,
+        entry: &mut RegisteredExchange,
+        cfg: ExchangeConfig,
+    ) -> anyhow::Result<bool> {
+        // Started by a concurrent enable while resolving
+        if entry.running.is_some() {
+            return Ok(false);
+        }
+
+        let Some(adapter) = get_adapter(&cfg.name) else {
+            return Ok(false);
+        };
+
+        tracing::info!("Starting {} collector", cfg.name);
+
+        let output = self.outputs.output_for(cfg.master_url.as_deref()).await?;
+
+        // Wildcards are already expanded, run_exchange won't fetch again
+        let handle = run_exchange(adapter, cfg, output, self.budget.clone()).await?;
+
+        entry.running = Some(handle);
+        METRICS.exchanges_active.fetch_add(1, Ordering::Relaxed);
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::recording::RecordingSink;
+
+    fn registry() -> ExchangeRegistry {
+        let master = serde_json::from_value(serde_json::json!({
+            "url": "wss://master.example", "connections": 1, "key": "k"
+        }))
+        .unwrap();
+        let outputs = MasterRoutes::new(Arc::new(RecordingSink::default()), master, false, None, false);
+
+        ExchangeRegistry::new(outputs, ConnectionBudget::new(None))
+    }
+
+    fn exchange(name: &str, enabled: bool) -> ExchangeConfig {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "enabled": enabled,
+            "pairs": { "trades": ["BTC/USDT"], "orderbooks": [] }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn nothing_starts_without_exchanges() {
+        assert_eq!(registry().start_enabled().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn nothing_starts_when_every_exchange_is_disabled() {
+        let registry = registry();
+        assert!(registry.register(exchange("binance", false)).await);
+        assert!(registry.register(exchange("okx", false)).await);
+
+        assert_eq!(registry.start_enabled().await.unwrap(), 0);
+        assert!(registry.status().await.iter().all(|s| s.state == "disabled"));
+    }
+
+    #[tokio::test]
+    async fn enable_and_disable_start_and_stop_collectors() {
+        let registry = registry();
+        // No pairs: the collector starts without dialing Binance
+        let mut cfg = exchange("binance", false);
+        cfg.pairs.trades.clear();
+        cfg.demo = Some(true);
+        assert!(registry.register(cfg).await);
+
+        // Only this test starts exchanges, so the gauge is exact
+        let active = METRICS.exchanges_active.load(Ordering::Relaxed);
+
+        assert!(registry.enable("binance").await.unwrap());
+        assert!(!registry.enable("binance").await.unwrap(), "already running");
+        assert_eq!(registry.status().await[0].state, "running");
+        assert_eq!(METRICS.exchanges_active.load(Ordering::Relaxed), active + 1);
+
+        assert!(registry.disable("binance").await.unwrap());
+        assert!(!registry.disable("binance").await.unwrap(), "already stopped");
+        assert_eq!(registry.status().await[0].state, "disabled");
+        assert_eq!(METRICS.exchanges_active.load(Ordering::Relaxed), active);
+
+        assert!(registry.enable("okx").await.is_err());
+        assert!(registry.disable("okx").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn paused_exchanges_are_reported_but_not_connected() {
+        let registry = registry();
+        let mut cfg = exchange("bitstamp", true);
+        cfg.state = serde_json::from_value(serde_json::json!("paused")).unwrap();
+        assert!(registry.register(cfg).await);
+
+        assert_eq!(registry.start_enabled().await.unwrap(), 0);
+
+        let status = registry.status().await;
+        assert_eq!(status[0].state, "paused");
+        assert!(CONNECTIONS.for_exchange("bitstamp").is_empty());
+    }
+}
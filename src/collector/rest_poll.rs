@@ -0,0 +1,231 @@
+//! REST snapshot poller.
+//!
+//! Fallback orderbook source for exchanges whose WebSocket is flaky or
+//! does not offer the channel. Each pair is polled at a fixed interval
+//! and every snapshot is forwarded as a regular `BookData` message.
+
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::task::AbortHandle;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use super::book_dedup::BookDedup;
+use super::local_book::LocalBooks;
+use super::runner::handle_parsed;
+use crate::config::{ExchangeConfig, RestPollConfig};
+use crate::exchanges::adapter::{ExchangeAdapter, ParseError, ParseResult};
+use crate::output::{OutputSink, SendMeta};
+use crate::schema::{BookData, MarketMessage};
+use crate::{net, util};
+
+/// Depth used for `{depth}` when no orderbook config is present
+pub(super) const DEFAULT_DEPTH: usize = 20;
+
+/// Spawns one poll task per orderbook pair.
+pub fn spawn_rest_pollers(
+    adapter: Arc<dyn ExchangeAdapter>,
+    cfg: &ExchangeConfig,
+    poll: &RestPollConfig,
+    output: Arc<dyn OutputSink>,
+) -> Vec<AbortHandle> {
+    let depth = cfg
+        .orderbook
+        .as_ref()
+        .map(|o| o.depth)
+        .unwrap_or(DEFAULT_DEPTH);
+
+    cfg.pairs
+        .orderbooks
+        .iter()
+        .map(|pair| {
+            let url = poll
+                .url
+                .replace("{symbol}", &util::symbol_to_exchange(adapter.name(), pair))
+                .replace("{depth}", &depth.to_string());
+
+            tracing::info!("[REST POLL] {} {} every {}ms", adapter.name(), pair, poll.interval_ms);
+
+            tokio::spawn(poll_loop(
+                adapter.name().to_string(),
+                cfg.clone(),
+                pair.clone(),
+                url,
+                Duration::from_millis(poll.interval_ms),
+                output.clone(),
+            ))
+            .abort_handle()
+        })
+        .collect()
+}
+
+async fn poll_loop(
+    exchange: String,
+    cfg: ExchangeConfig,
+    pair: String,
+    url: String,
+    every: Duration,
+    output: Arc<dyn OutputSink>,
+) {
+    let mut ticker = interval(every);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let mut dedup = cfg.orderbook.as_ref()
+        .is_some_and(|o| o.dedup)
+        .then(BookDedup::default);
+    let mut local_books = cfg.orderbook.as_ref()
+        .filter(|o| o.local_only())
+        .map(|o| LocalBooks::new(o.bbo));
+
+    loop {
+        ticker.tick().await;
+
+        let mut result = match fetch_snapshot(&url, &exchange, &pair).await {
+            Ok(Some(book)) => ParseResult::Market(MarketMessage::Book(book)),
+            Ok(None) => ParseResult::Error(ParseError::malformed("no bids / asks in REST snapshot")),
+            Err(e) => {
+                tracing::warn!("[REST POLL][{exchange}] {pair} failed: {e}");
+                continue;
+            }
+        };
+
+        if dedup.as_mut().is_some_and(|d| !d.observe(&mut result))
+            || local_books.as_mut().is_some_and(|l| !l.observe(&mut result))
+        {
+            continue;
+        }
+
+        handle_parsed(result, &cfg, output.as_ref(), SendMeta::rest(None)).await;
+    }
+}
+
+/// Fetches one orderbook snapshot of `pair`.
+///
+/// Ok(None) if the response has no recognizable bids / asks.
+pub(super) async fn fetch_snapshot(url: &str, exchange: &str, pair: &str) -> anyhow::Result<Option<BookData>> {
+    let body: Value = net::http_client()
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(parse_snapshot(&body, exchange, pair))
+}
+
+/// Extracts bids / asks from a REST orderbook snapshot.
+///
+/// Accepts the common layouts:
+/// - `{ "bids": [...], "asks": [...] }`           (Binance, Gate.io)
+/// - `{ "data": { "bids": ..., "asks": ... } }`   (KuCoin)
+/// - `{ "data": [ { "bids": ..., "asks": ... } ] }` (OKX)
+/// - `{ "result": { "PAIR": { "bids": ... } } }`  (Kraken)
+///
+/// Levels may be strings or numbers; extra entries per level
+/// (e.g. order counts) are ignored.
+fn parse_snapshot(body: &Value, exchange: &str, pair: &str) -> Option<BookData> {
+    let book = [
+        Some(body),
+        body.get("data"),
+        body.get("data").and_then(|d| d.get(0)),
+        body.get("result"),
+        body.get("result")
+            .and_then(|r| r.as_object())
+            .and_then(|r| r.values().next()),
+    ]
+    .into_iter()
+    .flatten()
+    .find(|b| b.get("bids").is_some() && b.get("asks").is_some())?;
+
+    Some(BookData {
+        exchange: exchange.to_string(),
+        symbol: pair.to_string(),
+        timestamp: util::now_ms(),
+        asks: parse_levels(&book["asks"])?,
+        bids: parse_levels(&book["bids"])?,
+        source: None,
+        snapshot: true,
+    })
+}
+
+fn parse_levels(levels: &Value) -> Option<Vec<[String; 2]>> {
+    levels
+        .as_array()?
+        .iter()
+        .map(|level| Some([level_field(level.get(0)?)?, level_field(level.get(1)?)?]))
+        .collect()
+}
+
+fn level_field(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// HTTP server answering every request with `body`.
+    async fn http_stub(body: Value) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let body = body.to_string();
+                tokio::spawn(async move {
+                    let mut request = [0u8; 4096];
+                    let _ = socket.read(&mut request).await;
+
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn snapshots_become_book_data() {
+        let url = http_stub(serde_json::json!({
+            "lastUpdateId": 1027024,
+            "bids": [["4.00000000", "431.00000000"]],
+            "asks": [["4.00000200", "12.00000000"], ["4.00000300", "1.00000000"]]
+        }))
+        .await;
+
+        let book = fetch_snapshot(&format!("{url}/api/v3/depth?symbol=BNBBTC"), "binance", "BNB/BTC")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!((book.exchange.as_str(), book.symbol.as_str()), ("binance", "BNB/BTC"));
+        assert_eq!(book.bids, [["4.00000000", "431.00000000"]].map(|l| l.map(String::from)));
+        assert_eq!(book.asks.len(), 2);
+        assert!(book.snapshot);
+    }
+
+    #[test]
+    fn nested_layouts_and_numeric_levels_are_parsed() {
+        let kraken = serde_json::json!({
+            "error": [],
+            "result": { "XXBTZUSD": { "asks": [["42001.0", "1.5", 1700000000]], "bids": [[42000, 2]] } }
+        });
+
+        let book = parse_snapshot(&kraken, "kraken", "BTC/USD").unwrap();
+        assert_eq!(book.bids, [["42000", "2"]].map(|l| l.map(String::from)));
+        assert_eq!(book.asks, [["42001.0", "1.5"]].map(|l| l.map(String::from)));
+
+        assert!(parse_snapshot(&serde_json::json!({ "code": "50011" }), "okx", "BTC/USDT").is_none());
+    }
+}
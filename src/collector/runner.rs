@@ -1,226 +1,745 @@
-use tokio_tungstenite::{connect_async, tungstenite::Message, tungstenite::Utf8Bytes};
+use tokio_tungstenite::tungstenite::{
+    self, Message, Utf8Bytes,
+    protocol::{CloseFrame, frame::coding::CloseCode},
+};
 use futures_util::{SinkExt, StreamExt};
 use std::sync::Arc;
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, sleep_until, Duration, Instant};
 use std::io::Read;
-use tokio::sync::OnceCell;
-use std::sync::atomic::Ordering;
+use tokio::task::AbortHandle;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+use crate::backoff::{ExponentialBackoff, Outcome, ReconnectPolicy};
 use crate::metrics::METRICS;
-use crate::{exchanges::adapter::{ExchangeAdapter, ChannelType, ParseResult}, master_sender::MasterPool, config::ExchangeConfig, util};
+use super::backfill::TradeBackfill;
+use super::book_dedup::BookDedup;
+use super::local_book::LocalBooks;
+use super::connections::{ConnectionEntry, CONNECTIONS};
+use super::rate_cap::RATE_CAPS;
+use super::rest_poll::{fetch_snapshot, spawn_rest_pollers, DEFAULT_DEPTH};
+use super::stream;
+use super::wildcard::expand_wildcards;
+use crate::{exchanges::adapter::{ExchangeAdapter, ChannelType, ParseErrorKind, ParseResult, SubscribeGranularity, is_subscription_limit_error}, output::{DemoSink, InFlightSink, OutputSink, SendMeta}, config::ExchangeConfig, net, util};
+use crate::schema::{BookData, MarketMessage};
 
-static KUCOIN_WS_URL: OnceCell<String> = OnceCell::const_new();
+/// Initial delay before a panicked WS loop is respawned
+const SUPERVISOR_MIN_BACKOFF: Duration = Duration::from_secs(1);
 
-async fn get_kucoin_ws_url() -> anyhow::Result<String> {
-    KUCOIN_WS_URL
-        .get_or_try_init(|| async {
-            let res: serde_json::Value = reqwest::Client::new()
-                .post("https://api.kucoin.com/api/v1/bullet-public")
-                .send()
-                .await?
-                .json()
-                .await?;
+/// Upper bound for the respawn delay of a repeatedly panicking loop
+const SUPERVISOR_MAX_BACKOFF: Duration = Duration::from_secs(60);
 
-            let token = res["data"]["token"]
-                .as_str()
-                .ok_or_else(|| anyhow::anyhow!("KuCoin token missing"))?;
+/// Reconnect delay after a read error or a failed connect
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
 
-            let endpoint = res["data"]["instanceServers"][0]["endpoint"]
-                .as_str()
-                .ok_or_else(|| anyhow::anyhow!("KuCoin endpoint missing"))?;
+/// Reconnect delay after the exchange closed the socket cleanly
+const CLEAN_CLOSE_RECONNECT_DELAY: Duration = Duration::from_secs(1);
 
-            Ok(format!("{endpoint}?token={token}"))
-        })
-        .await
-        .map(|s| s.clone())
+/// Reconnect delay after a rate-limit signal (see `rate_limit_backoff_ms`)
+const DEFAULT_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Maximum wait for the welcome frame after connecting
+/// (`ExchangeAdapter::wait_for_welcome`)
+const WELCOME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default timeout for exchange REST requests (see `rest_timeout_ms`)
+const DEFAULT_REST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Process-wide cap on spawned WS connections (`limits.max_connections`).
+///
+/// Shared by all exchanges; connections are returned to the budget
+/// when an exchange is stopped.
+pub struct ConnectionBudget {
+    max: Option<usize>,
+    used: AtomicUsize,
+}
+
+impl ConnectionBudget {
+    pub fn new(max: Option<usize>) -> Self {
+        Self {
+            max,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// Takes one connection from the budget; false if exhausted.
+    fn try_acquire(&self) -> bool {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                match self.max {
+                    Some(max) if used >= max => None,
+                    _ => Some(used + 1),
+                }
+            })
+            .is_ok()
+    }
+
+    fn release(&self, connections: usize) {
+        self.used.fetch_sub(connections, Ordering::Relaxed);
+    }
+}
+
+/// Handle to all collector tasks of one running exchange.
+///
+/// Dropping the handle does NOT stop the tasks; call `stop()`.
+pub struct ExchangeHandle {
+    tasks: Vec<AbortHandle>,
+    trade_pairs: usize,
+    orderbook_pairs: usize,
+    funding_pairs: usize,
+    connections: usize,
+    budget: Arc<ConnectionBudget>,
+}
+
+impl ExchangeHandle {
+    fn new(budget: Arc<ConnectionBudget>) -> Self {
+        Self {
+            tasks: Vec::new(),
+            trade_pairs: 0,
+            orderbook_pairs: 0,
+            funding_pairs: 0,
+            connections: 0,
+            budget,
+        }
+    }
+
+    /// Spawns one supervised WS connection for `subs`.
+    ///
+    /// If the connection budget is exhausted nothing is spawned and
+    /// the skipped pairs are logged.
+    fn spawn_ws(
+        &mut self,
+        adapter: &Arc<dyn ExchangeAdapter>,
+        cfg: &ExchangeConfig,
+        subs: Vec<Subscription>,
+        output: &Arc<dyn OutputSink>,
+    ) {
+        if !self.budget.try_acquire() {
+            tracing::warn!(
+                exchange = adapter.name(),
+                "[LIMIT][{}] max_connections reached – not subscribing {:?}",
+                adapter.name(),
+                subs
+            );
+            return;
+        }
+
+        for sub in &subs {
+            self.add_pairs(sub.channel, sub.pairs.len());
+        }
+
+        self.connections += 1;
+        self.tasks.push(spawn_supervised(
+            adapter.clone(),
+            cfg.clone(),
+            subs,
+            output.clone(),
+        ));
+    }
+
+    /// Counts active pairs in the handle and the global gauges.
+    fn add_pairs(&mut self, channel: ChannelType, count: usize) {
+        match channel {
+            ChannelType::Trades => {
+                self.trade_pairs += count;
+                METRICS.trade_pairs_active.fetch_add(count, Ordering::Relaxed);
+            }
+            ChannelType::OrderBooks => {
+                self.orderbook_pairs += count;
+                METRICS.orderbook_pairs_active.fetch_add(count, Ordering::Relaxed);
+            }
+            ChannelType::Funding => {
+                self.funding_pairs += count;
+                METRICS.funding_pairs_active.fetch_add(count, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Aborts all WS loops of this exchange and releases its pair
+    /// gauges and connection budget.
+    pub fn stop(self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+
+        METRICS
+            .trade_pairs_active
+            .fetch_sub(self.trade_pairs, Ordering::Relaxed);
+        METRICS
+            .orderbook_pairs_active
+            .fetch_sub(self.orderbook_pairs, Ordering::Relaxed);
+        METRICS
+            .funding_pairs_active
+            .fetch_sub(self.funding_pairs, Ordering::Relaxed);
+
+        self.budget.release(self.connections);
+    }
+}
+
+/// Wraps the shared output for one exchange: `demo` replaces it
+/// with stdout, `max_in_flight` caps the concurrent sends.
+fn exchange_output(name: &str, cfg: &ExchangeConfig, output: Arc<dyn OutputSink>) -> Arc<dyn OutputSink> {
+    let output: Arc<dyn OutputSink> = if cfg.demo.unwrap_or(false) {
+        tracing::info!(exchange = name, "[{}] running in DEMO mode", name);
+        Arc::new(DemoSink)
+    } else {
+        output
+    };
+
+    match cfg.max_in_flight {
+        Some(max) => Arc::new(InFlightSink::new(output, max)),
+        None => output,
+    }
 }
 
 pub async fn run_exchange(
     adapter: Arc<dyn ExchangeAdapter>,
-    cfg: ExchangeConfig,
-    master: MasterPool,
-) -> anyhow::Result<()> {
-    spawn_channel_chunks(
-        adapter.clone(),
-        cfg.clone(),
-        ChannelType::Trades,
-        master.clone(),
-    );
+    mut cfg: ExchangeConfig,
+    output: Arc<dyn OutputSink>,
+    budget: Arc<ConnectionBudget>,
+) -> anyhow::Result<ExchangeHandle> {
+    if !cfg.symbol_map.is_empty() {
+        util::set_symbol_map(adapter.name(), &cfg.symbol_map)?;
+    }
 
-    spawn_channel_chunks(
-        adapter,
-        cfg,
-        ChannelType::OrderBooks,
-        master,
-    );
+    expand_wildcards(adapter.as_ref(), &mut cfg).await;
+
+    fill_trades_per_connection(adapter.as_ref(), &mut cfg);
+
+    let output = exchange_output(adapter.name(), &cfg, output);
+
+    let mut handle = ExchangeHandle::new(budget);
+    let mut multiplex = cfg.multiplex.unwrap_or(false) && cfg.rest_poll.is_none();
+
+    if multiplex && !adapter.supports_multiplex() {
+        tracing::warn!(
+            exchange = adapter.name(),
+            "[{}] multiplex is not supported – using separate connections",
+            adapter.name()
+        );
+        multiplex = false;
+    } else if multiplex {
+        spawn_multiplexed(&mut handle, &adapter, &cfg, &output);
+    }
+
+    let supported = adapter.metadata().channels;
+
+    for &channel in ChannelType::all() {
+        let pairs = cfg.pairs.for_channel(channel);
+        if pairs.is_empty() {
+            continue;
+        }
+
+        // Trades and orderbooks share the multiplexed connections;
+        // funding streams may live on a separate endpoint
+        if multiplex && channel != ChannelType::Funding {
+            continue;
+        }
+
+        // REST polling replaces the orderbook WebSockets
+        if channel == ChannelType::OrderBooks
+            && let Some(poll) = &cfg.rest_poll
+        {
+            handle.add_pairs(channel, pairs.len());
+            handle.tasks.extend(spawn_rest_pollers(adapter.clone(), &cfg, poll, output.clone()));
+            continue;
+        }
+
+        if !supported.contains(&channel) {
+            tracing::warn!(
+                exchange = adapter.name(),
+                "[{}] {:?} not supported by the adapter – skipping {} pairs",
+                adapter.name(),
+                channel,
+                pairs.len()
+            );
+            continue;
+        }
+
+        spawn_channel_chunks(&mut handle, &adapter, &cfg, channel, &output);
+    }
+
+    Ok(handle)
+}
+
+/// Fills an unset `trades_per_connection` with the adapter default.
+fn fill_trades_per_connection(adapter: &dyn ExchangeAdapter, cfg: &mut ExchangeConfig) {
+    cfg.chunking.trades_per_connection
+        .get_or_insert_with(|| adapter.default_trades_per_connection());
+}
+
+/// Trade / funding pairs per connection: `trades_per_connection`,
+/// split further to stay within the adapter's stream limit.
+fn trades_chunk_size(adapter: &dyn ExchangeAdapter, cfg: &ExchangeConfig) -> usize {
+    let configured = cfg.chunking.trades_per_connection().max(1);
 
-    Ok(())
+    match adapter.metadata().max_streams_per_connection {
+        Some(max) => configured.min(max.max(1)),
+        None => configured,
+    }
+}
+
+/// Client ping interval: the `ping_interval_ms` override wins over
+/// the adapter default. None: no client pings.
+fn ping_interval(adapter: &dyn ExchangeAdapter, cfg: &ExchangeConfig) -> Option<Duration> {
+    cfg.ping_interval_ms
+        .map(Duration::from_millis)
+        .or_else(|| adapter.ping_interval())
+}
+
+/// Pairs of each subscribe message: one batch for all pairs, or one
+/// per pair (`SubscribeGranularity::PerSymbol`).
+fn subscribe_batches<'a>(adapter: &dyn ExchangeAdapter, pairs: &'a [String]) -> Vec<&'a [String]> {
+    match adapter.subscribe_granularity() {
+        SubscribeGranularity::Batch => vec![pairs],
+        SubscribeGranularity::PerSymbol => pairs.iter().map(std::slice::from_ref).collect(),
+    }
 }
 
 fn spawn_channel_chunks(
-    adapter: Arc<dyn ExchangeAdapter>,
-    cfg: ExchangeConfig,
+    handle: &mut ExchangeHandle,
+    adapter: &Arc<dyn ExchangeAdapter>,
+    cfg: &ExchangeConfig,
     channel: ChannelType,
-    master: MasterPool,
+    output: &Arc<dyn OutputSink>,
 ) {
     match channel {
         ChannelType::Trades => {
-            let pairs = cfg.pairs.trades.clone();
-
-            METRICS
-                .trade_pairs_active
-                .fetch_add(pairs.len(), Ordering::Relaxed);
-
-            let chunk_size = cfg.chunking.trades_per_connection;
-
-            for chunk in pairs.chunks(chunk_size) {
-                let adapter = adapter.clone();
-                let master = master.clone();
-                let cfg = cfg.clone();
-                let chunk_pairs = chunk.to_vec();
-
-                tokio::spawn(async move {
-                    run_ws_loop(
-                        adapter,
-                        cfg,
-                        ChannelType::Trades,
-                        chunk_pairs,
-                        master,
-                    )
-                        .await;
-                });
+            let chunk_size = trades_chunk_size(adapter.as_ref(), cfg);
+
+            for chunk in cfg.pairs.trades.chunks(chunk_size) {
+                handle.spawn_ws(
+                    adapter,
+                    cfg,
+                    vec![Subscription::new(ChannelType::Trades, chunk.to_vec())],
+                    output,
+                );
             }
         }
 
         ChannelType::OrderBooks => {
-            let pairs = cfg.pairs.orderbooks.clone();
-
-            METRICS
-                .orderbook_pairs_active
-                .fetch_add(pairs.len(), Ordering::Relaxed);
-
-            for pair in pairs {
-                eprintln!(
+            for pair in &cfg.pairs.orderbooks {
+                tracing::info!(
+                    exchange = adapter.name(),
                     "[ORDERBOOK] spawning WS for {} on {}",
                     pair,
                     adapter.name()
                 );
 
-                let adapter = adapter.clone();
-                let master = master.clone();
-                let cfg = cfg.clone();
-
-                tokio::spawn(async move {
-                    run_ws_loop(
-                        adapter,
-                        cfg,
-                        ChannelType::OrderBooks,
-                        vec![pair],
-                        master,
-                    )
-                        .await;
-                });
+                handle.spawn_ws(
+                    adapter,
+                    cfg,
+                    vec![Subscription::new(ChannelType::OrderBooks, vec![pair.clone()])],
+                    output,
+                );
+            }
+        }
+
+        // Low-rate streams, chunked like trades
+        ChannelType::Funding => {
+            let chunk_size = trades_chunk_size(adapter.as_ref(), cfg);
+
+            for chunk in cfg.pairs.funding.chunks(chunk_size) {
+                handle.spawn_ws(
+                    adapter,
+                    cfg,
+                    vec![Subscription::new(ChannelType::Funding, chunk.to_vec())],
+                    output,
+                );
+            }
+        }
+    }
+}
+
+/// Spawns connections carrying both trades and orderbooks.
+///
+/// Connection `i` gets the i-th trade chunk (`trades_per_connection`)
+/// and the i-th orderbook chunk (`orderbooks_per_connection`).
+fn spawn_multiplexed(
+    handle: &mut ExchangeHandle,
+    adapter: &Arc<dyn ExchangeAdapter>,
+    cfg: &ExchangeConfig,
+    output: &Arc<dyn OutputSink>,
+) {
+    let trade_chunks: Vec<&[String]> = cfg.pairs.trades
+        .chunks(trades_chunk_size(adapter.as_ref(), cfg))
+        .collect();
+    let book_chunks: Vec<&[String]> = cfg.pairs.orderbooks
+        .chunks(cfg.chunking.orderbooks_per_connection.max(1))
+        .collect();
+
+    for i in 0..trade_chunks.len().max(book_chunks.len()) {
+        let mut subs = Vec::new();
+
+        if let Some(chunk) = trade_chunks.get(i) {
+            subs.push(Subscription::new(ChannelType::Trades, chunk.to_vec()));
+        }
+        if let Some(chunk) = book_chunks.get(i) {
+            subs.push(Subscription::new(ChannelType::OrderBooks, chunk.to_vec()));
+        }
+
+        handle.spawn_ws(adapter, cfg, subs, output);
+    }
+}
+
+/// One channel subscription of a WS connection.
+#[derive(Clone, Debug)]
+struct Subscription {
+    channel: ChannelType,
+    pairs: Vec<String>,
+}
+
+impl Subscription {
+    fn new(channel: ChannelType, pairs: Vec<String>) -> Self {
+        Self { channel, pairs }
+    }
+}
+
+/// Aborts the wrapped task when dropped.
+///
+/// Ensures that aborting a supervisor also stops the WS loop it owns.
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Tracks one open exchange WebSocket in `ws_connections_active`.
+///
+/// The gauge is released on drop, so aborted or panicking loops
+/// never leave stale connections behind.
+struct ConnectionGauge;
+
+impl ConnectionGauge {
+    fn open() -> Self {
+        METRICS
+            .ws_connections_active
+            .fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for ConnectionGauge {
+    fn drop(&mut self) {
+        METRICS
+            .ws_connections_active
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Tracks whether a WS loop has ever connected.
+///
+/// Until the first successful connect the loop is counted in
+/// `ws_never_connected`, so a stream that fails from the very start
+/// is distinguishable from one that is reconnecting.
+struct FirstConnect {
+    /// Failed attempts so far; None once connected
+    attempts: Option<usize>,
+}
+
+impl FirstConnect {
+    fn pending() -> Self {
+        METRICS.ws_never_connected.fetch_add(1, Ordering::Relaxed);
+        Self { attempts: Some(0) }
+    }
+
+    fn done(&mut self) {
+        if self.attempts.take().is_some() {
+            METRICS.ws_never_connected.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records a failed attempt. Returns the number of failed
+    /// attempts while still never connected.
+    fn failed(&mut self) -> Option<usize> {
+        let attempts = self.attempts.as_mut()?;
+        *attempts += 1;
+        Some(*attempts)
+    }
+}
+
+impl Drop for FirstConnect {
+    fn drop(&mut self) {
+        self.done();
+    }
+}
+
+/// Why a WS loop iteration ended and the loop reconnects.
+#[derive(Clone, Copy)]
+enum ReconnectCause {
+    /// Close frame received from the exchange
+    CleanClose,
+    /// Read error or stream ended without close frame
+    Error,
+    /// Connect (or `connect_url` fetch) failed
+    ConnectFailed,
+    /// No frame within `idle_timeout_ms`
+    IdleTimeout,
+    /// Closed by us at `max_connection_age`
+    Recycled,
+    /// No market data within `subscribe_confirm_timeout_ms`
+    SubscribeTimeout,
+}
+
+impl ReconnectCause {
+    /// Counts the reconnect in its cause counter.
+    ///
+    /// `ws_reconnects` only counts established connections that are
+    /// lost; initial and repeated connect failures are not reconnects.
+    fn record(self) {
+        let counter = match self {
+            Self::CleanClose => &METRICS.reconnects_clean_close,
+            Self::Error => &METRICS.reconnects_error,
+            Self::ConnectFailed => &METRICS.reconnects_connect_failed,
+            Self::IdleTimeout => &METRICS.reconnects_idle_timeout,
+            Self::Recycled => &METRICS.ws_recycled,
+            Self::SubscribeTimeout => &METRICS.subscribe_timeouts,
+        };
+
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        if !matches!(self, Self::ConnectFailed) {
+            METRICS.ws_reconnects.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Delay before reconnecting.
+    ///
+    /// Errors and connect failures use the full delay; a planned close
+    /// (e.g. maintenance) reconnects quickly, a recycle immediately.
+    fn retry_delay(self) -> Duration {
+        match self {
+            Self::CleanClose => CLEAN_CLOSE_RECONNECT_DELAY,
+            Self::Recycled => Duration::ZERO,
+            Self::Error | Self::ConnectFailed | Self::IdleTimeout | Self::SubscribeTimeout => {
+                RECONNECT_DELAY
             }
         }
     }
 }
 
+/// Spawns a `run_ws_loop` under a watchdog.
+///
+/// `run_ws_loop` never returns on its own, so the inner task can only
+/// finish by panicking (e.g. an adapter bug on an unexpected frame).
+/// Without supervision that stream would be gone for good.
+///
+/// On panic the watchdog:
+/// - Logs the failing exchange / subscriptions
+/// - Increments `task_panics`
+/// - Respawns the loop after an exponential backoff
+///
+/// The backoff is reset once a respawned loop survived longer than
+/// the maximum backoff, so rare panics always recover quickly
+/// (`Outcome::Stable`).
+///
+/// Aborting the returned handle stops the supervisor and its loop.
+fn spawn_supervised(
+    adapter: Arc<dyn ExchangeAdapter>,
+    cfg: ExchangeConfig,
+    subs: Vec<Subscription>,
+    output: Arc<dyn OutputSink>,
+) -> AbortHandle {
+    let name = adapter.name();
+    let label = format!("{subs:?}");
+
+    supervise(
+        name,
+        label,
+        ExponentialBackoff::new(SUPERVISOR_MIN_BACKOFF, SUPERVISOR_MAX_BACKOFF),
+        move || run_ws_loop(adapter.clone(), cfg.clone(), subs.clone(), output.clone()),
+    )
+}
+
+/// Watchdog of `spawn_supervised`: runs `spawn_loop()` again after
+/// every panic, `backoff` apart.
+fn supervise<F, Fut>(
+    exchange: &'static str,
+    label: String,
+    mut backoff: ExponentialBackoff,
+    spawn_loop: F,
+) -> AbortHandle
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = ()> + Send + 'static,
+{
+    let supervisor = tokio::spawn(async move {
+        loop {
+            let started = Instant::now();
+
+            let task = tokio::spawn(spawn_loop());
+            let _guard = AbortOnDrop(task.abort_handle());
+
+            match task.await {
+                Ok(()) => return,
+                Err(e) if e.is_panic() => {
+                    METRICS.task_panics.fetch_add(1, Ordering::Relaxed);
+
+                    let outcome = if started.elapsed() > backoff.max() {
+                        Outcome::Stable
+                    } else {
+                        Outcome::Failed
+                    };
+                    let delay = backoff.next_delay(outcome);
+
+                    tracing::error!(
+                        exchange,
+                        "[SUPERVISOR][{}] task panicked for {} – respawn in {}s",
+                        exchange,
+                        label,
+                        delay.as_secs()
+                    );
+
+                    sleep(delay).await;
+                }
+                // Cancelled: the runtime is shutting down
+                Err(_) => return,
+            }
+        }
+    });
+
+    supervisor.abort_handle()
+}
+
 async fn run_ws_loop(
     adapter: Arc<dyn ExchangeAdapter>,
     cfg: ExchangeConfig,
-    channel: ChannelType,
-    pairs: Vec<String>,
-    master: MasterPool,
+    subs: Vec<Subscription>,
+    output: Arc<dyn OutputSink>,
 ) {
+    let channels: Vec<ChannelType> = subs.iter().map(|s| s.channel).collect();
+
+    let max_age = cfg.max_connection_age_ms
+        .map(Duration::from_millis)
+        .or_else(|| adapter.max_connection_age());
+    let idle_timeout = cfg.idle_timeout_ms.map(Duration::from_millis);
+    let confirm_timeout = cfg.subscribe_confirm_timeout_ms.map(Duration::from_millis);
+
+    // Streams carried in the URL: no subscribe messages needed
+    let subscribe_url = match subs.as_slice() {
+        [sub] => adapter.subscribe_url(sub.channel, &sub.pairs, &cfg),
+        _ => None,
+    };
+    let rest_timeout = cfg.rest_timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_REST_TIMEOUT);
+    let rate_limit_backoff = cfg.rate_limit_backoff_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_RATE_LIMIT_BACKOFF);
+
+    // Trade pairs of this connection, backfilled after reconnects
+    let backfill_pairs: Vec<String> = subs
+        .iter()
+        .filter(|s| s.channel == ChannelType::Trades)
+        .flat_map(|s| s.pairs.iter().cloned())
+        .collect();
+    let mut backfill = (cfg.backfill_trades.unwrap_or(false) && !backfill_pairs.is_empty())
+        .then(TradeBackfill::default);
+    let mut book_dedup = cfg.orderbook.as_ref()
+        .is_some_and(|o| o.dedup)
+        .then(BookDedup::default);
+    let mut local_books = cfg.orderbook.as_ref()
+        .filter(|o| o.local_only())
+        .map(|o| LocalBooks::new(o.bbo));
+    let book_pairs: Vec<String> = subs
+        .iter()
+        .filter(|s| s.channel == ChannelType::OrderBooks)
+        .flat_map(|s| s.pairs.iter().cloned())
+        .collect();
+    let book_depth = cfg.orderbook.as_ref().map_or(DEFAULT_DEPTH, |o| o.depth);
+    let tls = net::TlsSettings::from_network(cfg.network.as_ref());
+    let mut first_connect = FirstConnect::pending();
+    let conn = CONNECTIONS.register(
+        adapter.name(),
+        channels.clone(),
+        subs.iter().flat_map(|s| s.pairs.iter().cloned()).collect(),
+    );
+
     loop {
-        let ws_url = if adapter.name() == "kucoin" {
-            match get_kucoin_ws_url().await {
-                Ok(url) => url,
-                Err(e) => {
-                    eprintln!("[KUCOIN] failed to fetch WS url: {e}");
-                    sleep(Duration::from_secs(10)).await;
-                    continue;
-                }
+        let mut cause = ReconnectCause::ConnectFailed;
+        // Set on 429 handshakes, "try again later" closes and
+        // limit error frames; replaces `retry_delay`
+        let mut rate_limited = false;
+        conn.connecting();
+
+        let ws_url = match adapter.connect_url(rest_timeout).await {
+            Ok(Some(url)) => url,
+            Ok(None) => match &subscribe_url {
+                Some(url) => url.clone(),
+                None => adapter.ws_url_for(&channels).to_string(),
+            },
+            Err(e) => {
+                tracing::warn!(
+                    exchange = adapter.name(), channel = ?channels, conn_id = conn.id(),
+                    "[WS URL][{}] failed to fetch WS url: {e}",
+                    adapter.name()
+                );
+                conn.connect_failed(&format!("WS URL fetch failed: {e}"));
+                ReconnectCause::ConnectFailed.record();
+                sleep(Duration::from_secs(10)).await;
+                continue;
             }
-        } else {
-            adapter.ws_url().to_string()
         };
 
-        match connect_async(&ws_url).await {
-            Ok((ws, _)) => {
-                METRICS
-                    .ws_connections_active
-                    .fetch_add(1, Ordering::Relaxed);
+        let connected = match net::connect_ws(&ws_url, &tls, adapter.subprotocols()).await {
+            Ok(ws) if adapter.wait_for_welcome() => await_welcome(ws, adapter.as_ref()).await,
+            other => other,
+        };
+
+        match connected {
+            Ok(ws) => {
+                first_connect.done();
+                conn.connected();
+
+                if let Some(books) = &mut local_books {
+                    books.reset();
+                }
+
+                cause = ReconnectCause::Error;
+                let _gauge = ConnectionGauge::open();
+                let recycle_at = max_age.map(|age| Instant::now() + age);
 
                 let (write, mut read) = ws.split();
                 let write = Arc::new(tokio::sync::Mutex::new(write));
 
-                // ---- KUCOIN CLIENT PING LOOP ----
-                if adapter.name() == "kucoin" {
-                    let ping_interval = ws_url
-                        .split("|ping=")
-                        .nth(1)
-                        .and_then(|s| s.parse::<u64>().ok())
-                        .unwrap_or(20000);
-
+                // ---- CLIENT PING LOOP ----
+                // Stopped together with this connection.
+                let _ping_task = ping_interval(adapter.as_ref(), &cfg).map(|every| {
                     let ping_write = write.clone();
+                    let adapter = adapter.clone();
 
-                    let ping_every = Duration::from_millis(ping_interval / 2);
-
-                    tokio::spawn(async move {
+                    let task = tokio::spawn(async move {
                         loop {
-                            sleep(ping_every).await;
+                            sleep(every).await;
 
-                            let ping = serde_json::json!({
-                "type": "ping",
-                "id": util::now_ms().to_string()
-            });
+                            // Adapter JSON ping, or a WS-level ping frame
+                            let ping = match adapter.ping_message() {
+                                Some(msg) => Message::Text(Utf8Bytes::from(msg.to_string())),
+                                None => Message::Ping(Default::default()),
+                            };
 
-                            if ping_write
-                                .lock()
-                                .await
-                                .send(Message::Text(Utf8Bytes::from(ping.to_string())))
-                                .await
-                                .is_err()
-                            {
+                            if ping_write.lock().await.send(ping).await.is_err() {
                                 break;
                             }
                         }
                     });
-                }
 
+                    AbortOnDrop(task.abort_handle())
+                });
 
-                match adapter.name() {
-                    // Exchanges that require ONE subscribe per symbol
-                    "bitfinex" | "bitstamp" => {
-                        for pair in &pairs {
-                            let sub = adapter.build_subscribe_message(
-                                channel,
-                                &[pair.clone()],
-                                &cfg,
-                            );
+                // One connection may carry several channels (multiplex)
+                let subs_to_send = if subscribe_url.is_some() { &[][..] } else { &subs[..] };
 
-                            if write
-                                .lock()
-                                .await
-                                .send(Message::Text(Utf8Bytes::from(sub.to_string())))
-                                .await
-                                .is_err()
-                            {
-                                METRICS.subscription_errors.fetch_add(1, Ordering::Relaxed);
-                                METRICS
-                                    .ws_connections_active
-                                    .fetch_sub(1, Ordering::Relaxed);
-                                break;
-                            }
+                for Subscription { channel, pairs } in subs_to_send {
+                    let channel = *channel;
 
-                            METRICS.subscriptions_sent.fetch_add(1, Ordering::Relaxed);
-                        }
-                    }
+                    for batch in subscribe_batches(adapter.as_ref(), pairs) {
+                        let sub = adapter.build_subscribe_message(channel, batch, &cfg);
 
-                    // Exchanges that support batch subscribe
-                    _ => {
-                        let sub = adapter.build_subscribe_message(channel, &pairs, &cfg);
+                        if is_empty_subscribe(&sub) {
+                            continue;
+                        }
 
                         if write
                             .lock()
@@ -230,9 +749,6 @@ async fn run_ws_loop(
                             .is_err()
                         {
                             METRICS.subscription_errors.fetch_add(1, Ordering::Relaxed);
-                            METRICS
-                                .ws_connections_active
-                                .fetch_sub(1, Ordering::Relaxed);
                             break;
                         }
 
@@ -240,50 +756,166 @@ async fn run_ws_loop(
                     }
                 }
 
+                // ---- BOOK RESNAPSHOT ----
+                // Local books were reset on connect and drop deltas
+                // until a snapshot arrives; feeds sending deltas only
+                // get one via REST. Deltas queued meanwhile are applied
+                // on top, so the book is approximate until they pass.
+                if let Some(books) = &mut local_books {
+                    resnapshot_books(
+                        books,
+                        adapter.as_ref(),
+                        &book_pairs,
+                        book_depth,
+                        &cfg,
+                        output.as_ref(),
+                        conn.id(),
+                    )
+                    .await;
+                }
+
+                // ---- TRADE BACKFILL ----
+                // Live frames queue up meanwhile; duplicates of
+                // backfilled trades are dropped via `observe`.
+                if let Some(backfill) = &mut backfill {
+                    backfill
+                        .run(
+                            adapter.as_ref(),
+                            &cfg,
+                            &backfill_pairs,
+                            rest_timeout,
+                            output.as_ref(),
+                            conn.id(),
+                        )
+                        .await;
+                }
+
+                // Cleared by the first market data frame
+                let mut confirm_at = confirm_timeout.map(|t| Instant::now() + t);
+
+                loop {
+                    let idle_at = idle_timeout.map(|t| Instant::now() + t);
+
+                    let msg = tokio::select! {
+                        msg = read.next() => msg,
+
+                        // ---- SUBSCRIBE CONFIRMATION TIMEOUT ----
+                        // Subscribed, but no data: connection is wedged
+                        _ = sleep_until_opt(confirm_at) => {
+                            tracing::warn!(
+                                exchange = adapter.name(), channel = ?channels, conn_id = conn.id(),
+                                "[WS SUB TIMEOUT][{} {:?}] no data {}ms after subscribing – reconnecting",
+                                adapter.name(),
+                                channels,
+                                confirm_timeout.unwrap_or_default().as_millis()
+                            );
+
+                            cause = ReconnectCause::SubscribeTimeout;
+                            break;
+                        }
+
+                        // ---- IDLE TIMEOUT ----
+                        // Silently stalled connection → reconnect
+                        _ = sleep_until_opt(idle_at) => {
+                            tracing::warn!(
+                                exchange = adapter.name(), channel = ?channels, conn_id = conn.id(),
+                                "[WS IDLE][{} {:?}] no data for {}ms – reconnecting",
+                                adapter.name(),
+                                channels,
+                                idle_timeout.unwrap_or_default().as_millis()
+                            );
+
+                            cause = ReconnectCause::IdleTimeout;
+                            break;
+                        }
+
+                        // ---- MAX CONNECTION AGE ----
+                        // Close cleanly and reconnect immediately,
+                        // before the exchange forces a disconnect.
+                        _ = sleep_until_opt(recycle_at) => {
+                            tracing::info!(
+                                exchange = adapter.name(), channel = ?channels, conn_id = conn.id(),
+                                "[WS RECYCLE][{} {:?}] max connection age reached – reconnecting",
+                                adapter.name(),
+                                channels
+                            );
+
+                            let _ = write.lock().await.send(Message::Close(None)).await;
+
+                            cause = ReconnectCause::Recycled;
+                            break;
+                        }
+                    };
+
+                    let Some(msg) = msg else { break };
 
-                while let Some(msg) = read.next().await {
                     match msg {
                         Ok(Message::Text(text)) => {
-                            // ---- KUCOIN JSON PING HANDLING ----
-                            if adapter.name() == "kucoin" {
-                                if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) {
-                                    if v.get("type").and_then(|t| t.as_str()) == Some("ping") {
-                                        let pong = serde_json::json!({
-                                            "type": "pong",
-                                            "id": v.get("id")
-                                        });
-
-                                        let _ = write
-                                            .lock()
-                                            .await
-                                            .send(Message::Text(Utf8Bytes::from(pong.to_string())))
-                                            .await;
-
-                                        // optional metrics
-                                        // METRICS.pongs_sent.fetch_add(1, Ordering::Relaxed);
-                                        continue;
-                                    }
-                                }
+                            METRICS.bytes_received.fetch_add(text.len(), Ordering::Relaxed);
+                            conn.frame();
+
+                            // ---- JSON PING HANDLING (KuCoin) ----
+                            if let Some(pong) = adapter.pong_for(&text) {
+                                let _ = write
+                                    .lock()
+                                    .await
+                                    .send(Message::Text(Utf8Bytes::from(pong.to_string())))
+                                    .await;
+
+                                // optional metrics
+                                // METRICS.pongs_sent.fetch_add(1, Ordering::Relaxed);
+                                continue;
                             }
 
                             // ---- NORMAL MESSAGE FLOW ----
-                            handle_parsed(
-                                adapter.parse_message(&text, adapter.name()),
-                                &master,
-                            )
-                                .await;
+                            let received = Instant::now();
+                            let meta = SendMeta::live(conn.id());
+                            let mut result = adapter.parse_message(&text, adapter.name());
+                            METRICS.parse_latency.observe(received.elapsed());
+
+                            if result.is_market() {
+                                confirm_at = None;
+                                stream_up(&conn, adapter.name(), &channels);
+                            }
+                            rate_limited |= matches!(result, ParseResult::SubscriptionLimit(_));
+
+                            if backfill.as_mut().is_some_and(|b| !b.observe(&mut result))
+                                || book_dedup.as_mut().is_some_and(|d| !d.observe(&mut result))
+                                || local_books.as_mut().is_some_and(|l| !l.observe(&mut result))
+                            {
+                                continue;
+                            }
+
+                            handle_parsed(result, &cfg, output.as_ref(), meta).await;
                         }
 
                         Ok(Message::Binary(bin)) => {
+                            METRICS.bytes_received.fetch_add(bin.len(), Ordering::Relaxed);
+                            conn.frame();
+
+                            let received = Instant::now();
+                            let meta = SendMeta::live(conn.id());
                             let mut decoder = flate2::read::GzDecoder::new(&bin[..]);
                             let mut decoded = String::new();
 
                             if decoder.read_to_string(&mut decoded).is_ok() {
-                                handle_parsed(
-                                    adapter.parse_message(&decoded, adapter.name()),
-                                    &master,
-                                )
-                                    .await;
+                                let mut result = adapter.parse_message(&decoded, adapter.name());
+                                METRICS.parse_latency.observe(received.elapsed());
+
+                                if result.is_market() {
+                                    confirm_at = None;
+                                    stream_up(&conn, adapter.name(), &channels);
+                                }
+                                rate_limited |= matches!(result, ParseResult::SubscriptionLimit(_));
+
+                                if backfill.as_mut().is_some_and(|b| !b.observe(&mut result))
+                                    || book_dedup.as_mut().is_some_and(|d| !d.observe(&mut result))
+                                    || local_books.as_mut().is_some_and(|l| !l.observe(&mut result))
+                                {
+                                    continue;
+                                }
+
+                                handle_parsed(result, &cfg, output.as_ref(), meta).await;
                             }
                         }
 
@@ -296,61 +928,1064 @@ async fn run_ws_loop(
                         }
 
                         Ok(Message::Close(frame)) => {
-                            eprintln!(
+                            tracing::info!(
+                                exchange = adapter.name(), channel = ?channels, conn_id = conn.id(),
                                 "[WS CLOSE][{}] {:?}",
                                 adapter.name(),
                                 frame
                             );
+
+                            cause = ReconnectCause::CleanClose;
+                            rate_limited |= frame.as_ref().is_some_and(is_rate_limit_close);
                             break;
                         }
                         Ok(_) => {}
-                        Err(_) => break,
+                        Err(e) => {
+                            let kind = count_read_error(&e);
+                            tracing::warn!(
+                                exchange = adapter.name(), channel = ?channels, conn_id = conn.id(),
+                                "[WS READ ERROR][{} {:?}] {} error: {}",
+                                adapter.name(),
+                                channels,
+                                kind,
+                                e
+                            );
+                            break;
+                        }
                     }
                 }
-
-                METRICS
-                    .ws_connections_active
-                    .fetch_sub(1, Ordering::Relaxed);
             }
 
             Err(e) => {
-                eprintln!(
-                    "WS connect failed [{} {:?}] – retry in 5s",
-                    adapter.name(),
-                    channel
-                );
-                eprintln!("   {}", e);
+                rate_limited = is_rate_limit_response(&e);
+                conn.connect_failed(&e.to_string());
+
+                if let Some(attempts) = first_connect.failed() {
+                    tracing::warn!(
+                        exchange = adapter.name(), channel = ?channels, conn_id = conn.id(),
+                        "WS NEVER CONNECTED [{} {:?}] – attempt {} failed, retry in {}s\n   {}",
+                        adapter.name(),
+                        channels,
+                        attempts,
+                        RECONNECT_DELAY.as_secs(),
+                        e
+                    );
+                } else {
+                    tracing::warn!(
+                        exchange = adapter.name(), channel = ?channels, conn_id = conn.id(),
+                        "WS connect failed [{} {:?}] – retry in {}s\n   {}",
+                        adapter.name(),
+                        channels,
+                        RECONNECT_DELAY.as_secs(),
+                        e
+                    );
+                }
             }
         }
 
-        METRICS.ws_reconnects.fetch_add(1, Ordering::Relaxed);
-        sleep(Duration::from_secs(5)).await;
-    }
-}
+        let retry_delay = reconnect_delay(cause, rate_limited, rate_limit_backoff);
 
-async fn handle_parsed(
-    result: ParseResult,
-    master: &MasterPool,
-) {
-    match result {
-        ParseResult::Market(mm) => {
-            METRICS.trades_received.fetch_add(1, Ordering::Relaxed);
-
-            if master.send(serde_json::to_value(mm).unwrap()).await.is_ok() {
-                METRICS.trades_forwarded.fetch_add(1, Ordering::Relaxed);
-            } else {
-                METRICS.send_errors.fetch_add(1, Ordering::Relaxed);
-                METRICS.dropped_messages.fetch_add(1, Ordering::Relaxed);
-            }
-        }
+        if rate_limited {
+            tracing::warn!(
+                exchange = adapter.name(), channel = ?channels, conn_id = conn.id(),
+                "[RATE LIMIT][{} {:?}] rate limited – reconnecting in {}s",
+                adapter.name(),
+                channels,
+                rate_limit_backoff.as_secs()
+            );
 
-        ParseResult::Control => {
-            // optional:
-            // METRICS.control_messages.fetch_add(1, Ordering::Relaxed);
+            METRICS.rate_limit_backoffs.fetch_add(1, Ordering::Relaxed);
         }
 
-        ParseResult::Error => {
-            METRICS.parse_errors.fetch_add(1, Ordering::Relaxed);
+        conn.disconnected(!matches!(cause, ReconnectCause::ConnectFailed));
+        cause.record();
+        sleep(retry_delay).await;
+    }
+}
+
+/// Delay before the next connect: `rate_limit_backoff` after a
+/// rate-limit signal, otherwise the delay of the cause.
+fn reconnect_delay(cause: ReconnectCause, rate_limited: bool, rate_limit_backoff: Duration) -> Duration {
+    if rate_limited {
+        rate_limit_backoff
+    } else {
+        cause.retry_delay()
+    }
+}
+
+/// True for handshake rejections by rate limiting
+/// (HTTP 429, Binance 418 IP ban).
+fn is_rate_limit_response(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<tungstenite::Error>(),
+        Some(tungstenite::Error::Http(response)) if matches!(response.status().as_u16(), 418 | 429)
+    )
+}
+
+/// True for close frames asking to back off: code 1013
+/// ("try again later") or a reason with limit wording.
+fn is_rate_limit_close(frame: &CloseFrame) -> bool {
+    frame.code == CloseCode::Again || is_subscription_limit_error(&frame.reason)
+}
+
+/// Counts a read error as protocol error (malformed / oversized
+/// frame: likely an adapter or exchange bug) or transport error
+/// (I/O, TLS, closed socket: transient). Returns the class.
+fn count_read_error(err: &tungstenite::Error) -> &'static str {
+    use tungstenite::Error;
+
+    match err {
+        Error::Protocol(_) | Error::Capacity(_) | Error::Utf8(_) | Error::AttackAttempt => {
+            METRICS.ws_protocol_errors.fetch_add(1, Ordering::Relaxed);
+            "protocol"
+        }
+        _ => {
+            METRICS.ws_io_errors.fetch_add(1, Ordering::Relaxed);
+            "io"
+        }
+    }
+}
+
+/// Records market data on a connection; logs the first of each connect.
+fn stream_up(conn: &ConnectionEntry, exchange: &str, channels: &[ChannelType]) {
+    if conn.market_data() {
+        tracing::info!(
+            exchange, channel = ?channels, conn_id = conn.id(),
+            "[STREAM UP][{} {:?}] data flowing {}ms after connect",
+            exchange,
+            channels,
+            conn.connected_for_ms()
+        );
+    }
+}
+
+/// Seeds `books` with a REST snapshot of every pair the adapter
+/// offers one for (`book_snapshot_url`).
+///
+/// Failed pairs keep waiting for a snapshot until the next connect.
+async fn resnapshot_books(
+    books: &mut LocalBooks,
+    adapter: &dyn ExchangeAdapter,
+    pairs: &[String],
+    depth: usize,
+    cfg: &ExchangeConfig,
+    output: &dyn OutputSink,
+    conn_id: usize,
+) {
+    for pair in pairs {
+        let Some(url) = adapter.book_snapshot_url(pair, depth) else {
+            continue;
+        };
+
+        let mut result = match fetch_snapshot(&url, adapter.name(), pair).await {
+            Ok(Some(book)) => ParseResult::Market(MarketMessage::Book(book)),
+            Ok(None) => {
+                tracing::warn!(
+                    exchange = adapter.name(),
+                    "[BOOK SNAPSHOT][{}] {} no bids / asks in response",
+                    adapter.name(),
+                    pair
+                );
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!(exchange = adapter.name(), "[BOOK SNAPSHOT][{}] {} failed: {e}", adapter.name(), pair);
+                continue;
+            }
+        };
+
+        // A BBO ticker, if requested
+        if books.observe(&mut result) {
+            handle_parsed(result, cfg, output, SendMeta::rest(Some(conn_id))).await;
+        }
+    }
+}
+
+/// Reads frames until the adapter's welcome frame arrives
+/// (`wait_for_welcome`).
+///
+/// A close, read error or no welcome within `WELCOME_TIMEOUT` fails
+/// the connect, so the usual retry applies.
+async fn await_welcome(mut ws: net::WsStream, adapter: &dyn ExchangeAdapter) -> anyhow::Result<net::WsStream> {
+    let deadline = Instant::now() + WELCOME_TIMEOUT;
+
+    loop {
+        let frame = tokio::time::timeout_at(deadline, ws.next())
+            .await
+            .map_err(|_| anyhow::anyhow!("no welcome frame within {}s", WELCOME_TIMEOUT.as_secs()))?;
+
+        match frame {
+            Some(Ok(Message::Text(text))) if adapter.is_welcome(&text) => return Ok(ws),
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(e.into()),
+            None => return Err(anyhow::anyhow!("closed before the welcome frame")),
+        }
+    }
+}
+
+/// Sleeps until `deadline`, or forever if there is none.
+async fn sleep_until_opt(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Returns true for placeholder subscribe messages (`{}`).
+///
+/// Adapters return an empty object for empty pair slices and for
+/// channels they do not support. Those must never be sent to the
+/// exchange.
+fn is_empty_subscribe(sub: &serde_json::Value) -> bool {
+    sub.as_object().is_some_and(|o| o.is_empty())
+}
+
+/// Routes one parse result: forwards market data to `output` (and
+/// the in-process `stream`) and updates the metrics.
+///
+/// Takes any `OutputSink`, so the whole parse → forward path can be
+/// driven with a recording sink instead of a live master pool.
+/// `meta` travels with every forwarded message.
+pub(super) async fn handle_parsed(
+    result: ParseResult,
+    cfg: &ExchangeConfig,
+    output: &dyn OutputSink,
+    meta: SendMeta,
+) {
+    match result {
+        ParseResult::Market(mm) => forward(mm, cfg, output, meta).await,
+
+        ParseResult::Batch(batch) => {
+            for mm in batch {
+                forward(mm, cfg, output, meta).await;
+            }
+        }
+
+        ParseResult::Control => {
+            METRICS.control_messages.fetch_add(1, Ordering::Relaxed);
+        }
+
+        ParseResult::Ignored => {
+            METRICS.ignored_messages.fetch_add(1, Ordering::Relaxed);
+        }
+
+        // Rare and actionable: always logged
+        ParseResult::Error(err) if err.kind == ParseErrorKind::Subscription => {
+            METRICS.subscription_errors.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(exchange = %cfg.name, "[SUB ERROR][{}] {}", cfg.name, err);
+        }
+
+        ParseResult::Error(err) => {
+            // First few, then every 1000th: enough to spot a protocol change
+            let count = METRICS.parse_errors.fetch_add(1, Ordering::Relaxed);
+            if count < 10 || count.is_multiple_of(1_000) {
+                tracing::warn!(
+                    exchange = %cfg.name,
+                    "[PARSE][{}] {} (parse errors: {})",
+                    cfg.name,
+                    err,
+                    count + 1
+                );
+            }
+        }
+
+        ParseResult::SubscriptionLimit(msg) => {
+            METRICS.subscription_limit_errors.fetch_add(1, Ordering::Relaxed);
+
+            tracing::warn!(
+                exchange = %cfg.name,
+                "[SUB LIMIT][{}] {} – consider a smaller trades_per_connection (currently {})",
+                cfg.name,
+                msg,
+                cfg.chunking.trades_per_connection()
+            );
+        }
+    }
+}
+
+/// Normalizes one market message and forwards it to `output`.
+async fn forward(mut mm: MarketMessage, cfg: &ExchangeConfig, output: &dyn OutputSink, meta: SendMeta) {
+    let parsed = Instant::now();
+    METRICS.trades_received.fetch_add(1, Ordering::Relaxed);
+
+    if let Some(max) = cfg.max_msgs_per_sec
+        && !RATE_CAPS.allow(mm.exchange(), mm.symbol(), max)
+    {
+        METRICS.rate_capped.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+
+    // REST data (backfill, snapshots) is old by design, not skewed
+    if meta.live {
+        let skew = util::now_ms() - mm.timestamp();
+        if let Some(avg) = METRICS.clock_skew.record(mm.exchange(), skew) {
+            tracing::warn!(
+                exchange = mm.exchange(),
+                "[CLOCK SKEW][{}] local clock - exchange timestamps averages {}ms – check NTP",
+                mm.exchange(),
+                avg
+            );
+        }
+    }
+
+    if let MarketMessage::Book(book) = &mut mm {
+        book.normalize();
+
+        if let Some(depth) = cfg.orderbook.as_ref().and_then(|o| o.forward_depth) {
+            truncate_book(book, depth);
+        }
+    }
+
+    if let MarketMessage::Trade(trade) = &mut mm
+        && !cfg.event_time.unwrap_or(false)
+    {
+        trade.event_ts = None;
+    }
+
+    mm.set_source(cfg.source_tag());
+
+    stream::publish(&mm);
+
+    if output.send(&mm, meta).await.is_ok() {
+        METRICS.trades_forwarded.fetch_add(1, Ordering::Relaxed);
+        METRICS.forward_latency.observe(parsed.elapsed());
+    } else {
+        METRICS.send_errors.fetch_add(1, Ordering::Relaxed);
+        METRICS.dropped_messages.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Sorts a book (asks ascending, bids descending) and keeps the best
+/// `depth` levels per side.
+///
+/// Levels with unparsable prices sort last.
+fn truncate_book(book: &mut BookData, depth: usize) {
+    let price = |level: &[String; 2], missing: f64| level[0].parse::<f64>().unwrap_or(missing);
+
+    book.asks.sort_by(|a, b| price(a, f64::INFINITY).total_cmp(&price(b, f64::INFINITY)));
+    book.bids.sort_by(|a, b| price(b, f64::NEG_INFINITY).total_cmp(&price(a, f64::NEG_INFINITY)));
+
+    book.asks.truncate(depth);
+    book.bids.truncate(depth);
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+    use tokio_tungstenite::{accept_async, WebSocketStream};
+
+    use super::*;
+    use crate::exchanges::adapter::AdapterMetadata;
+    use crate::output::recording::RecordingSink;
+
+    /// Held by tests running WS loops and by tests asserting exact
+    /// deltas of the global reconnect / read error counters those
+    /// loops move.
+    static COUNTERS: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    /// Adapter talking to a `mock_server`.
+    ///
+    /// Subscribes with `{"subscribe": pairs}`; a `trade` frame parses
+    /// as a trade, any other frame as an ack.
+    struct Local {
+        url: &'static str,
+        welcome: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl ExchangeAdapter for Local {
+        fn name(&self) -> &'static str {
+            "local"
+        }
+
+        fn ws_url(&self) -> &'static str {
+            self.url
+        }
+
+        fn metadata(&self) -> AdapterMetadata {
+            AdapterMetadata {
+                name: self.name(),
+                ws_url: self.ws_url(),
+                channels: &[ChannelType::Trades],
+                auth_required: false,
+                max_streams_per_connection: None,
+            }
+        }
+
+        fn build_subscribe_message(&self, _: ChannelType, pairs: &[String], _: &ExchangeConfig) -> serde_json::Value {
+            serde_json::json!({ "subscribe": pairs })
+        }
+
+        fn parse_message(&self, raw: &str, exchange: &str) -> ParseResult {
+            if raw == "trade" { trade(exchange, util::now_ms()) } else { ParseResult::Control }
+        }
+
+        fn wait_for_welcome(&self) -> bool {
+            self.welcome
+        }
+
+        fn is_welcome(&self, raw: &str) -> bool {
+            raw == "welcome"
+        }
+    }
+
+    /// Local WS server; yields every accepted connection.
+    async fn mock_server() -> (&'static str, mpsc::UnboundedReceiver<WebSocketStream<TcpStream>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((tcp, _)) = listener.accept().await {
+                if let Ok(ws) = accept_async(tcp).await
+                    && tx.send(ws).is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        (Box::leak(url.into_boxed_str()), rx)
+    }
+
+    /// Runs a WS loop for the trades of BTC/USDT against `adapter`;
+    /// aborted when the returned guard is dropped.
+    fn spawn_loop(adapter: Local, cfg: ExchangeConfig) -> AbortOnDrop {
+        let subs = vec![Subscription::new(ChannelType::Trades, vec!["BTC/USDT".to_string()])];
+        let output: Arc<dyn OutputSink> = Arc::new(RecordingSink::default());
+
+        AbortOnDrop(tokio::spawn(run_ws_loop(Arc::new(adapter), cfg, subs, output)).abort_handle())
+    }
+
+    /// Next text frame from the collector; None once it closed.
+    async fn next_text(ws: &mut WebSocketStream<TcpStream>) -> Option<String> {
+        loop {
+            match tokio::time::timeout(Duration::from_secs(5), ws.next()).await.expect("no frame within 5s") {
+                Some(Ok(Message::Text(text))) => return Some(text.to_string()),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return None,
+                Some(Ok(_)) => continue,
+            }
+        }
+    }
+
+    /// Waits up to 5s for `done`.
+    async fn eventually(done: impl Fn() -> bool) {
+        for _ in 0..500 {
+            if done() {
+                return;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not reached within 5s");
+    }
+
+    fn exchange_cfg(name: &str) -> ExchangeConfig {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "enabled": true,
+            "pairs": { "trades": ["BTC/USDT"], "orderbooks": [] }
+        }))
+        .unwrap()
+    }
+
+    fn trade(exchange: &str, timestamp: i64) -> ParseResult {
+        ParseResult::Market(
+            serde_json::from_value(serde_json::json!({
+                "type": "trade", "exchange": exchange, "symbol": "BTC/USDT",
+                "timestamp": timestamp, "price": "1", "amount": "1", "side": "buy",
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn demo_exchanges_bypass_the_shared_output() {
+        let shared = Arc::new(RecordingSink::default());
+        let demo_cfg = ExchangeConfig { demo: Some(true), ..exchange_cfg("binance") };
+        let live_cfg = exchange_cfg("okx");
+
+        let demo = exchange_output("binance", &demo_cfg, shared.clone());
+        let live = exchange_output("okx", &live_cfg, shared.clone());
+
+        for (output, exchange) in [(demo, "binance"), (live, "okx")] {
+            let ParseResult::Market(msg) = trade(exchange, 1) else { unreachable!() };
+            output.send(&msg, SendMeta::rest(None)).await.unwrap();
+        }
+
+        let exchanges: Vec<_> = shared.messages().iter().map(|m| m.exchange().to_string()).collect();
+        assert_eq!(exchanges, ["okx"]);
+    }
+
+    #[test]
+    fn chunks_are_split_at_the_stream_limit() {
+        let cfg = |per_connection: usize| ExchangeConfig {
+            chunking: crate::config::ExchangeChunking {
+                trades_per_connection: Some(per_connection),
+                ..exchange_cfg("binance").chunking
+            },
+            ..exchange_cfg("binance")
+        };
+        let binance = crate::exchanges::binance::BinanceAdapter;
+
+        assert_eq!(trades_chunk_size(&binance, &cfg(2000)), 1024);
+        assert_eq!(trades_chunk_size(&binance, &cfg(100)), 100);
+        assert_eq!(trades_chunk_size(&Local { url: "ws://unused", welcome: false }, &cfg(2000)), 2000);
+    }
+
+    #[test]
+    fn placeholder_subscribes_are_not_sent() {
+        assert!(is_empty_subscribe(&serde_json::json!({})));
+        assert!(!is_empty_subscribe(&serde_json::json!({ "op": "subscribe", "args": [] })));
+        assert!(!is_empty_subscribe(&serde_json::json!([])));
+    }
+
+    #[tokio::test]
+    async fn panicking_loops_are_respawned() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (alive_tx, mut alive_rx) = tokio::sync::mpsc::unbounded_channel();
+        let panics_before = METRICS.task_panics.load(Ordering::Relaxed);
+
+        let backoff = ExponentialBackoff::new(Duration::from_millis(1), Duration::from_millis(5));
+        let supervisor = supervise("test", "panicking loop".into(), backoff, {
+            let runs = runs.clone();
+            move || {
+                let run = runs.fetch_add(1, Ordering::SeqCst);
+                let alive = alive_tx.clone();
+                async move {
+                    if run < 2 {
+                        panic!("loop bug #{run}");
+                    }
+                    let _ = alive.send(run);
+                    std::future::pending::<()>().await;
+                }
+            }
+        });
+
+        let run = tokio::time::timeout(Duration::from_secs(5), alive_rx.recv()).await.unwrap();
+        supervisor.abort();
+
+        assert_eq!(run, Some(2));
+        assert!(METRICS.task_panics.load(Ordering::Relaxed) >= panics_before + 2);
+    }
+
+    #[test]
+    fn reconnects_are_counted_by_cause() {
+        let _counters = COUNTERS.blocking_lock();
+        let counters = || {
+            [
+                &METRICS.reconnects_clean_close,
+                &METRICS.reconnects_error,
+                &METRICS.reconnects_connect_failed,
+                &METRICS.reconnects_idle_timeout,
+                &METRICS.ws_recycled,
+                &METRICS.subscribe_timeouts,
+            ]
+            .map(|c| c.load(Ordering::Relaxed))
+        };
+        let before = counters();
+        let reconnects = METRICS.ws_reconnects.load(Ordering::Relaxed);
+
+        for cause in [
+            ReconnectCause::CleanClose,
+            ReconnectCause::Error,
+            ReconnectCause::Error,
+            ReconnectCause::ConnectFailed,
+            ReconnectCause::IdleTimeout,
+            ReconnectCause::Recycled,
+            ReconnectCause::SubscribeTimeout,
+        ] {
+            cause.record();
+        }
+
+        let after = counters();
+        let deltas: Vec<_> = after.iter().zip(before).map(|(a, b)| a - b).collect();
+        assert_eq!(deltas, [1, 2, 1, 1, 1, 1]);
+
+        // Everything but the connect failure lost an established connection
+        assert_eq!(METRICS.ws_reconnects.load(Ordering::Relaxed) - reconnects, 6);
+    }
+
+    #[test]
+    fn clean_closes_reconnect_faster_than_errors() {
+        let delay = |cause: ReconnectCause| cause.retry_delay();
+
+        assert!(delay(ReconnectCause::CleanClose) < delay(ReconnectCause::Error));
+        assert_eq!(delay(ReconnectCause::CleanClose), CLEAN_CLOSE_RECONNECT_DELAY);
+        assert_eq!(delay(ReconnectCause::Recycled), Duration::ZERO);
+
+        for cause in [
+            ReconnectCause::Error,
+            ReconnectCause::ConnectFailed,
+            ReconnectCause::IdleTimeout,
+            ReconnectCause::SubscribeTimeout,
+        ] {
+            assert_eq!(delay(cause), RECONNECT_DELAY);
+        }
+    }
+
+    #[test]
+    fn rate_limit_signals_are_recognized() {
+        let handshake = |status: u16| -> anyhow::Error {
+            let response = tungstenite::http::Response::builder().status(status).body(None).unwrap();
+            tungstenite::Error::Http(Box::new(response)).into()
+        };
+
+        assert!(is_rate_limit_response(&handshake(429)));
+        assert!(is_rate_limit_response(&handshake(418)));
+        assert!(!is_rate_limit_response(&handshake(503)));
+        assert!(!is_rate_limit_response(&anyhow::anyhow!("connection refused")));
+
+        let close = |code: CloseCode, reason: &str| CloseFrame { code, reason: reason.into() };
+
+        assert!(is_rate_limit_close(&close(CloseCode::Again, "")));
+        assert!(is_rate_limit_close(&close(CloseCode::Policy, "Too many requests")));
+        assert!(!is_rate_limit_close(&close(CloseCode::Normal, "maintenance")));
+    }
+
+    #[test]
+    fn rate_limits_select_the_rate_limit_backoff() {
+        let backoff = Duration::from_secs(60);
+
+        assert_eq!(reconnect_delay(ReconnectCause::Error, true, backoff), backoff);
+        assert_eq!(reconnect_delay(ReconnectCause::CleanClose, true, backoff), backoff);
+        assert_eq!(reconnect_delay(ReconnectCause::ConnectFailed, true, backoff), backoff);
+        assert_eq!(reconnect_delay(ReconnectCause::Error, false, backoff), RECONNECT_DELAY);
+    }
+
+    #[tokio::test]
+    async fn acked_subscriptions_without_data_are_recycled() {
+        let _counters = COUNTERS.lock().await;
+        let timeouts = METRICS.subscribe_timeouts.load(Ordering::Relaxed);
+        let (url, mut server) = mock_server().await;
+
+        let mut cfg = exchange_cfg("local");
+        cfg.subscribe_confirm_timeout_ms = Some(100);
+        let _ws_loop = spawn_loop(Local { url, welcome: false }, cfg);
+
+        let mut ws = server.recv().await.unwrap();
+        assert_eq!(next_text(&mut ws).await.unwrap(), r#"{"subscribe":["BTC/USDT"]}"#);
+        ws.send(Message::Text("ack".into())).await.unwrap();
+
+        // Acks are not data: the collector gives up on the connection
+        assert_eq!(next_text(&mut ws).await, None);
+        eventually(|| METRICS.subscribe_timeouts.load(Ordering::Relaxed) == timeouts + 1).await;
+    }
+
+    #[tokio::test]
+    async fn exchange_and_master_bytes_are_counted() {
+        let _counters = COUNTERS.lock().await;
+        let received = METRICS.bytes_received.load(Ordering::Relaxed);
+        let sent = METRICS.bytes_sent_master.load(Ordering::Relaxed);
+
+        let (master_url, mut master_server) = mock_server().await;
+        let master_cfg = serde_json::from_value(serde_json::json!({ "url": master_url, "connections": 1, "key": "k" })).unwrap();
+        let master = crate::master_sender::MasterPool::new(&master_cfg, false).await.unwrap();
+
+        let (url, mut server) = mock_server().await;
+        let subs = vec![Subscription::new(ChannelType::Trades, vec!["BTC/USDT".to_string()])];
+        let _ws_loop = AbortOnDrop(
+            tokio::spawn(run_ws_loop(Arc::new(Local { url, welcome: false }), exchange_cfg("local"), subs, Arc::new(master)))
+                .abort_handle(),
+        );
+
+        let mut ws = server.recv().await.unwrap();
+        assert!(next_text(&mut ws).await.is_some());
+        ws.send(Message::Text("trade".into())).await.unwrap();
+
+        let mut master_ws = master_server.recv().await.unwrap();
+        assert!(next_text(&mut master_ws).await.unwrap().starts_with("key=k"));
+        // The master's keepalive pings are not counted
+        let forwarded = loop {
+            let text = next_text(&mut master_ws).await.unwrap();
+            if text != r#"{"op":"ping"}"# {
+                break text;
+            }
+        };
+
+        assert_eq!(METRICS.bytes_received.load(Ordering::Relaxed), received + "trade".len());
+        assert!(forwarded.contains(r#""type":"trade""#));
+        eventually(|| METRICS.bytes_sent_master.load(Ordering::Relaxed) == sent + forwarded.len()).await;
+    }
+
+    #[tokio::test]
+    async fn connections_past_the_max_age_are_recycled() {
+        let _counters = COUNTERS.lock().await;
+        let recycled = METRICS.ws_recycled.load(Ordering::Relaxed);
+        let (url, mut server) = mock_server().await;
+
+        let mut cfg = exchange_cfg("local");
+        cfg.max_connection_age_ms = Some(100);
+        let _ws_loop = spawn_loop(Local { url, welcome: false }, cfg);
+
+        let mut first = server.recv().await.unwrap();
+        assert!(next_text(&mut first).await.is_some());
+
+        // Closed by the collector, replaced right away
+        assert_eq!(next_text(&mut first).await, None);
+        let mut second = tokio::time::timeout(Duration::from_secs(1), server.recv()).await.unwrap().unwrap();
+        assert!(next_text(&mut second).await.is_some());
+
+        eventually(|| METRICS.ws_recycled.load(Ordering::Relaxed) > recycled).await;
+    }
+
+    #[tokio::test]
+    async fn connections_beyond_the_budget_are_not_spawned() {
+        let _counters = COUNTERS.lock().await;
+        let (url, _server) = mock_server().await;
+        let budget = Arc::new(ConnectionBudget::new(Some(2)));
+
+        let mut cfg = exchange_cfg("local");
+        cfg.pairs.trades = ["BTC/USDT", "ETH/USDT", "SOL/USDT"].map(String::from).to_vec();
+        cfg.chunking.trades_per_connection = Some(1);
+
+        let output: Arc<dyn OutputSink> = Arc::new(RecordingSink::default());
+        let handle = run_exchange(Arc::new(Local { url, welcome: false }), cfg, output, budget.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(handle.connections, 2);
+        assert_eq!(handle.tasks.len(), 2);
+        assert_eq!(handle.trade_pairs, 2);
+        assert!(!budget.try_acquire());
+
+        // Stopping returns the connections to the budget
+        handle.stop();
+        assert_eq!(budget.used.load(Ordering::Relaxed), 0);
+        assert!(budget.try_acquire());
+    }
+
+    #[tokio::test]
+    async fn subscribes_wait_for_the_welcome_frame() {
+        let _counters = COUNTERS.lock().await;
+        let (url, mut server) = mock_server().await;
+        let _ws_loop = spawn_loop(Local { url, welcome: true }, exchange_cfg("local"));
+
+        let mut ws = server.recv().await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(300), ws.next()).await.is_err());
+
+        ws.send(Message::Text("hello".into())).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(300), ws.next()).await.is_err());
+
+        ws.send(Message::Text("welcome".into())).await.unwrap();
+        assert_eq!(next_text(&mut ws).await.unwrap(), r#"{"subscribe":["BTC/USDT"]}"#);
+    }
+
+    #[test]
+    fn read_errors_are_classified_and_counted() {
+        use tungstenite::error::{CapacityError, ProtocolError};
+
+        let _counters = COUNTERS.blocking_lock();
+        let counts = || {
+            (METRICS.ws_protocol_errors.load(Ordering::Relaxed), METRICS.ws_io_errors.load(Ordering::Relaxed))
+        };
+        let (protocol, io) = counts();
+
+        let errors = [
+            tungstenite::Error::Protocol(ProtocolError::ResetWithoutClosingHandshake),
+            tungstenite::Error::Capacity(CapacityError::MessageTooLong { size: 2 << 20, max_size: 1 << 20 }),
+            tungstenite::Error::Io(std::io::ErrorKind::ConnectionReset.into()),
+        ];
+        let kinds: Vec<_> = errors.iter().map(count_read_error).collect();
+
+        assert_eq!(kinds, ["protocol", "protocol", "io"]);
+        assert_eq!(counts(), (protocol + 2, io + 1));
+    }
+
+    #[tokio::test]
+    async fn binance_frames_reach_the_sink_normalized() {
+        let adapter = crate::exchanges::get_adapter("binance").unwrap();
+        let cfg = exchange_cfg("binance");
+        let sink = RecordingSink::default();
+
+        let frames = [
+            r#"{"stream":"btcusdt@trade","data":{"e":"trade","E":1700000000005,"s":"BTCUSDT","t":12345,
+                "p":"42000.10","q":"0.500","T":1700000000001,"m":true}}"#,
+            r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1700000000010,"s":"BTCUSDT",
+                "U":1,"u":2,"b":[["41999.00","1.0"],["41999.00","2.0"]],"a":[["42001.00","0.00000000"]]}}"#,
+            r#"{"result":null,"id":1}"#,
+        ];
+
+        for frame in frames {
+            handle_parsed(adapter.parse_message(frame, "binance"), &cfg, &sink, SendMeta::live(3)).await;
+        }
+
+        let sent: Vec<_> = sink.messages().iter().map(|m| serde_json::to_value(m).unwrap()).collect();
+        assert_eq!(
+            sent,
+            [
+                serde_json::json!({
+                    "type": "trade", "exchange": "binance", "symbol": "BTC/USDT",
+                    "timestamp": 1_700_000_000_001_i64, "price": "42000.10", "amount": "0.500",
+                    "side": "sell", "trade_id": "12345", "source": "binance",
+                }),
+                serde_json::json!({
+                    "type": "book", "exchange": "binance", "symbol": "BTC/USDT",
+                    "timestamp": 1_700_000_000_010_i64,
+                    "asks": [["42001.00", "0.00000000"]],
+                    "bids": [["41999.00", "2.0"]],
+                    "source": "binance",
+                }),
+            ]
+        );
+        assert!(sink.metas().iter().all(|m| m.conn_id == Some(3)));
+    }
+
+    #[tokio::test]
+    async fn books_are_cut_to_the_forward_depth() {
+        let mut cfg = exchange_cfg("depth-test");
+        cfg.orderbook = Some(
+            serde_json::from_value(serde_json::json!({
+                "depth": 100, "update_interval_ms": 100, "forward_depth": 10
+            }))
+            .unwrap(),
+        );
+        let sink = RecordingSink::default();
+
+        // 100 levels per side, worst first
+        let asks: Vec<_> = (1..=100).rev().map(|p| [p.to_string(), "1".to_string()]).collect();
+        let bids: Vec<_> = (1..=100).map(|p| [p.to_string(), "1".to_string()]).collect();
+        let book = serde_json::from_value(serde_json::json!({
+            "type": "book", "exchange": "depth-test", "symbol": "BTC/USDT",
+            "timestamp": 1, "asks": asks, "bids": bids,
+        }))
+        .unwrap();
+
+        handle_parsed(ParseResult::Market(book), &cfg, &sink, SendMeta::rest(None)).await;
+
+        let MarketMessage::Book(sent) = &sink.messages()[0] else {
+            panic!("expected a book");
+        };
+        let prices = |levels: &[[String; 2]]| levels.iter().map(|l| l[0].clone()).collect::<Vec<_>>();
+
+        assert_eq!(prices(&sent.asks), (1..=10).map(|p| p.to_string()).collect::<Vec<_>>());
+        assert_eq!(prices(&sent.bids), (91..=100).rev().map(|p| p.to_string()).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn event_time_is_forwarded_only_when_enabled() {
+        let trade_with_event_time = || -> MarketMessage {
+            serde_json::from_value(serde_json::json!({
+                "type": "trade", "exchange": "binance", "symbol": "BTC/USDT", "timestamp": 1,
+                "price": "1", "amount": "1", "side": "buy", "event_ts": 1_700_000_000_005_i64,
+            }))
+            .unwrap()
+        };
+        let event_ts = |sink: &RecordingSink| match &sink.messages()[0] {
+            MarketMessage::Trade(trade) => trade.event_ts,
+            _ => panic!("expected a trade"),
+        };
+
+        let (stripped, kept) = (RecordingSink::default(), RecordingSink::default());
+        let enabled = ExchangeConfig { event_time: Some(true), ..exchange_cfg("binance") };
+
+        forward(trade_with_event_time(), &exchange_cfg("binance"), &stripped, SendMeta::rest(None)).await;
+        forward(trade_with_event_time(), &enabled, &kept, SendMeta::rest(None)).await;
+
+        assert_eq!(event_ts(&stripped), None);
+        assert_eq!(event_ts(&kept), Some(1_700_000_000_005));
+    }
+
+    #[tokio::test]
+    async fn forwarded_messages_carry_the_source_tag() {
+        let source = |sink: &RecordingSink| serde_json::to_value(&sink.messages()[0]).unwrap()["source"].clone();
+
+        let (by_name, tagged) = (RecordingSink::default(), RecordingSink::default());
+        let tagged_cfg = ExchangeConfig { source_tag: Some("binance-eu".into()), ..exchange_cfg("binance") };
+
+        let ParseResult::Market(mm) = trade("binance", 1) else { unreachable!() };
+        forward(mm, &exchange_cfg("binance"), &by_name, SendMeta::rest(None)).await;
+        let ParseResult::Market(mm) = trade("binance", 1) else { unreachable!() };
+        forward(mm, &tagged_cfg, &tagged, SendMeta::rest(None)).await;
+
+        assert_eq!(source(&by_name), "binance");
+        assert_eq!(source(&tagged), "binance-eu");
+    }
+
+    #[tokio::test]
+    async fn forwarded_messages_reach_stream_subscribers() {
+        let mut rx = stream::subscribe();
+        let ParseResult::Market(mm) = trade("stream-test", 1) else { unreachable!() };
+
+        forward(mm, &exchange_cfg("stream-test"), &RecordingSink::default(), SendMeta::rest(None)).await;
+
+        // Other tests forward concurrently on the same stream
+        loop {
+            let received = rx.recv().await.unwrap();
+            if received.exchange() == "stream-test" {
+                assert_eq!(received.symbol(), "BTC/USDT");
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn clock_skew_is_sampled_from_live_frames_only() {
+        let sink = RecordingSink::default();
+        let hour_ago = util::now_ms() - 3_600_000;
+
+        for exchange in ["skew-rest", "skew-live"] {
+            let cfg = exchange_cfg(exchange);
+            let meta = if exchange == "skew-live" { SendMeta::live(1) } else { SendMeta::rest(Some(1)) };
+
+            handle_parsed(trade(exchange, hour_ago), &cfg, &sink, meta).await;
+        }
+
+        // Both forwarded with their context, only the live one counted as skew
+        assert_eq!(sink.messages().len(), 2);
+        assert_eq!(sink.metas().iter().map(|m| m.live).collect::<Vec<_>>(), [false, true]);
+
+        let skew = METRICS.clock_skew.snapshot();
+        assert!(!skew.contains_key("skew-rest"));
+        assert!(skew["skew-live"] >= 3_600_000);
+    }
+
+    #[test]
+    fn configured_ping_intervals_override_the_adapter_default() {
+        let kucoin = crate::exchanges::get_adapter("kucoin").unwrap();
+        let binance = crate::exchanges::get_adapter("binance").unwrap();
+
+        let default = exchange_cfg("kucoin");
+        assert_eq!(ping_interval(kucoin.as_ref(), &default), kucoin.ping_interval());
+        assert!(kucoin.ping_interval().is_some());
+        assert_eq!(ping_interval(binance.as_ref(), &exchange_cfg("binance")), None);
+
+        let mut configured = exchange_cfg("binance");
+        configured.ping_interval_ms = Some(5_000);
+        assert_eq!(ping_interval(binance.as_ref(), &configured), Some(Duration::from_secs(5)));
+
+        configured.name = "kucoin".into();
+        assert_eq!(ping_interval(kucoin.as_ref(), &configured), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn per_symbol_adapters_subscribe_each_pair_separately() {
+        let pairs = ["BTC/USD".to_string(), "ETH/USD".to_string(), "XRP/USD".to_string()];
+
+        for name in ["bitfinex", "bitstamp"] {
+            let adapter = crate::exchanges::get_adapter(name).unwrap();
+            let batches = subscribe_batches(adapter.as_ref(), &pairs);
+
+            assert_eq!(batches.len(), pairs.len(), "{name}");
+            assert!(batches.iter().zip(&pairs).all(|(batch, pair)| *batch == std::slice::from_ref(pair)));
+
+            let cfg = exchange_cfg(name);
+            let subscribes = batches
+                .iter()
+                .map(|batch| adapter.build_subscribe_message(ChannelType::Trades, batch, &cfg))
+                .filter(|sub| !is_empty_subscribe(sub))
+                .count();
+            assert_eq!(subscribes, pairs.len(), "{name}");
+        }
+
+        let binance = crate::exchanges::get_adapter("binance").unwrap();
+        assert_eq!(subscribe_batches(binance.as_ref(), &pairs), [&pairs[..]]);
+    }
+
+    #[test]
+    fn unset_chunk_sizes_use_the_adapter_default() {
+        let chunk_size = |name: &str, configured: Option<usize>| {
+            let adapter = crate::exchanges::get_adapter(name).unwrap();
+            let mut cfg = exchange_cfg(name);
+            cfg.chunking.trades_per_connection = configured;
+
+            fill_trades_per_connection(adapter.as_ref(), &mut cfg);
+            cfg.chunking.trades_per_connection()
+        };
+
+        assert_eq!(chunk_size("binance", None), 100);
+        assert_eq!(chunk_size("binanceus", None), 100);
+        assert_eq!(chunk_size("okx", None), 10);
+        assert_eq!(chunk_size("binance", Some(5)), 5);
+    }
+
+    #[tokio::test]
+    async fn loops_count_as_never_connected_until_the_first_connect() {
+        let _counters = COUNTERS.lock().await;
+        let never_connected = || METRICS.ws_never_connected.load(Ordering::Relaxed);
+        let before = never_connected();
+
+        let mut first = FirstConnect::pending();
+        assert_eq!(never_connected(), before + 1);
+        assert_eq!((first.failed(), first.failed()), (Some(1), Some(2)));
+
+        first.done();
+        assert_eq!(never_connected(), before);
+
+        // Later failures are reconnects, not first-connect attempts
+        assert_eq!(first.failed(), None);
+        first.done();
+        drop(first);
+        assert_eq!(never_connected(), before);
+
+        // A loop stopped before it ever connected leaves the gauge
+        drop(FirstConnect::pending());
+        assert_eq!(never_connected(), before);
+    }
+
+    /// `mock_server` adapter merging trades and books on one socket;
+    /// subscribes with `{"subscribe": channel, "pairs": pairs}`.
+    struct Merged(&'static str);
+
+    #[async_trait::async_trait]
+    impl ExchangeAdapter for Merged {
+        fn name(&self) -> &'static str {
+            "merged"
+        }
+
+        fn ws_url(&self) -> &'static str {
+            self.0
+        }
+
+        fn metadata(&self) -> AdapterMetadata {
+            AdapterMetadata {
+                name: self.name(),
+                ws_url: self.ws_url(),
+                channels: &[ChannelType::Trades, ChannelType::OrderBooks],
+                auth_required: false,
+                max_streams_per_connection: None,
+            }
+        }
+
+        fn build_subscribe_message(&self, channel: ChannelType, pairs: &[String], _: &ExchangeConfig) -> serde_json::Value {
+            serde_json::json!({ "subscribe": channel, "pairs": pairs })
+        }
+
+        fn parse_message(&self, _: &str, _: &str) -> ParseResult {
+            ParseResult::Control
+        }
+
+        fn supports_multiplex(&self) -> bool {
+            true
         }
     }
+
+    #[tokio::test]
+    async fn multiplexed_trades_and_books_share_one_socket() {
+        let _counters = COUNTERS.lock().await;
+        let (url, mut server) = mock_server().await;
+
+        let mut cfg = exchange_cfg("merged");
+        cfg.pairs.orderbooks = vec!["ETH/USDT".to_string()];
+        cfg.multiplex = Some(true);
+
+        let output: Arc<dyn OutputSink> = Arc::new(RecordingSink::default());
+        let budget = Arc::new(ConnectionBudget::new(None));
+        let handle = run_exchange(Arc::new(Merged(url)), cfg, output, budget).await.unwrap();
+        assert_eq!(handle.connections, 1);
+
+        let mut ws = server.recv().await.unwrap();
+        let subscribes = [next_text(&mut ws).await.unwrap(), next_text(&mut ws).await.unwrap()];
+        assert_eq!(
+            subscribes.map(|sub| serde_json::from_str::<serde_json::Value>(&sub).unwrap()),
+            [
+                serde_json::json!({ "subscribe": "trades", "pairs": ["BTC/USDT"] }),
+                serde_json::json!({ "subscribe": "orderbooks", "pairs": ["ETH/USDT"] }),
+            ]
+        );
+
+        // No second connection for the books
+        assert!(tokio::time::timeout(Duration::from_millis(300), server.recv()).await.is_err());
+        handle.stop();
+    }
 }
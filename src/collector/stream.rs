@@ -0,0 +1,35 @@
+//! In-process message stream for embedders.
+//!
+//! Every message forwarded by `handle_parsed` is also published on
+//! a broadcast channel, so code embedding the collector can process
+//! the data in-process alongside the outputs. Publishing is skipped
+//! while nobody is subscribed.
+//!
+//! Receivers that fall more than `STREAM_CAPACITY` messages behind
+//! lose the oldest ones (`RecvError::Lagged`); the collector never
+//! waits for them.
+
+use once_cell::sync::Lazy;
+use tokio::sync::broadcast;
+
+use crate::schema::MarketMessage;
+
+/// Messages buffered per receiver before it lags
+const STREAM_CAPACITY: usize = 10_000;
+
+static STREAM: Lazy<broadcast::Sender<MarketMessage>> =
+    Lazy::new(|| broadcast::channel(STREAM_CAPACITY).0);
+
+/// Subscribes to every forwarded market message.
+///
+/// Only messages forwarded after this call are received.
+pub fn subscribe() -> broadcast::Receiver<MarketMessage> {
+    STREAM.subscribe()
+}
+
+/// Publishes a message to all current subscribers.
+pub(super) fn publish(mm: &MarketMessage) {
+    if STREAM.receiver_count() > 0 {
+        let _ = STREAM.send(mm.clone());
+    }
+}
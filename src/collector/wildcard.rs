@@ -0,0 +1,179 @@
+use std::collections::HashSet;
+
+use crate::{config::ExchangeConfig, exchanges::adapter::ExchangeAdapter};
+
+/// Pair list entry meaning "all spot pairs of this exchange"
+const WILDCARD: &str = "*";
+
+/// Prefix of a quote-filtered wildcard, e.g. `*/USDT`
+const QUOTE_WILDCARD_PREFIX: &str = "*/";
+
+/// Expands wildcard entries in the configured pair lists.
+///
+/// Supported forms:
+/// - `*`       → all spot pairs
+/// - `*/USDT`  → all spot pairs quoted in USDT
+///
+/// The full symbol list is fetched once via `ExchangeAdapter::all_symbols`
+/// and only if at least one list contains a wildcard.
+///
+/// FAILURE:
+/// - If the adapter does not support wildcards or the fetch fails,
+///   the wildcard is dropped and only explicit pairs remain.
+///
+/// Must run before chunking, so the expanded lists are split into
+/// connections like any explicit configuration.
+pub async fn expand_wildcards(
+    adapter: &dyn ExchangeAdapter,
+    cfg: &mut ExchangeConfig,
+) {
+    let has_wildcard = cfg.pairs.trades
+        .iter()
+        .chain(&cfg.pairs.orderbooks)
+        .any(|p| is_wildcard(p));
+
+    if !has_wildcard {
+        return;
+    }
+
+    let all = match adapter.all_symbols().await {
+        Ok(all) => all,
+        Err(e) => {
+            tracing::warn!("[WILDCARD][{}] failed to resolve wildcard: {e}", adapter.name());
+            Vec::new()
+        }
+    };
+
+    cfg.pairs.trades = expand_pair_list(&cfg.pairs.trades, &all);
+    cfg.pairs.orderbooks = expand_pair_list(&cfg.pairs.orderbooks, &all);
+
+    tracing::info!(
+        "[WILDCARD][{}] resolved {} trade / {} orderbook pairs",
+        adapter.name(),
+        cfg.pairs.trades.len(),
+        cfg.pairs.orderbooks.len()
+    );
+}
+
+fn is_wildcard(pair: &str) -> bool {
+    pair == WILDCARD || pair.starts_with(QUOTE_WILDCARD_PREFIX)
+}
+
+/// Replaces every wildcard entry with the matching symbols in `all`.
+///
+/// - Explicit pairs are kept in their configured order
+/// - Duplicates (explicit + wildcard) are emitted once
+/// - Quote matching is case-insensitive (`*/usdt` == `*/USDT`)
+fn expand_pair_list(pairs: &[String], all: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::with_capacity(pairs.len());
+
+    for pair in pairs {
+        let resolved: Vec<&String> = if pair == WILDCARD {
+            all.iter().collect()
+        } else if let Some(quote) = pair.strip_prefix(QUOTE_WILDCARD_PREFIX) {
+            let quote = quote.to_uppercase();
+            all.iter()
+                .filter(|s| s.rsplit('/').next() == Some(quote.as_str()))
+                .collect()
+        } else {
+            vec![pair]
+        };
+
+        for symbol in resolved {
+            if seen.insert(symbol.as_str()) {
+                out.push(symbol.clone());
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::exchanges::adapter::{AdapterMetadata, ChannelType, ParseResult};
+
+    /// Adapter whose REST symbol list is `listed` (None: fetch fails).
+    struct Listed(Option<Vec<&'static str>>);
+
+    #[async_trait::async_trait]
+    impl ExchangeAdapter for Listed {
+        fn name(&self) -> &'static str {
+            "listed"
+        }
+
+        fn ws_url(&self) -> &'static str {
+            ""
+        }
+
+        fn metadata(&self) -> AdapterMetadata {
+            AdapterMetadata {
+                name: self.name(),
+                ws_url: self.ws_url(),
+                channels: &[ChannelType::Trades, ChannelType::OrderBooks],
+                auth_required: false,
+                max_streams_per_connection: None,
+            }
+        }
+
+        fn build_subscribe_message(&self, _: ChannelType, _: &[String], _: &ExchangeConfig) -> Value {
+            json!({})
+        }
+
+        fn parse_message(&self, _: &str, _: &str) -> ParseResult {
+            ParseResult::Ignored
+        }
+
+        async fn all_symbols(&self) -> anyhow::Result<Vec<String>> {
+            match &self.0 {
+                Some(listed) => Ok(listed.iter().map(|s| s.to_string()).collect()),
+                None => Err(anyhow::anyhow!("exchangeInfo unavailable")),
+            }
+        }
+    }
+
+    const LISTED: [&str; 4] = ["BTC/USDT", "ETH/USDT", "ETH/BTC", "SOL/USDC"];
+
+    fn config(trades: &[&str], orderbooks: &[&str]) -> ExchangeConfig {
+        serde_json::from_value(json!({
+            "name": "listed",
+            "enabled": true,
+            "pairs": { "trades": trades, "orderbooks": orderbooks }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn star_expands_to_every_listed_pair() {
+        let mut cfg = config(&["ETH/BTC", "*"], &["BTC/USDT"]);
+
+        expand_wildcards(&Listed(Some(LISTED.to_vec())), &mut cfg).await;
+
+        // Explicit pair first, no duplicate from the wildcard
+        assert_eq!(cfg.pairs.trades, ["ETH/BTC", "BTC/USDT", "ETH/USDT", "SOL/USDC"]);
+        assert_eq!(cfg.pairs.orderbooks, ["BTC/USDT"]);
+    }
+
+    #[tokio::test]
+    async fn quote_wildcard_keeps_only_that_quote() {
+        let mut cfg = config(&["*/usdt"], &["*/USDC", "*/EUR"]);
+
+        expand_wildcards(&Listed(Some(LISTED.to_vec())), &mut cfg).await;
+
+        assert_eq!(cfg.pairs.trades, ["BTC/USDT", "ETH/USDT"]);
+        assert_eq!(cfg.pairs.orderbooks, ["SOL/USDC"]);
+    }
+
+    #[tokio::test]
+    async fn failed_listing_keeps_explicit_pairs_only() {
+        let mut cfg = config(&["*", "ETH/BTC"], &[]);
+
+        expand_wildcards(&Listed(None), &mut cfg).await;
+
+        assert_eq!(cfg.pairs.trades, ["ETH/BTC"]);
+    }
+}
@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+
 use serde::Deserialize;
+use crate::exchanges::adapter::ChannelType;
 
 // ------------------------------------------------------------
 // Root configuration
@@ -11,6 +14,12 @@ use serde::Deserialize;
 // - Master connection settings
 // - Enabled exchanges and their parameters
 // - Optional debug configuration
+// - Optional admin endpoint
+// - Optional startup health probe
+// - Optional output sinks
+// - Optional value normalization
+// - Optional resource limits
+// - Optional log output format
 //
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
@@ -22,6 +31,111 @@ pub struct Config {
 
     /// Optional debug configuration
     pub debug: Option<DebugConfig>,
+
+    /// Optional admin HTTP endpoint
+    pub admin: Option<AdminConfig>,
+
+    /// Optional startup liveness probe
+    pub health: Option<HealthConfig>,
+
+    /// Optional output sinks (default: master only)
+    pub output: Option<OutputConfig>,
+
+    /// Optional price / amount rounding applied to all messages
+    pub normalize: Option<NormalizeConfig>,
+
+    /// Drop malformed messages (empty symbol, price, …) before
+    /// any output. Default: false
+    #[serde(default)]
+    pub validate_output: bool,
+
+    /// Optional resource limits
+    pub limits: Option<LimitsConfig>,
+
+    /// Optional log output settings (default: text)
+    #[serde(default)]
+    pub log: LogConfig,
+}
+
+impl Config {
+    /// Checks config semantics serde cannot express.
+    ///
+    /// RETURNS:
+    /// - One human-readable line per problem; empty if valid
+    ///
+    /// Adapter-specific checks (supported exchanges / channels)
+    /// are done by the caller, keeping this module free of
+    /// exchange knowledge.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.master.connections == 0 {
+            problems.push("master.connections must be at least 1".to_string());
+        }
+
+        for ex in &self.exchanges {
+            let name = if ex.name.is_empty() { "<unnamed>" } else { &ex.name };
+            let mut problem = |msg: String| problems.push(format!("exchange {name}: {msg}"));
+
+            if ex.name.is_empty() {
+                problem("name is empty".to_string());
+            }
+
+            if ex.chunking.trades_per_connection == Some(0) {
+                problem("chunking.trades_per_connection must be at least 1".to_string());
+            }
+
+            if ex.chunking.orderbooks_per_connection == 0 {
+                problem("chunking.orderbooks_per_connection must be at least 1".to_string());
+            }
+
+            let mut exchange_symbols: Vec<&String> = ex.symbol_map.values().collect();
+            exchange_symbols.sort();
+            if let Some(dup) = exchange_symbols.windows(2).find(|w| w[0] == w[1]) {
+                problem(format!("symbol_map maps several symbols to '{}'", dup[0]));
+            }
+
+            if ex.rest_poll.as_ref().is_some_and(|p| p.interval_ms == 0) {
+                problem("rest_poll.interval_ms must be at least 1".to_string());
+            }
+
+            let pairs = ex.pairs.trades
+                .iter()
+                .chain(&ex.pairs.orderbooks)
+                .chain(&ex.pairs.funding);
+
+            for pair in pairs {
+                let wildcard = pair == "*" || pair.starts_with("*/");
+                let valid = pair
+                    .split_once('/')
+                    .is_some_and(|(base, quote)| !base.is_empty() && !quote.is_empty());
+
+                if !wildcard && !valid {
+                    problem(format!("pair '{pair}' is not in BASE/QUOTE format"));
+                }
+            }
+        }
+
+        // Overrides are registered per exchange name (`util::set_symbol_map`)
+        for (i, ex) in self.exchanges.iter().enumerate() {
+            let conflicting = self.exchanges[..i]
+                .iter()
+                .any(|other| other.name == ex.name && other.symbol_map != ex.symbol_map);
+
+            if conflicting {
+                problems.push(format!(
+                    "exchange {}: symbol_map differs between instances of the same exchange",
+                    ex.name
+                ));
+            }
+        }
+
+        if self.limits.as_ref().and_then(|l| l.max_connections) == Some(0) {
+            problems.push("limits.max_connections is 0 – no exchange can connect".to_string());
+        }
+
+        problems
+    }
 }
 
 // ------------------------------------------------------------
@@ -34,6 +148,8 @@ pub struct Config {
 // - The `key` is security-sensitive and must never be committed.
 // - `connections` controls parallel WebSocket connections.
 // - `demo` disables sending data to the master (local testing).
+// - `envelope_template` wraps messages for masters expecting one.
+// - `dead_letter_path` keeps unforwardable messages for replay.
 //
 #[derive(Debug, Deserialize, Clone)]
 pub struct MasterConfig {
@@ -44,11 +160,108 @@ pub struct MasterConfig {
     pub connections: usize,
 
     /// Authentication key used during login
-    /// (sent as: key=XYZ&role=collector)
+    /// (sent as: key=XYZ&role=ROLE&client_id=ID, see `login_format`)
     pub key: String,
 
+    /// Encoding of the login message (default: query)
+    #[serde(default)]
+    pub login_format: LoginFormat,
+
+    /// Role sent during login (default: "collector")
+    ///
+    /// Examples: "collector", "backfill"
+    pub role: Option<String>,
+
+    /// Identifier of this collector instance, sent during login
+    ///
+    /// Defaults to the hostname (see `MasterConfig::client_id`).
+    pub client_id: Option<String>,
+
     /// Demo mode flag (no data sent, only logged)
     pub demo: Option<bool>,
+
+    /// Optional envelope wrapped around every outgoing message
+    ///
+    /// Every string value equal to "$message" is replaced by the
+    /// market message, e.g.:
+    /// { "topic": "market", "payload": "$message" }
+    ///
+    /// Collector metadata for downstream dedup / routing:
+    /// - "$collector_id" → `client_id`
+    /// - "$received_at"  → time the collector received the message (ms)
+    /// - "$shard"        → shard of the message symbol (see `shards`)
+    ///
+    /// When unset, bare messages are sent.
+    pub envelope_template: Option<serde_json::Value>,
+
+    /// Number of master shards for the "$shard" envelope hint
+    ///
+    /// The shard is a stable hash of the symbol modulo `shards`, so
+    /// every collector routes a symbol to the same shard.
+    pub shards: Option<u32>,
+
+    /// Pin every connection to one master sender (default false)
+    ///
+    /// Messages of one WS loop / REST poller then always take the same
+    /// master link and keep their order. Falls back to random senders
+    /// when the pinned one is busy.
+    pub sticky_routing: Option<bool>,
+
+    /// Optional SHA-256 fingerprint (hex) of the master TLS certificate
+    ///
+    /// When set, only a certificate with exactly this fingerprint is
+    /// accepted for `wss://` master connections.
+    pub pinned_cert_sha256: Option<String>,
+
+    /// Optional connection-level network settings
+    pub network: Option<NetworkConfig>,
+
+    /// Optional NDJSON file receiving messages that could not be
+    /// handed to any master connection (for later replay)
+    pub dead_letter_path: Option<String>,
+
+    /// Maximum size of the dead-letter file in bytes; further
+    /// messages are dropped. Default: 100 MB
+    pub dead_letter_max_bytes: Option<u64>,
+
+    /// Rotate a full dead-letter file into `<path>.<ms>.<seq>.gz` instead
+    /// of dropping, keeping this many gzipped segments
+    pub dead_letter_rotate_segments: Option<usize>,
+}
+
+impl MasterConfig {
+    /// Returns the configured login role or "collector".
+    pub fn role(&self) -> &str {
+        self.role.as_deref().unwrap_or("collector")
+    }
+
+    /// Returns the configured client id or, if unset, the hostname.
+    ///
+    /// Falls back to "unknown" if the hostname cannot be resolved.
+    pub fn client_id(&self) -> String {
+        if let Some(id) = &self.client_id {
+            return id.clone();
+        }
+
+        std::env::var("HOSTNAME")
+            .ok()
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .map(|h| h.trim().to_string())
+            .filter(|h| !h.is_empty())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+}
+
+/// Encoding of the master login message (`master.login_format`).
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginFormat {
+    /// URL-encoded querystring: `key=…&role=…&client_id=…`
+    #[default]
+    Query,
+
+    /// JSON object: `{"key": …, "role": …, "client_id": …}`
+    Json,
 }
 
 // ------------------------------------------------------------
@@ -70,14 +283,208 @@ pub struct ExchangeConfig {
     /// Enables or disables this exchange at runtime
     pub enabled: bool,
 
+    /// Lifecycle state (default: "active")
+    ///
+    /// A paused exchange stays registered and is reported as
+    /// "paused" in the admin status, but is not connected.
+    #[serde(default)]
+    pub state: ExchangeState,
+
     /// Trading pairs to subscribe to
     pub pairs: ExchangePairs,
 
     /// Chunking configuration for WebSocket connections
+    ///
+    /// Optional; defaults to the adapter's trade chunk size and one
+    /// orderbook per connection.
+    #[serde(default)]
     pub chunking: ExchangeChunking,
 
     /// Optional orderbook-specific configuration
     pub orderbook: Option<OrderbookConfig>,
+
+    /// Client-side ping interval in milliseconds
+    ///
+    /// Overrides the adapter default. Adapters without an
+    /// application-level ping send WebSocket ping frames instead.
+    pub ping_interval_ms: Option<u64>,
+
+    /// Maximum connection lifetime in milliseconds
+    ///
+    /// Connections are closed and re-established before this age,
+    /// ahead of exchange-enforced disconnects. Overrides the
+    /// adapter default (Binance: 23h).
+    pub max_connection_age_ms: Option<u64>,
+
+    /// Reconnect if no frame arrives for this long (milliseconds)
+    ///
+    /// Detects silently stalled connections. Default: disabled
+    pub idle_timeout_ms: Option<u64>,
+
+    /// Reconnect if no market data arrives this long after
+    /// subscribing (milliseconds); acks alone do not count
+    ///
+    /// Must exceed the quietest pair's trade interval.
+    /// Default: disabled
+    pub subscribe_confirm_timeout_ms: Option<u64>,
+
+    /// Timeout for REST requests of this exchange in milliseconds
+    /// (e.g. the KuCoin WS token fetch). Default: 10000
+    pub rest_timeout_ms: Option<u64>,
+
+    /// Subscribe via the combined-stream URL instead of SUBSCRIBE
+    /// frames (Binance). Default: false
+    pub combined_streams: Option<bool>,
+
+    /// Fetch trades missed during a reconnect via REST
+    /// (Binance, OKX), deduped by trade id. Default: false
+    pub backfill_trades: Option<bool>,
+
+    /// Origin tag added to every message of this exchange
+    /// (e.g. "binance-fra"), default: the exchange name
+    ///
+    /// Lets aggregators tell collectors / regions apart.
+    pub source_tag: Option<String>,
+
+    /// Reconnect delay after a rate-limit signal (HTTP 429 / 418,
+    /// close code 1013, limit error frames). Default: 60000
+    pub rate_limit_backoff_ms: Option<u64>,
+
+    /// Forward the exchange event time of trades as `event_ts`
+    /// (Binance). Default: false
+    pub event_time: Option<bool>,
+
+    /// Maximum concurrent sends of this exchange into the output
+    ///
+    /// Keeps a flooding exchange from starving the others.
+    /// Unset: unlimited
+    pub max_in_flight: Option<usize>,
+
+    /// Maximum messages per second forwarded per symbol
+    ///
+    /// Protects the master from a single runaway market; excess
+    /// messages are dropped and counted in `rate_capped`.
+    /// Unset: unlimited
+    pub max_msgs_per_sec: Option<u32>,
+
+    /// Print this exchange's messages to stdout instead of sending
+    /// them (default false)
+    ///
+    /// Per-exchange counterpart of `master.demo`, for validating a
+    /// new exchange while the others forward live.
+    pub demo: Option<bool>,
+
+    /// Send this exchange to a different master
+    ///
+    /// Uses a dedicated master pool (same `master` settings, this
+    /// URL), shared by all exchanges with the same URL.
+    pub master_url: Option<String>,
+
+    /// Symbol name overrides: internal → exchange symbol
+    ///
+    /// For listings the generic conversion gets wrong (rebrands,
+    /// odd tickers), e.g. `{"MATIC/USDT": "POLUSDT"}`. Applied in
+    /// both directions before the generic rules.
+    #[serde(default)]
+    pub symbol_map: HashMap<String, String>,
+
+    /// Optional connection-level network settings
+    pub network: Option<NetworkConfig>,
+
+    /// Subscribe trades and orderbooks on shared connections
+    ///
+    /// Only for adapters that support it (e.g. Coinbase, Bybit);
+    /// ignored with a warning otherwise. Default: false
+    pub multiplex: Option<bool>,
+
+    /// Optional REST snapshot poller for orderbooks
+    ///
+    /// When set, orderbook pairs are polled via REST instead of
+    /// subscribed via WebSocket.
+    pub rest_poll: Option<RestPollConfig>,
+}
+
+impl ExchangeConfig {
+    /// Returns the configured source tag or the exchange name.
+    pub fn source_tag(&self) -> &str {
+        self.source_tag.as_deref().unwrap_or(&self.name)
+    }
+}
+
+// ------------------------------------------------------------
+// Exchange state
+// ------------------------------------------------------------
+//
+// Distinct from `enabled: false`: "paused" documents that the
+// exchange is intentionally stopped for now (e.g. maintenance)
+// and is surfaced as such by the admin status.
+//
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExchangeState {
+    #[default]
+    Active,
+    Paused,
+}
+
+// ------------------------------------------------------------
+// Network configuration
+// ------------------------------------------------------------
+//
+// Connection-level settings, available for the master and for
+// every exchange.
+//
+// Example (CDN fronting):
+// "network": { "sni_override": "edge.example.com" }
+//
+// Example (IPv4 only):
+// "network": { "ip_family": "v4" }
+//
+#[derive(Debug, Deserialize, Clone)]
+pub struct NetworkConfig {
+    /// Host name sent as TLS SNI instead of the URL host
+    ///
+    /// Only for `wss://` URLs. The certificate is validated
+    /// against this name.
+    pub sni_override: Option<String>,
+
+    /// Address family used to connect (default "auto")
+    ///
+    /// For dual-stack hosts where one family is slow or broken.
+    #[serde(default)]
+    pub ip_family: IpFamily,
+
+    /// TCP keepalive idle time in seconds
+    ///
+    /// Detects dead peers behind NAT at the socket level, below
+    /// the application ping. Unset: OS default (usually off)
+    pub tcp_keepalive_secs: Option<u64>,
+}
+
+/// Address family preference of `network.ip_family`.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFamily {
+    /// Resolver order, both families
+    #[default]
+    Auto,
+
+    /// IPv4 addresses only
+    V4,
+
+    /// IPv6 addresses only
+    V6,
+}
+
+impl IpFamily {
+    /// Returns true if `addr` may be connected to.
+    pub fn allows(self, addr: &std::net::SocketAddr) -> bool {
+        match self {
+            IpFamily::Auto => true,
+            IpFamily::V4 => addr.is_ipv4(),
+            IpFamily::V6 => addr.is_ipv6(),
+        }
+    }
 }
 
 // ------------------------------------------------------------
@@ -99,6 +506,22 @@ pub struct ExchangePairs {
 
     /// Trading pairs for orderbook subscriptions
     pub orderbooks: Vec<String>,
+
+    /// Perpetual underlyings for mark price / funding rate
+    /// subscriptions (Binance, Bybit, OKX). Default: none
+    #[serde(default)]
+    pub funding: Vec<String>,
+}
+
+impl ExchangePairs {
+    /// Configured pairs of one channel.
+    pub fn for_channel(&self, channel: ChannelType) -> &[String] {
+        match channel {
+            ChannelType::Trades => &self.trades,
+            ChannelType::OrderBooks => &self.orderbooks,
+            ChannelType::Funding => &self.funding,
+        }
+    }
 }
 
 // ------------------------------------------------------------
@@ -116,17 +539,43 @@ pub struct ExchangePairs {
 #[derive(Debug, Deserialize, Clone)]
 pub struct ExchangeChunking {
     /// Number of trade pairs per WebSocket connection
-    pub trades_per_connection: usize,
+    ///
+    /// When unset, the adapter default is filled in by the runner
+    /// (see `ExchangeAdapter::default_trades_per_connection`).
+    #[serde(default)]
+    pub trades_per_connection: Option<usize>,
 
     /// Number of orderbook pairs per WebSocket connection
     ///
     /// NOTE:
-    /// Currently unused for Gate.io, which requires one
-    /// orderbook subscription per connection.
-    #[allow(dead_code)]
+    /// Only used with `multiplex`. Otherwise every orderbook
+    /// gets its own connection (Gate.io requires one orderbook
+    /// subscription per connection).
+    #[serde(default = "default_orderbooks_per_connection")]
     pub orderbooks_per_connection: usize,
 }
 
+impl ExchangeChunking {
+    /// Trade pairs per connection; 1 until the adapter default is
+    /// filled in.
+    pub fn trades_per_connection(&self) -> usize {
+        self.trades_per_connection.unwrap_or(1)
+    }
+}
+
+impl Default for ExchangeChunking {
+    fn default() -> Self {
+        Self {
+            trades_per_connection: None,
+            orderbooks_per_connection: default_orderbooks_per_connection(),
+        }
+    }
+}
+
+fn default_orderbooks_per_connection() -> usize {
+    1
+}
+
 // ------------------------------------------------------------
 // Orderbook configuration
 // ------------------------------------------------------------
@@ -144,6 +593,58 @@ pub struct OrderbookConfig {
 
     /// Update interval in milliseconds
     pub update_interval_ms: u64,
+
+    /// Levels per side forwarded to the outputs
+    ///
+    /// Books are sorted and truncated to the best N asks / bids
+    /// before sending. Unset: forward everything received.
+    pub forward_depth: Option<usize>,
+
+    /// Drop books whose levels equal the previous book of the same
+    /// symbol on the same connection. Default: false
+    #[serde(default)]
+    pub dedup: bool,
+
+    /// Forward books to the outputs (default true)
+    ///
+    /// With false, books are still subscribed and kept as local
+    /// books on the connection, but not sent.
+    pub forward: Option<bool>,
+
+    /// With `forward: false`, forward the best bid / ask of the
+    /// local book as a ticker whenever it changes. Default: false
+    #[serde(default)]
+    pub bbo: bool,
+}
+
+impl OrderbookConfig {
+    /// Returns true if books are kept locally instead of forwarded.
+    pub fn local_only(&self) -> bool {
+        !self.forward.unwrap_or(true)
+    }
+}
+
+// ------------------------------------------------------------
+// REST poll configuration
+// ------------------------------------------------------------
+//
+// Fallback source for exchanges whose orderbook WebSocket is
+// flaky or unsupported.
+//
+// The URL template may contain:
+// - {symbol} → exchange-specific symbol (e.g. BTCUSDT)
+// - {depth}  → orderbook depth (default: 20)
+//
+// Example:
+// "https://api.binance.com/api/v3/depth?symbol={symbol}&limit={depth}"
+//
+#[derive(Debug, Deserialize, Clone)]
+pub struct RestPollConfig {
+    /// Snapshot URL template
+    pub url: String,
+
+    /// Poll interval in milliseconds
+    pub interval_ms: u64,
 }
 
 // ------------------------------------------------------------
@@ -164,3 +665,154 @@ pub struct DebugConfig {
     /// Enables structured debug logging
     pub log: Option<bool>,
 }
+
+// ------------------------------------------------------------
+// Admin configuration
+// ------------------------------------------------------------
+//
+// Optional HTTP endpoint for runtime operations
+// (e.g. enabling / disabling exchanges).
+//
+// SECURITY:
+// - No authentication; bind to localhost or a private interface.
+//
+#[derive(Debug, Deserialize, Clone)]
+pub struct AdminConfig {
+    /// Listen address, e.g. "127.0.0.1:9100"
+    pub bind: String,
+}
+
+// ------------------------------------------------------------
+// Health configuration
+// ------------------------------------------------------------
+//
+// Startup liveness probe for container orchestration.
+//
+// If fewer than `min_messages` market messages were received
+// across all exchanges after the grace period, the process
+// exits non-zero so the orchestrator restarts it.
+//
+#[derive(Debug, Deserialize, Clone)]
+pub struct HealthConfig {
+    /// Grace period after startup in milliseconds
+    pub startup_grace_ms: u64,
+
+    /// Messages required after the grace period (default: 1)
+    pub min_messages: Option<usize>,
+}
+
+impl HealthConfig {
+    /// Returns the configured threshold or 1 ("no data at all").
+    pub fn min_messages(&self) -> usize {
+        self.min_messages.unwrap_or(1)
+    }
+}
+
+// ------------------------------------------------------------
+// Output configuration
+// ------------------------------------------------------------
+//
+// Destinations for market messages. Without this section all
+// messages go to the master. When set, `sinks` lists ALL
+// destinations; include `{ "type": "master" }` to keep the master.
+//
+// Every sink receives every message with its own queue, so a
+// slow sink does not stall the others. `types` restricts a sink
+// to some message types ("trade", "book", "ticker").
+//
+// Example:
+// "output": { "sinks": [
+//     { "type": "master", "types": ["trade"] },
+//     { "type": "unix", "path": "/run/ftso.sock", "types": ["book"] }
+// ] }
+//
+#[derive(Debug, Deserialize, Clone)]
+pub struct OutputConfig {
+    /// Output sinks
+    pub sinks: Vec<SinkEntry>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SinkEntry {
+    /// Sink type and its settings
+    #[serde(flatten)]
+    pub sink: SinkConfig,
+
+    /// Forwarded message types (default: all)
+    pub types: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkConfig {
+    /// The master pool (see `master`)
+    Master,
+
+    /// NDJSON broadcast to local clients of a UNIX domain socket
+    Unix {
+        /// Socket path (a stale file is replaced)
+        path: String,
+    },
+}
+
+// ------------------------------------------------------------
+// Normalization configuration
+// ------------------------------------------------------------
+//
+// Uniform decimal precision across exchanges.
+//
+// Values are rounded (decimal arithmetic, midpoint away from
+// zero) before they reach any output. Unset fields are left
+// exactly as reported by the exchange.
+//
+#[derive(Debug, Deserialize, Clone)]
+pub struct NormalizeConfig {
+    /// Fractional digits for prices (trade, book, ticker)
+    pub price_decimals: Option<u32>,
+
+    /// Fractional digits for amounts / volumes
+    pub amount_decimals: Option<u32>,
+}
+
+// ------------------------------------------------------------
+// Limits configuration
+// ------------------------------------------------------------
+//
+// Guards against accidental resource exhaustion by large
+// configs (e.g. wildcard pairs with one orderbook connection
+// per pair).
+//
+#[derive(Debug, Deserialize, Clone)]
+pub struct LimitsConfig {
+    /// Maximum number of exchange WebSocket connections
+    ///
+    /// Connections beyond the cap are not spawned; the skipped
+    /// pairs are logged.
+    pub max_connections: Option<usize>,
+}
+
+// ------------------------------------------------------------
+// Log configuration
+// ------------------------------------------------------------
+//
+// Line format of the collector logs. JSON lines carry the
+// event fields (`exchange`, `channel`, `conn_id`) for log
+// aggregation (Loki, ELK).
+//
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LogConfig {
+    /// "text" (default) or "json"
+    #[serde(default)]
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Plain message lines
+    #[default]
+    Text,
+
+    /// One JSON object per line
+    Json,
+}
@@ -1,11 +1,139 @@
+use serde::Serialize;
 use serde_json::Value;
-use crate::schema::MarketMessage;
+use std::fmt;
+use std::time::Duration;
+use crate::schema::{MarketMessage, TradeData};
 use crate::config::ExchangeConfig;
+use crate::util;
 
 pub enum ParseResult {
     Market(MarketMessage),
+
+    /// Several messages from one frame (trade batches), in order
+    Batch(Vec<MarketMessage>),
+
+    /// Recognized protocol frame without market data
+    /// (subscribe ack, pong, heartbeat, snapshot skipped on purpose)
     Control,
-    Error,
+
+    /// Valid frame the adapter does not recognize at all
+    Ignored,
+
+    /// Frame that should carry data but could not be parsed
+    Error(ParseError),
+
+    /// Exchange rejected subscriptions because a connection carries too
+    /// many streams or sent them too fast. Carries the exchange message.
+    SubscriptionLimit(String),
+}
+
+impl ParseResult {
+    /// Wraps the messages parsed from one frame.
+    ///
+    /// None yields `Control`, one a plain `Market`, more a `Batch`.
+    pub fn from_messages(mut messages: Vec<MarketMessage>) -> Self {
+        match messages.len() {
+            0 => Self::Control,
+            1 => Self::Market(messages.remove(0)),
+            _ => Self::Batch(messages),
+        }
+    }
+
+    /// Returns true if the result carries market data.
+    pub fn is_market(&self) -> bool {
+        matches!(self, Self::Market(_) | Self::Batch(_))
+    }
+}
+
+/// Returns true if an exchange error text indicates a subscription /
+/// request limit (too many streams per connection, too frequent).
+///
+/// Shared by adapters so the wording list lives in one place.
+pub fn is_subscription_limit_error(msg: &str) -> bool {
+    let msg = msg.to_lowercase();
+
+    [
+        "too many",
+        "too frequent",
+        "rate limit",
+        "exceed",
+        "max stream",
+    ]
+    .iter()
+    .any(|p| msg.contains(p))
+}
+
+/// How an adapter's subscribe messages cover the pairs of a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscribeGranularity {
+    /// One subscribe message for all pairs
+    Batch,
+
+    /// One subscribe message per pair (Bitfinex, Bitstamp)
+    PerSymbol,
+}
+
+/// Category of a parse failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// Frame is not valid JSON
+    InvalidJson,
+
+    /// Required field absent or of the wrong type
+    MissingField,
+
+    /// Field present but unusable (e.g. topic without symbol)
+    Malformed,
+
+    /// Error frame sent by the exchange
+    Exchange,
+
+    /// Exchange rejected a subscription (unknown pair, bad channel)
+    Subscription,
+}
+
+/// Reason a frame could not be parsed, for logs.
+///
+/// `detail` is the field name, the exchange message, etc.;
+/// `Display` renders e.g. `missing field 'p'`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub detail: String,
+}
+
+impl ParseError {
+    pub fn invalid_json(err: &serde_json::Error) -> Self {
+        Self { kind: ParseErrorKind::InvalidJson, detail: err.to_string() }
+    }
+
+    pub fn missing_field(field: &str) -> Self {
+        Self { kind: ParseErrorKind::MissingField, detail: field.to_string() }
+    }
+
+    pub fn malformed(detail: impl Into<String>) -> Self {
+        Self { kind: ParseErrorKind::Malformed, detail: detail.into() }
+    }
+
+    pub fn exchange(msg: impl Into<String>) -> Self {
+        Self { kind: ParseErrorKind::Exchange, detail: msg.into() }
+    }
+
+    pub fn subscription(msg: impl Into<String>) -> Self {
+        Self { kind: ParseErrorKind::Subscription, detail: msg.into() }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ParseErrorKind::InvalidJson => write!(f, "invalid JSON: {}", self.detail),
+            ParseErrorKind::MissingField => write!(f, "missing field '{}'", self.detail),
+            ParseErrorKind::Malformed => write!(f, "malformed frame: {}", self.detail),
+            ParseErrorKind::Exchange => write!(f, "exchange error: {}", self.detail),
+            ParseErrorKind::Subscription => write!(f, "subscription rejected: {}", self.detail),
+        }
+    }
 }
 
 /// Defines the supported logical data channels.
@@ -20,13 +148,64 @@ pub enum ParseResult {
 ///   - runner logic
 ///   - all exchange adapters
 ///
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ChannelType {
     /// Trade stream (individual executions)
     Trades,
 
     /// Orderbook stream (Level 2 updates, incremental)
     OrderBooks,
+
+    /// Mark price / funding rate of perpetual futures
+    ///
+    /// Adapters without derivatives return an empty subscribe (`{}`).
+    Funding,
+}
+
+impl ChannelType {
+    /// All channels, in spawn order.
+    pub fn all() -> &'static [ChannelType] {
+        &[ChannelType::Trades, ChannelType::OrderBooks, ChannelType::Funding]
+    }
+}
+
+/// Letter case an exchange expects for symbols in subscriptions.
+///
+/// Applied by `ExchangeAdapter::subscribe_symbol` on top of the
+/// exchange symbol format (`util::symbol_to_exchange`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolCase {
+    /// btcusdt (Binance streams, Bitstamp, Bitrue)
+    Lower,
+
+    /// BTC-USDT (KuCoin, MEXC futures)
+    Upper,
+
+    /// Exchange format unchanged
+    AsIs,
+}
+
+/// Static self-description of an adapter.
+///
+/// Served in the admin `/status` so tooling does not need
+/// per-exchange knowledge.
+#[derive(Debug, Clone, Serialize)]
+pub struct AdapterMetadata {
+    /// Same as `ExchangeAdapter::name`
+    pub name: &'static str,
+
+    /// Default endpoint; empty if resolved at connect time (KuCoin)
+    pub ws_url: &'static str,
+
+    /// Channels the adapter actually subscribes
+    pub channels: &'static [ChannelType],
+
+    /// True if subscribing requires API credentials
+    pub auth_required: bool,
+
+    /// Exchange limit of streams per connection, if documented
+    pub max_streams_per_connection: Option<usize>,
 }
 
 /// ExchangeAdapter is the core abstraction layer between:
@@ -75,6 +254,49 @@ pub trait ExchangeAdapter: Send + Sync {
     ///
     fn ws_url(&self) -> &'static str;
 
+    /// Describes the adapter (endpoint, channels, limits).
+    ///
+    /// CONTRACT:
+    /// - `name` and `ws_url` must match `name()` / `ws_url()`
+    /// - `channels` lists only channels with a non-empty subscribe
+    ///
+    fn metadata(&self) -> AdapterMetadata;
+
+    /// Returns the WebSocket endpoint for a connection carrying
+    /// `channels`.
+    ///
+    /// Exchanges serving derivatives on a separate endpoint
+    /// (Binance, Bybit) override this for `Funding`.
+    ///
+    /// Default: `ws_url()`
+    ///
+    fn ws_url_for(&self, _channels: &[ChannelType]) -> &'static str {
+        self.ws_url()
+    }
+
+    /// Letter case of subscription symbols.
+    ///
+    /// Default: `SymbolCase::AsIs`
+    ///
+    fn symbol_case(&self) -> SymbolCase {
+        SymbolCase::AsIs
+    }
+
+    /// Converts a normalized pair (BTC/USDT) into the symbol used in
+    /// subscriptions: exchange format, then `symbol_case()`.
+    ///
+    /// Adapters should use this instead of casing symbols themselves.
+    ///
+    fn subscribe_symbol(&self, pair: &str) -> String {
+        let symbol = util::symbol_to_exchange(self.name(), pair);
+
+        match self.symbol_case() {
+            SymbolCase::Lower => symbol.to_lowercase(),
+            SymbolCase::Upper => symbol.to_uppercase(),
+            SymbolCase::AsIs => symbol,
+        }
+    }
+
     /// Builds a subscription message for a given channel.
     ///
     /// PARAMETERS:
@@ -105,6 +327,43 @@ pub trait ExchangeAdapter: Send + Sync {
         config: &ExchangeConfig,
     ) -> Value;
 
+    /// Whether `build_subscribe_message` takes all pairs at once or
+    /// must be called per pair.
+    ///
+    /// The runner calls it accordingly. Default: `Batch`
+    ///
+    fn subscribe_granularity(&self) -> SubscribeGranularity {
+        SubscribeGranularity::Batch
+    }
+
+    /// Returns a URL that subscribes `pairs` by itself.
+    ///
+    /// For exchanges taking streams in the URL (Binance combined
+    /// streams). When Some, the runner connects there and sends no
+    /// subscribe message. Only used for connections carrying a
+    /// single channel.
+    ///
+    /// Default: None (subscribe via message)
+    ///
+    fn subscribe_url(
+        &self,
+        _channel: ChannelType,
+        _pairs: &[String],
+        _config: &ExchangeConfig,
+    ) -> Option<String> {
+        None
+    }
+
+    /// WebSocket subprotocols requested in the handshake
+    /// (`Sec-WebSocket-Protocol`), for exchanges / proxies that
+    /// require one.
+    ///
+    /// Default: none
+    ///
+    fn subprotocols(&self) -> &[&str] {
+        &[]
+    }
+
     /// Parses a raw WebSocket message into a MarketMessage.
     ///
     /// INPUT:
@@ -112,16 +371,21 @@ pub trait ExchangeAdapter: Send + Sync {
     /// - `exchange_name`: adapter.name(), injected by runtime
     ///
     /// OUTPUT:
-    /// - Some(MarketMessage) for valid market data
-    /// - None for:
-    ///   - Heartbeats
-    ///   - Subscribe acknowledgements
-    ///   - Errors
-    ///   - Unsupported messages
+    /// - Market(MarketMessage) for valid market data
+    /// - Batch(Vec<MarketMessage>) for frames carrying several messages
+    /// - Control for protocol frames without data (subscribe acks,
+    ///   pongs, heartbeats)
+    /// - Ignored for valid frames the adapter does not recognize
+    ///   (e.g. unknown channels)
+    /// - Error(ParseError) for invalid JSON, frames missing required
+    ///   fields and exchange error frames
+    /// - SubscriptionLimit(msg) when the exchange rejects subscriptions
+    ///   for too many streams / too fast (see
+    ///   `is_subscription_limit_error`)
     ///
     /// IMPORTANT:
     /// - This function must NEVER panic
-    /// - Invalid messages must be ignored safely
+    /// - Invalid messages are reported, never forwarded
     ///
     /// DATA NORMALIZATION:
     /// - Symbols must be converted to internal format (BASE/QUOTE)
@@ -132,12 +396,153 @@ pub trait ExchangeAdapter: Send + Sync {
     /// - Called on every incoming WS message
     /// - Must be allocation-aware
     ///
-    /// TODO:
-    /// - Add structured error reporting (optional)
-    ///
     fn parse_message(
         &self,
         raw: &str,
         exchange_name: &str,
     ) -> ParseResult;
+
+    /// Returns the reply to an application-level ping sent by the
+    /// exchange (e.g. KuCoin `{"type":"ping"}` → `{"type":"pong"}`).
+    ///
+    /// Checked by the runner before `parse_message`; frames answered
+    /// here are not parsed. Default: None (no server pings)
+    ///
+    fn pong_for(&self, _raw: &str) -> Option<Value> {
+        None
+    }
+
+    /// Returns true if subscriptions must wait for a welcome frame
+    /// (KuCoin only accepts subscribes after `{"type":"welcome"}`).
+    ///
+    /// The runner then reads frames until `is_welcome` matches
+    /// before subscribing. Default: false (subscribe right away)
+    ///
+    fn wait_for_welcome(&self) -> bool {
+        false
+    }
+
+    /// Returns true if `raw` is the welcome frame that allows
+    /// subscribing (see `wait_for_welcome`). Default: false
+    ///
+    fn is_welcome(&self, _raw: &str) -> bool {
+        false
+    }
+
+    /// Returns the client-side ping message, if the exchange needs one.
+    ///
+    /// Sent by the runner every `ping_interval()` (or the configured
+    /// `ping_interval_ms`). Default: no application-level ping; a
+    /// configured interval then sends WebSocket ping frames.
+    ///
+    fn ping_message(&self) -> Option<Value> {
+        None
+    }
+
+    /// Returns the default client-side ping interval.
+    ///
+    /// Default: None (no client ping unless configured).
+    ///
+    fn ping_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Returns the maximum lifetime of a connection.
+    ///
+    /// For exchanges that force a disconnect after a fixed time
+    /// (Binance: 24h), the runner closes and reconnects before the
+    /// limit. Overridden by `max_connection_age_ms`.
+    ///
+    /// Default: None (no limit)
+    ///
+    fn max_connection_age(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Returns the trade pairs per connection used when the config
+    /// omits `chunking.trades_per_connection`.
+    ///
+    /// Exchanges tolerating large subscriptions (Binance) override
+    /// this with a bigger chunk.
+    ///
+    /// Default: 10
+    ///
+    fn default_trades_per_connection(&self) -> usize {
+        10
+    }
+
+    /// Returns true if trades and orderbooks can share one connection.
+    ///
+    /// Enables the `multiplex` exchange option: both channels are
+    /// subscribed on the same socket, halving the connection count.
+    /// Requires `parse_message` to handle both channels of any frame.
+    ///
+    /// Default: false
+    ///
+    fn supports_multiplex(&self) -> bool {
+        false
+    }
+
+    /// Resolves the WS URL before each connect, for exchanges handing
+    /// out endpoints / tokens via REST (KuCoin bullet-public).
+    ///
+    /// RETURNS:
+    /// - Some(url): connect there
+    /// - None: use `subscribe_url` / `ws_url_for` (default)
+    /// - Err: counted as a failed connect, retried later
+    ///
+    async fn connect_url(&self, _timeout: Duration) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Returns all tradable spot symbols of this exchange.
+    ///
+    /// Used to expand the `"*"` wildcard in pair lists at startup.
+    ///
+    /// OUTPUT:
+    /// - Symbols in internal format (BASE/QUOTE)
+    ///
+    /// NOTES:
+    /// - Allowed to perform network I/O (like `connect_url` and
+    ///   `recent_trades`)
+    /// - It is called once per exchange before chunking
+    /// - Default: unsupported (wildcards are rejected)
+    ///
+    async fn all_symbols(&self) -> anyhow::Result<Vec<String>> {
+        Err(anyhow::anyhow!(
+            "{} does not support wildcard subscriptions",
+            self.name()
+        ))
+    }
+
+    /// Returns the most recent trades of `pair` via REST.
+    ///
+    /// Used to fill the gap after a reconnect (`backfill_trades`).
+    ///
+    /// OUTPUT:
+    /// - Trades in internal format, each with `trade_id` set
+    ///
+    /// NOTES:
+    /// - Network I/O allowed (like `all_symbols`)
+    /// - Default: unsupported
+    ///
+    async fn recent_trades(&self, _pair: &str, _timeout: Duration) -> anyhow::Result<Vec<TradeData>> {
+        Err(anyhow::anyhow!(
+            "{} does not support trade backfill",
+            self.name()
+        ))
+    }
+
+    /// Returns a REST URL serving an orderbook snapshot of `pair`,
+    /// for exchanges streaming books as deltas only.
+    ///
+    /// Fetched after every connect to seed the local books
+    /// (`orderbook.forward = false`). The response must use a layout
+    /// the REST poller understands (`bids` / `asks`).
+    ///
+    /// Default: None (the WS feed sends snapshots itself)
+    ///
+    fn book_snapshot_url(&self, _pair: &str, _depth: usize) -> Option<String> {
+        None
+    }
 }
@@ -1,12 +1,72 @@
 use serde_json::{Value, json};
+use std::time::Duration;
 
 use crate::{
+    net,
     util,
-    schema::{MarketMessage, TradeData, BookData},
+    schema::{MarketMessage, TradeData, BookData, FundingData},
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, is_subscription_limit_error, AdapterMetadata, SymbolCase};
+
+/// Extracts tradable spot symbols from a `/api/v3/exchangeInfo` response.
+///
+/// Only symbols with `status == "TRADING"` and spot trading enabled
+/// are returned, converted to internal format (BASE/QUOTE).
+fn symbols_from_exchange_info(info: &Value) -> Vec<String> {
+    info.get("symbols")
+        .and_then(|v| v.as_array())
+        .map(|symbols| {
+            symbols
+                .iter()
+                .filter(|s| s.get("status").and_then(|v| v.as_str()) == Some("TRADING"))
+                .filter(|s| s.get("isSpotTradingAllowed").and_then(|v| v.as_bool()) != Some(false))
+                .filter_map(|s| {
+                    let base = s.get("baseAsset")?.as_str()?;
+                    let quote = s.get("quoteAsset")?.as_str()?;
+                    Some(format!("{}/{}", base.to_uppercase(), quote.to_uppercase()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Book update interval of the `@depth@100ms` streams
+const FAST_DEPTH_INTERVAL_MS: u64 = 100;
+
+/// Maps pairs to stream names, e.g. `btcusdt@trade`.
+fn stream_names(channel: ChannelType, pairs: &[String], config: &ExchangeConfig) -> Vec<String> {
+    // 100ms book updates only when asked for: ~10x the volume
+    let fast_books = config.orderbook
+        .as_ref()
+        .is_some_and(|o| o.update_interval_ms <= FAST_DEPTH_INTERVAL_MS);
+
+    pairs.iter().map(|p| {
+        let symbol = BinanceAdapter.subscribe_symbol(p);
+
+        match channel {
+            ChannelType::Trades => {
+                format!("{}@trade", symbol)
+            }
+
+            // Default cadence is 1000ms; `orderbook.update_interval_ms`
+            // of 100 or less selects the high-frequency stream
+            ChannelType::OrderBooks if fast_books => {
+                format!("{}@depth@100ms", symbol)
+            }
+
+            ChannelType::OrderBooks => {
+                format!("{}@depth", symbol)
+            }
+
+            // USDⓈ-M futures stream (see `ws_url_for`), 3s updates
+            ChannelType::Funding => {
+                format!("{}@markPrice", symbol)
+            }
+        }
+    }).collect()
+}
 
 /// Binance (Global) WebSocket adapter
 ///
@@ -14,6 +74,9 @@ use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
 /// https://developers.binance.com/docs/binance-spot-api-docs/websocket-market-streams
 ///
 /// Supports MULTI combined streams per connection.
+///
+/// `pairs.funding` subscribes `{symbol}@markPrice` on the
+/// USDⓈ-M futures endpoint.
 pub struct BinanceAdapter;
 
 #[async_trait::async_trait]
@@ -23,40 +86,139 @@ impl ExchangeAdapter for BinanceAdapter {
         "binance"
     }
 
+    fn symbol_case(&self) -> SymbolCase {
+        SymbolCase::Lower
+    }
+
     fn ws_url(&self) -> &'static str {
         "wss://stream.binance.com:9443/ws"
     }
 
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades, ChannelType::OrderBooks, ChannelType::Funding],
+            auth_required: false,
+            max_streams_per_connection: Some(1024),
+        }
+    }
+
+    /// Mark price / funding streams only exist on the
+    /// USDⓈ-M futures endpoint.
+    fn ws_url_for(&self, channels: &[ChannelType]) -> &'static str {
+        if channels.iter().any(|c| matches!(c, ChannelType::Funding)) {
+            "wss://fstream.binance.com/ws"
+        } else {
+            self.ws_url()
+        }
+    }
+
     fn build_subscribe_message(
         &self,
         channel: ChannelType,
         pairs: &[String],
-        _config: &ExchangeConfig,
+        config: &ExchangeConfig,
     ) -> Value {
 
-        let streams: Vec<String> = pairs.iter().map(|p| {
-            let symbol = util::symbol_to_exchange(self.name(), p).to_lowercase();
-
-            match channel {
-                ChannelType::Trades => {
-                    format!("{}@trade", symbol)
-                }
-
-                ChannelType::OrderBooks => {
-                    // Binance Global supports depth params,
-                    // but we intentionally use the safest default
-                    format!("{}@depth", symbol)
-                }
-            }
-        }).collect();
-
         json!({
             "method": "SUBSCRIBE",
-            "params": streams,
+            "params": stream_names(channel, pairs, config),
             "id": util::now_ms()
         })
     }
 
+    /// Combined-stream endpoint (`combined_streams`):
+    /// `/stream?streams=btcusdt@trade/ethusdt@trade`
+    ///
+    /// Streams are subscribed via the URL, no SUBSCRIBE frame is
+    /// sent, so large chunks do not hit the subscribe rate limit.
+    fn subscribe_url(
+        &self,
+        channel: ChannelType,
+        pairs: &[String],
+        config: &ExchangeConfig,
+    ) -> Option<String> {
+        if !config.combined_streams.unwrap_or(false) {
+            return None;
+        }
+
+        let base = self.ws_url_for(&[channel]).strip_suffix("/ws")?;
+
+        Some(format!("{}/stream?streams={}", base, stream_names(channel, pairs, config).join("/")))
+    }
+
+    /// Binance drops market stream connections after 24h;
+    /// recycle them well ahead of that.
+    fn max_connection_age(&self) -> Option<Duration> {
+        Some(Duration::from_secs(23 * 60 * 60))
+    }
+
+    /// Up to 1024 streams per connection; 100 keeps the subscribe
+    /// frames well below the message rate limit.
+    fn default_trades_per_connection(&self) -> usize {
+        100
+    }
+
+    /// `@depth` streams send deltas only
+    fn book_snapshot_url(&self, pair: &str, depth: usize) -> Option<String> {
+        Some(format!(
+            "https://api.binance.com/api/v3/depth?symbol={}&limit={}",
+            util::symbol_to_exchange(self.name(), pair),
+            depth
+        ))
+    }
+
+    async fn all_symbols(&self) -> anyhow::Result<Vec<String>> {
+        let info: Value = net::http_client()
+            .get("https://api.binance.com/api/v3/exchangeInfo")
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(symbols_from_exchange_info(&info))
+    }
+
+    /// `/api/v3/trades`: the last 1000 trades
+    async fn recent_trades(&self, pair: &str, timeout: Duration) -> anyhow::Result<Vec<TradeData>> {
+        let url = format!(
+            "https://api.binance.com/api/v3/trades?symbol={}&limit=1000",
+            util::symbol_to_exchange(self.name(), pair)
+        );
+
+        let trades: Vec<Value> = net::http_client()
+            .get(url)
+            .timeout(timeout)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // [ { "id": 28457, "price": "4.0", "qty": "12.0", "time": 1499865549590, "isBuyerMaker": true } ]
+        Ok(trades
+            .iter()
+            .filter_map(|t| {
+                Some(TradeData {
+                    exchange: self.name().to_string(),
+                    symbol: pair.to_string(),
+                    timestamp: t.get("time")?.as_i64()?,
+                    price: t.get("price")?.as_str()?.to_string(),
+                    amount: t.get("qty")?.as_str()?.to_string(),
+                    side: if t.get("isBuyerMaker")?.as_bool()? {
+                        "sell".into()
+                    } else {
+                        "buy".into()
+                    },
+                    trade_id: Some(t.get("id")?.as_i64()?.to_string()),
+                    event_ts: None,
+                    source: None,
+                })
+            })
+            .collect())
+    }
+
     fn parse_message(
         &self,
         raw: &str,
@@ -65,9 +227,26 @@ impl ExchangeAdapter for BinanceAdapter {
 
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
+        // --------------------------------------------------
+        // Error responses
+        // { "error": { "code": 2, "msg": "..." }, "id": 123 }
+        // { "code": 2, "msg": "...", "id": 123 }
+        // --------------------------------------------------
+        if let Some(msg) = v
+            .get("error")
+            .unwrap_or(&v)
+            .get("msg")
+            .and_then(|m| m.as_str())
+        {
+            if is_subscription_limit_error(msg) {
+                return ParseResult::SubscriptionLimit(msg.to_string());
+            }
+            return ParseResult::Error(ParseError::exchange(msg));
+        }
+
         // --------------------------------------------------
         // Binance control / ack messages
         // --------------------------------------------------
@@ -86,9 +265,20 @@ impl ExchangeAdapter for BinanceAdapter {
             &v
         };
 
+        // Symbol of the event; falls back to the combined-stream
+        // name ("btcusdt@trade") for payloads without "s"
+        let raw_symbol = data["s"]
+            .as_str()
+            .map(str::to_string)
+            .or_else(|| {
+                let stream = v.get("stream")?.as_str()?;
+                Some(stream.split('@').next()?.to_uppercase())
+            })
+            .unwrap_or_default();
+
         let event = match data.get("e").and_then(|e| e.as_str()) {
             Some(e) => e,
-            None => return ParseResult::Control, // ping / keepalive / unknown control
+            None => return ParseResult::Ignored, // unknown frame
         };
 
         match event {
@@ -97,29 +287,39 @@ impl ExchangeAdapter for BinanceAdapter {
             // TRADES
             // -----------------------------
             "trade" => {
+                let Some(price) = data["p"].as_str() else {
+                    return ParseResult::Error(ParseError::missing_field("p"));
+                };
+                let Some(amount) = data["q"].as_str() else {
+                    return ParseResult::Error(ParseError::missing_field("q"));
+                };
+
                 let msg = MarketMessage::Trade(TradeData {
                     exchange: exchange.to_string(),
                     symbol: util::symbol_from_exchange(
                         exchange,
-                        data["s"].as_str().unwrap_or_default()
+                        &raw_symbol
                     ),
                     timestamp: data["T"]
                         .as_i64()
                         .unwrap_or_else(util::now_ms),
-                    price: data["p"].as_str().unwrap_or("0").to_string(),
-                    amount: data["q"].as_str().unwrap_or("0").to_string(),
+                    price: price.to_string(),
+                    amount: amount.to_string(),
                     side: if data["m"].as_bool().unwrap_or(false) {
                         "sell".into()
                     } else {
                         "buy".into()
                     },
+                    trade_id: data["t"].as_i64().map(|id| id.to_string()),
+                    event_ts: data["E"].as_i64(),
+                    source: None,
                 });
 
                 ParseResult::Market(msg)
             }
 
             // -----------------------------
-            // ORDER BOOK (delta)
+            // ORDER BOOK (delta, amount 0 removes the level)
             // -----------------------------
             "depthUpdate" => {
                 let asks = data["a"]
@@ -129,9 +329,6 @@ impl ExchangeAdapter for BinanceAdapter {
                     .filter_map(|x| {
                         let price = x.get(0)?.as_str()?;
                         let qty   = x.get(1)?.as_str()?;
-                        if qty == "0.00000000" {
-                            return None;
-                        }
                         Some([price.to_string(), qty.to_string()])
                     })
                     .collect();
@@ -143,9 +340,6 @@ impl ExchangeAdapter for BinanceAdapter {
                     .filter_map(|x| {
                         let price = x.get(0)?.as_str()?;
                         let qty   = x.get(1)?.as_str()?;
-                        if qty == "0.00000000" {
-                            return None;
-                        }
                         Some([price.to_string(), qty.to_string()])
                     })
                     .collect();
@@ -154,13 +348,40 @@ impl ExchangeAdapter for BinanceAdapter {
                     exchange: exchange.to_string(),
                     symbol: util::symbol_from_exchange(
                         exchange,
-                        data["s"].as_str().unwrap_or_default()
+                        &raw_symbol
                     ),
                     timestamp: data["E"]
                         .as_i64()
                         .unwrap_or_else(util::now_ms),
                     asks,
                     bids,
+                    source: None,
+                    snapshot: false,
+                });
+
+                ParseResult::Market(msg)
+            }
+
+            // -----------------------------
+            // MARK PRICE / FUNDING (futures)
+            // -----------------------------
+            "markPriceUpdate" => {
+                let msg = MarketMessage::Funding(FundingData {
+                    exchange: exchange.to_string(),
+                    symbol: util::symbol_from_exchange(
+                        exchange,
+                        &raw_symbol
+                    ),
+                    timestamp: data["E"]
+                        .as_i64()
+                        .unwrap_or_else(util::now_ms),
+                    mark_price: data["p"].as_str().map(str::to_string),
+                    funding_rate: data["r"]
+                        .as_str()
+                        .filter(|r| !r.is_empty())
+                        .map(str::to_string),
+                    next_funding_time: data["T"].as_i64().filter(|t| *t > 0),
+                    source: None,
                 });
 
                 ParseResult::Market(msg)
@@ -169,8 +390,134 @@ impl ExchangeAdapter for BinanceAdapter {
             // -----------------------------
             // Everything else
             // -----------------------------
-            _ => ParseResult::Control,
+            _ => ParseResult::Ignored,
         }
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exchange_info_lists_trading_spot_pairs() {
+        let info = json!({
+            "symbols": [
+                { "status": "TRADING", "baseAsset": "BTC", "quoteAsset": "USDT", "isSpotTradingAllowed": true },
+                { "status": "BREAK", "baseAsset": "LUNA", "quoteAsset": "USDT", "isSpotTradingAllowed": true },
+                { "status": "TRADING", "baseAsset": "ETH", "quoteAsset": "BTC" },
+                { "status": "TRADING", "baseAsset": "X", "quoteAsset": "USDT", "isSpotTradingAllowed": false }
+            ]
+        });
+
+        assert_eq!(symbols_from_exchange_info(&info), ["BTC/USDT", "ETH/BTC"]);
+        assert!(symbols_from_exchange_info(&json!({ "code": -1003 })).is_empty());
+    }
+
+    #[test]
+    fn mark_price_updates_are_funding() {
+        let raw = r#"{"stream":"btcusdt@markPrice","data":{"e":"markPriceUpdate","E":1700000000000,
+            "s":"BTCUSDT","p":"42000.50","i":"42001.00","r":"0.00010000","T":1700006400000}}"#;
+
+        let ParseResult::Market(funding @ MarketMessage::Funding(_)) = BinanceAdapter.parse_message(raw, "binance") else {
+            panic!("expected funding");
+        };
+
+        assert_eq!(
+            serde_json::to_value(&funding).unwrap(),
+            json!({
+                "type": "funding", "exchange": "binance", "symbol": "BTC/USDT",
+                "timestamp": 1_700_000_000_000_i64, "mark_price": "42000.50",
+                "funding_rate": "0.00010000", "next_funding_time": 1_700_006_400_000_i64,
+            })
+        );
+    }
+
+    #[test]
+    fn fast_book_streams_only_when_configured() {
+        let config = |orderbook: Value| -> ExchangeConfig {
+            serde_json::from_value(json!({
+                "name": "binance",
+                "enabled": true,
+                "pairs": { "trades": [], "orderbooks": ["BTC/USDT"] },
+                "orderbook": orderbook
+            }))
+            .unwrap()
+        };
+        let pairs = ["BTC/USDT".to_string()];
+        let streams = |orderbook| stream_names(ChannelType::OrderBooks, &pairs, &config(orderbook));
+
+        assert_eq!(streams(json!({ "depth": 20, "update_interval_ms": 100 })), ["btcusdt@depth@100ms"]);
+        assert_eq!(streams(json!({ "depth": 20, "update_interval_ms": 1000 })), ["btcusdt@depth"]);
+        assert_eq!(streams(Value::Null), ["btcusdt@depth"]);
+    }
+
+    #[test]
+    fn combined_streams_subscribe_via_the_url() {
+        let config = |combined: bool| -> ExchangeConfig {
+            serde_json::from_value(json!({
+                "name": "binance",
+                "enabled": true,
+                "pairs": { "trades": ["BTC/USDT", "ETH/USDT"], "orderbooks": [] },
+                "combined_streams": combined
+            }))
+            .unwrap()
+        };
+        let pairs = ["BTC/USDT".to_string(), "ETH/USDT".to_string()];
+
+        assert_eq!(
+            BinanceAdapter.subscribe_url(ChannelType::Trades, &pairs, &config(true)).unwrap(),
+            "wss://stream.binance.com:9443/stream?streams=btcusdt@trade/ethusdt@trade"
+        );
+        assert_eq!(
+            BinanceAdapter.subscribe_url(ChannelType::Funding, &pairs[..1], &config(true)).unwrap(),
+            "wss://fstream.binance.com/stream?streams=btcusdt@markPrice"
+        );
+        assert_eq!(BinanceAdapter.subscribe_url(ChannelType::Trades, &pairs, &config(false)), None);
+    }
+
+    #[test]
+    fn trades_carry_the_event_time() {
+        let raw = r#"{"stream":"btcusdt@trade","data":{"e":"trade","E":1700000000005,"s":"BTCUSDT",
+            "t":12345,"p":"42000.10","q":"0.500","T":1700000000001,"m":false}}"#;
+
+        let ParseResult::Market(MarketMessage::Trade(trade)) = BinanceAdapter.parse_message(raw, "binance") else {
+            panic!("expected a trade");
+        };
+
+        assert_eq!(trade.timestamp, 1_700_000_000_001);
+        assert_eq!(trade.event_ts, Some(1_700_000_000_005));
+        assert_eq!(trade.side, "buy");
+    }
+
+    #[test]
+    fn trades_without_price_are_rejected() {
+        let raw = r#"{"stream":"btcusdt@trade","data":{"e":"trade","E":1700000000005,"s":"BTCUSDT",
+            "t":12345,"q":"0.500","T":1700000000001,"m":true}}"#;
+
+        let ParseResult::Error(err) = BinanceAdapter.parse_message(raw, "binance") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err, ParseError::missing_field("p"));
+        assert_eq!(err.to_string(), "missing field 'p'");
+    }
+
+    #[test]
+    fn stream_limit_errors_are_subscription_limits() {
+        let frames = [
+            r#"{"error":{"code":1,"msg":"Too many requests"},"id":3}"#,
+            r#"{"code":2,"msg":"Exceeded the maximum number of streams","id":4}"#,
+        ];
+
+        for raw in frames {
+            assert!(
+                matches!(BinanceAdapter.parse_message(raw, "binance"), ParseResult::SubscriptionLimit(_)),
+                "{raw}"
+            );
+        }
+
+        let invalid = r#"{"error":{"code":2,"msg":"Invalid request"},"id":5}"#;
+        assert!(matches!(BinanceAdapter.parse_message(invalid, "binance"), ParseResult::Error(_)));
+    }
+}
@@ -1,4 +1,5 @@
 use serde_json::{Value, json};
+use std::time::Duration;
 
 use crate::{
     util,
@@ -6,7 +7,7 @@ use crate::{
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, is_subscription_limit_error, AdapterMetadata, SymbolCase};
 
 /// Binance US WebSocket adapter
 ///
@@ -23,10 +24,24 @@ impl ExchangeAdapter for BinanceUsAdapter {
         "binanceus"
     }
 
+    fn symbol_case(&self) -> SymbolCase {
+        SymbolCase::Lower
+    }
+
     fn ws_url(&self) -> &'static str {
         "wss://stream.binance.us:9443/ws"
     }
 
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades, ChannelType::OrderBooks],
+            auth_required: false,
+            max_streams_per_connection: Some(1024),
+        }
+    }
+
     fn build_subscribe_message(
         &self,
         channel: ChannelType,
@@ -34,13 +49,17 @@ impl ExchangeAdapter for BinanceUsAdapter {
         _config: &ExchangeConfig,
     ) -> Value {
 
-        let streams: Vec<String> = pairs.iter().map(|p| {
-            let symbol = util::symbol_to_exchange(self.name(), p).to_lowercase();
+        let stream = match channel {
+            ChannelType::Trades => "trade",
+            ChannelType::OrderBooks => "depth",
 
-            match channel {
-                ChannelType::Trades => format!("{}@trade", symbol),
-                ChannelType::OrderBooks => format!("{}@depth", symbol),
-            }
+            // Binance.US lists no perpetuals – no funding streams
+            ChannelType::Funding => return json!({}),
+        };
+
+        let streams: Vec<String> = pairs.iter().map(|p| {
+            let symbol = self.subscribe_symbol(p);
+            format!("{}@{}", symbol, stream)
         }).collect();
 
         json!({
@@ -50,6 +69,27 @@ impl ExchangeAdapter for BinanceUsAdapter {
         })
     }
 
+    /// Binance drops market stream connections after 24h;
+    /// recycle them well ahead of that.
+    fn max_connection_age(&self) -> Option<Duration> {
+        Some(Duration::from_secs(23 * 60 * 60))
+    }
+
+    /// `@depth` streams send deltas only
+    fn book_snapshot_url(&self, pair: &str, depth: usize) -> Option<String> {
+        Some(format!(
+            "https://api.binance.us/api/v3/depth?symbol={}&limit={}",
+            util::symbol_to_exchange(self.name(), pair),
+            depth
+        ))
+    }
+
+    /// Up to 1024 streams per connection; 100 keeps the subscribe
+    /// frames well below the message rate limit.
+    fn default_trades_per_connection(&self) -> usize {
+        100
+    }
+
     fn parse_message(
         &self,
         raw: &str,
@@ -58,9 +98,26 @@ impl ExchangeAdapter for BinanceUsAdapter {
 
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
+        // --------------------------------------------------
+        // Error responses
+        // { "error": { "code": 2, "msg": "..." }, "id": 123 }
+        // { "code": 2, "msg": "...", "id": 123 }
+        // --------------------------------------------------
+        if let Some(msg) = v
+            .get("error")
+            .unwrap_or(&v)
+            .get("msg")
+            .and_then(|m| m.as_str())
+        {
+            if is_subscription_limit_error(msg) {
+                return ParseResult::SubscriptionLimit(msg.to_string());
+            }
+            return ParseResult::Error(ParseError::exchange(msg));
+        }
+
         // --------------------------------------------------
         // Subscribe ACK / control message
         // { "result": null, "id": ... }
@@ -80,7 +137,7 @@ impl ExchangeAdapter for BinanceUsAdapter {
 
         let event = match data.get("e").and_then(|e| e.as_str()) {
             Some(e) => e,
-            None => return ParseResult::Control,
+            None => return ParseResult::Ignored,
         };
 
         match event {
@@ -105,13 +162,16 @@ impl ExchangeAdapter for BinanceUsAdapter {
                     } else {
                         "buy".into()
                     },
+                    trade_id: None,
+                    event_ts: data["E"].as_i64(),
+                    source: None,
                 });
 
                 ParseResult::Market(msg)
             }
 
             // -----------------------------
-            // ORDER BOOK (delta)
+            // ORDER BOOK (delta, amount 0 removes the level)
             // -----------------------------
             "depthUpdate" => {
                 let asks = data["a"]
@@ -121,9 +181,6 @@ impl ExchangeAdapter for BinanceUsAdapter {
                     .filter_map(|x| {
                         let price = x.get(0)?.as_str()?;
                         let qty   = x.get(1)?.as_str()?;
-                        if qty == "0.00000000" {
-                            return None;
-                        }
                         Some([price.to_string(), qty.to_string()])
                     })
                     .collect();
@@ -135,9 +192,6 @@ impl ExchangeAdapter for BinanceUsAdapter {
                     .filter_map(|x| {
                         let price = x.get(0)?.as_str()?;
                         let qty   = x.get(1)?.as_str()?;
-                        if qty == "0.00000000" {
-                            return None;
-                        }
                         Some([price.to_string(), qty.to_string()])
                     })
                     .collect();
@@ -153,6 +207,8 @@ impl ExchangeAdapter for BinanceUsAdapter {
                         .unwrap_or_else(util::now_ms),
                     asks,
                     bids,
+                    source: None,
+                    snapshot: false,
                 });
 
                 ParseResult::Market(msg)
@@ -161,7 +217,7 @@ impl ExchangeAdapter for BinanceUsAdapter {
             // -----------------------------
             // Everything else
             // -----------------------------
-            _ => ParseResult::Control,
+            _ => ParseResult::Ignored,
         }
     }
 }
@@ -1,5 +1,5 @@
 use serde_json::{Value, json};
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::sync::Mutex;
 
 use crate::{
@@ -8,7 +8,7 @@ use crate::{
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, AdapterMetadata, SubscribeGranularity};
 
 /// Bitfinex WebSocket adapter (Spot trades)
 ///
@@ -18,20 +18,15 @@ use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
 /// Supports:
 /// - Multiple symbols per WS
 /// - Channel-ID routing
-/// - Trade batches via internal buffer
 pub struct BitfinexAdapter {
     /// chanId → symbol
     chan_map: Mutex<HashMap<i64, String>>,
-
-    /// Parsed trades waiting to be emitted
-    trade_buffer: Mutex<VecDeque<MarketMessage>>,
 }
 
 impl BitfinexAdapter {
     pub fn new() -> Self {
         Self {
             chan_map: Mutex::new(HashMap::new()),
-            trade_buffer: Mutex::new(VecDeque::with_capacity(64)),
         }
     }
 }
@@ -62,16 +57,35 @@ impl ExchangeAdapter for BitfinexAdapter {
         "bitfinex"
     }
 
+    /// One subscribe message per symbol
+    fn subscribe_granularity(&self) -> SubscribeGranularity {
+        SubscribeGranularity::PerSymbol
+    }
+
     fn ws_url(&self) -> &'static str {
         "wss://api-pub.bitfinex.com/ws/2"
     }
 
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades],
+            auth_required: false,
+            max_streams_per_connection: None,
+        }
+    }
+
     fn build_subscribe_message(
         &self,
         channel: ChannelType,
         pairs: &[String],
         _config: &ExchangeConfig,
     ) -> Value {
+        if pairs.is_empty() {
+            return json!({});
+        }
+
         match channel {
             ChannelType::Trades => {
                 // Bitfinex: ONLY FIRST SYMBOL per message
@@ -82,6 +96,8 @@ impl ExchangeAdapter for BitfinexAdapter {
             })
             }
             ChannelType::OrderBooks => json!({}),
+            // Derivatives status channel (funding) not implemented
+            ChannelType::Funding => json!({}),
         }
     }
 
@@ -91,30 +107,24 @@ impl ExchangeAdapter for BitfinexAdapter {
         exchange: &str,
     ) -> ParseResult {
         //println!("[RAW {}] {}", exchange, raw);
-        // 1️⃣ Emit buffered trades first
-        if let Some(msg) = self.trade_buffer.lock().unwrap().pop_front() {
-            return ParseResult::Market(msg);
-        }
-
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
         // --------------------------------------------------
         // Control / subscribe messages (objects)
         // --------------------------------------------------
         if v.is_object() {
-            if v.get("event").and_then(|v| v.as_str()) == Some("subscribed") {
-                if v.get("channel").and_then(|v| v.as_str()) == Some("trades") {
-                    if let (Some(chan_id), Some(symbol)) = (
-                        v.get("chanId").and_then(|v| v.as_i64()),
-                        v.get("symbol").and_then(|v| v.as_str()),
-                    ) {
-                        let norm = util::symbol_from_exchange(exchange, symbol);
-                        self.chan_map.lock().unwrap().insert(chan_id, norm);
-                    }
-                }
+            if v.get("event").and_then(|v| v.as_str()) == Some("subscribed")
+                && v.get("channel").and_then(|v| v.as_str()) == Some("trades")
+                && let (Some(chan_id), Some(symbol)) = (
+                    v.get("chanId").and_then(|v| v.as_i64()),
+                    v.get("symbol").and_then(|v| v.as_str()),
+                )
+            {
+                let norm = util::symbol_from_exchange(exchange, symbol);
+                self.chan_map.lock().unwrap_or_else(|e| e.into_inner()).insert(chan_id, norm);
             }
             return ParseResult::Control;
         }
@@ -124,12 +134,12 @@ impl ExchangeAdapter for BitfinexAdapter {
         // --------------------------------------------------
         let arr = match v.as_array() {
             Some(a) if a.len() >= 2 => a,
-            _ => return ParseResult::Control,
+            _ => return ParseResult::Ignored,
         };
 
-        let chan_id = match arr.get(0).and_then(|v| v.as_i64()) {
+        let chan_id = match arr.first().and_then(|v| v.as_i64()) {
             Some(id) => id,
-            None => return ParseResult::Control,
+            None => return ParseResult::Ignored,
         };
 
         let msg_type = arr.get(1).and_then(|v| v.as_str());
@@ -144,9 +154,9 @@ impl ExchangeAdapter for BitfinexAdapter {
             _ => return ParseResult::Control,
         };
 
-        let symbol = match self.chan_map.lock().unwrap().get(&chan_id) {
+        let symbol = match self.chan_map.lock().unwrap_or_else(|e| e.into_inner()).get(&chan_id) {
             Some(s) => s.clone(),
-            None => return ParseResult::Control,
+            None => return ParseResult::Ignored,
         };
 
         let ts = trade.get(1)
@@ -175,15 +185,11 @@ impl ExchangeAdapter for BitfinexAdapter {
             price,
             amount,
             side,
+            trade_id: None,
+            event_ts: None,
+            source: None,
         });
 
-        self.trade_buffer.lock().unwrap().push_back(msg);
-
-        self.trade_buffer
-            .lock()
-            .unwrap()
-            .pop_front()
-            .map(ParseResult::Market)
-            .unwrap_or(ParseResult::Control)
+        ParseResult::Market(msg)
     }
 }
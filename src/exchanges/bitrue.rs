@@ -6,7 +6,7 @@ use crate::{
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, AdapterMetadata, SymbolCase};
 
 /// Bitrue WebSocket adapter
 ///
@@ -23,10 +23,24 @@ impl ExchangeAdapter for BitrueAdapter {
         "bitrue"
     }
 
+    fn symbol_case(&self) -> SymbolCase {
+        SymbolCase::Lower
+    }
+
     fn ws_url(&self) -> &'static str {
         "wss://fmarket-ws.bitrue.com/kline-api/ws"
     }
 
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades],
+            auth_required: false,
+            max_streams_per_connection: None,
+        }
+    }
+
     fn build_subscribe_message(
         &self,
         channel: ChannelType,
@@ -43,8 +57,7 @@ impl ExchangeAdapter for BitrueAdapter {
                 let pair = &pairs[0];
 
                 // BTC/USDT → btcusdt
-                let sym = util::symbol_to_exchange(self.name(), pair)
-                    .to_lowercase();
+                let sym = self.subscribe_symbol(pair);
 
                 json!({
                     "event": "sub",
@@ -55,6 +68,9 @@ impl ExchangeAdapter for BitrueAdapter {
             }
 
             ChannelType::OrderBooks => json!({}),
+
+            // Funding not implemented (trade stream only)
+            ChannelType::Funding => json!({}),
         }
     }
 
@@ -66,7 +82,7 @@ impl ExchangeAdapter for BitrueAdapter {
 
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
         // --------------------------------------------------
@@ -87,7 +103,9 @@ impl ExchangeAdapter for BitrueAdapter {
         // --------------------------------------------------
         let sym = match channel.split('_').nth(2) {
             Some(s) => s,
-            None => return ParseResult::Error,
+            None => {
+                return ParseResult::Error(ParseError::malformed(format!("channel without symbol: {channel}")));
+            }
         };
 
         let symbol = util::symbol_from_exchange(exchange, sym);
@@ -124,6 +142,9 @@ impl ExchangeAdapter for BitrueAdapter {
                 .and_then(|v| v.as_str())
                 .unwrap_or("unknown")
                 .to_lowercase(),
+            trade_id: None,
+            event_ts: None,
+            source: None,
         });
 
         ParseResult::Market(msg)
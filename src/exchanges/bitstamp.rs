@@ -6,7 +6,7 @@ use crate::{
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, AdapterMetadata, SymbolCase, SubscribeGranularity};
 
 /// Bitstamp WebSocket adapter (Spot trades)
 ///
@@ -17,9 +17,33 @@ use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
 /// - No auth
 /// - One trade per message
 /// - price / amount are strings (perfect)
-/// - type: 0 = buy, 1 = sell
+/// - type: 0 = buy, 1 = sell (taker side, see `taker_side`)
 pub struct BitstampAdapter;
 
+/// Returns the taker side of a Bitstamp trade.
+///
+/// `type` is the side of the taker order (0 = buy, 1 = sell), the
+/// same convention as everywhere else: "buy" = the buyer took
+/// liquidity (Binance `m: false`).
+///
+/// Without `type`, the taker is the newer of the two orders, i.e.
+/// the higher of `buy_order_id` / `sell_order_id`.
+fn taker_side(data: &Value) -> &'static str {
+    match data.get("type").and_then(|v| v.as_i64()) {
+        Some(0) => return "buy",
+        Some(1) => return "sell",
+        _ => {}
+    }
+
+    let order_id = |key| data.get(key).and_then(|v| v.as_i64());
+
+    match (order_id("buy_order_id"), order_id("sell_order_id")) {
+        (Some(buy), Some(sell)) if buy > sell => "buy",
+        (Some(buy), Some(sell)) if sell > buy => "sell",
+        _ => "unknown",
+    }
+}
+
 #[async_trait::async_trait]
 impl ExchangeAdapter for BitstampAdapter {
 
@@ -27,10 +51,29 @@ impl ExchangeAdapter for BitstampAdapter {
         "bitstamp"
     }
 
+    /// One subscribe message per symbol
+    fn subscribe_granularity(&self) -> SubscribeGranularity {
+        SubscribeGranularity::PerSymbol
+    }
+
+    fn symbol_case(&self) -> SymbolCase {
+        SymbolCase::Lower
+    }
+
     fn ws_url(&self) -> &'static str {
         "wss://ws.bitstamp.net"
     }
 
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades],
+            auth_required: false,
+            max_streams_per_connection: None,
+        }
+    }
+
     fn build_subscribe_message(
         &self,
         channel: ChannelType,
@@ -38,12 +81,15 @@ impl ExchangeAdapter for BitstampAdapter {
         _config: &ExchangeConfig,
     ) -> Value {
 
+        if pairs.is_empty() {
+            return json!({});
+        }
+
         match channel {
             ChannelType::Trades => {
                 let pair = &pairs[0];
 
-                let sym = util::symbol_to_exchange(self.name(), pair)
-                    .to_lowercase();
+                let sym = self.subscribe_symbol(pair);
 
                 json!({
                 "event": "bts:subscribe",
@@ -54,6 +100,9 @@ impl ExchangeAdapter for BitstampAdapter {
             }
 
             ChannelType::OrderBooks => json!({}),
+
+            // Bitstamp WS v2 has no funding channel
+            ChannelType::Funding => json!({}),
         }
     }
 
@@ -62,10 +111,10 @@ impl ExchangeAdapter for BitstampAdapter {
         raw: &str,
         exchange: &str,
     ) -> ParseResult {
-        println!("[RAW {}] {}", exchange, raw);
+        tracing::debug!("[RAW {}] {}", exchange, raw);
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
         // Ignore control / non-trade messages
@@ -105,11 +154,7 @@ impl ExchangeAdapter for BitstampAdapter {
             .map(|t| t / 1000) // µs → ms
             .unwrap_or_else(util::now_ms);
 
-        let side = match data.get("type").and_then(|v| v.as_i64()) {
-            Some(0) => "buy",
-            Some(1) => "sell",
-            _ => "unknown",
-        }.to_string();
+        let side = taker_side(data).to_string();
 
         let msg = MarketMessage::Trade(TradeData {
             exchange: exchange.to_string(),
@@ -118,8 +163,46 @@ impl ExchangeAdapter for BitstampAdapter {
             price,
             amount,
             side,
+            trade_id: None,
+            event_ts: None,
+            source: None,
         });
 
         ParseResult::Market(msg)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trade_type_is_the_taker_side() {
+        let raw = json!({
+            "event": "trade",
+            "channel": "live_trades_btcusd",
+            "data": {
+                "id": 1, "type": 0, "price_str": "42000.5", "amount_str": "0.25",
+                "microtimestamp": "1700000000123456", "buy_order_id": 1, "sell_order_id": 2
+            }
+        })
+        .to_string();
+
+        let ParseResult::Market(MarketMessage::Trade(trade)) = BitstampAdapter.parse_message(&raw, "bitstamp") else {
+            panic!("expected a trade");
+        };
+
+        // type 0: the buyer took liquidity, regardless of the order ids
+        assert_eq!(trade.side, "buy");
+        assert_eq!(trade.symbol, "BTC/USD");
+        assert_eq!(trade.timestamp, 1_700_000_000_123);
+        assert_eq!(taker_side(&json!({ "type": 1 })), "sell");
+    }
+
+    #[test]
+    fn without_type_the_newer_order_is_the_taker() {
+        assert_eq!(taker_side(&json!({ "buy_order_id": 9, "sell_order_id": 5 })), "buy");
+        assert_eq!(taker_side(&json!({ "buy_order_id": 5, "sell_order_id": 9 })), "sell");
+        assert_eq!(taker_side(&json!({ "buy_order_id": 5 })), "unknown");
+    }
+}
@@ -1,32 +1,75 @@
 use serde_json::{Value, json};
 
 use crate::{
-    util,
-    schema::{MarketMessage, TradeData, BookData},
+    util::{self, json},
+    schema::{MarketMessage, TradeData, BookData, FundingData},
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, is_subscription_limit_error, AdapterMetadata};
 
-/// Bybit Spot WebSocket adapter
+/// Bybit V5 WebSocket adapter, one instance per market
 ///
 /// WS:
-/// wss://stream.bybit.com/v5/public/spot
+/// - bybit:        wss://stream.bybit.com/v5/public/spot
+/// - bybitlinear:  wss://stream.bybit.com/v5/public/linear  (USDT / USDC perpetuals)
+/// - bybitinverse: wss://stream.bybit.com/v5/public/inverse (coin-margined, BTC/USD)
 ///
-/// Channels:
+/// Channels (identical frames on all markets):
 /// - publicTrade.{symbol}
 /// - orderbook.50.{symbol}
-pub struct BybitAdapter;
+/// - tickers.{symbol} (mark price / funding; spot uses the linear endpoint)
+pub struct BybitAdapter {
+    name: &'static str,
+    ws_url: &'static str,
+}
+
+/// Endpoint carrying the funding tickers of spot pairs
+const LINEAR_WS_URL: &str = "wss://stream.bybit.com/v5/public/linear";
+
+impl BybitAdapter {
+    pub fn spot() -> Self {
+        Self { name: "bybit", ws_url: "wss://stream.bybit.com/v5/public/spot" }
+    }
+
+    pub fn linear() -> Self {
+        Self { name: "bybitlinear", ws_url: LINEAR_WS_URL }
+    }
+
+    pub fn inverse() -> Self {
+        Self { name: "bybitinverse", ws_url: "wss://stream.bybit.com/v5/public/inverse" }
+    }
+}
 
 #[async_trait::async_trait]
 impl ExchangeAdapter for BybitAdapter {
 
     fn name(&self) -> &'static str {
-        "bybit"
+        self.name
     }
 
     fn ws_url(&self) -> &'static str {
-        "wss://stream.bybit.com/v5/public/spot"
+        self.ws_url
+    }
+
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades, ChannelType::OrderBooks, ChannelType::Funding],
+            auth_required: false,
+            max_streams_per_connection: None,
+        }
+    }
+
+    /// Spot tickers carry no funding fields; spot funding pairs use
+    /// the linear (USDT perpetual) tickers.
+    fn ws_url_for(&self, channels: &[ChannelType]) -> &'static str {
+        if self.name == "bybit" && channels.iter().any(|c| matches!(c, ChannelType::Funding)) {
+            LINEAR_WS_URL
+        } else {
+            self.ws_url()
+        }
     }
 
     fn build_subscribe_message(
@@ -45,6 +88,10 @@ impl ExchangeAdapter for BybitAdapter {
 
                 ChannelType::OrderBooks =>
                     format!("orderbook.50.{}", symbol),
+
+                // Perpetual tickers (see `ws_url_for`)
+                ChannelType::Funding =>
+                    format!("tickers.{}", symbol),
             }
         }).collect();
 
@@ -54,6 +101,10 @@ impl ExchangeAdapter for BybitAdapter {
         })
     }
 
+    fn supports_multiplex(&self) -> bool {
+        true
+    }
+
     fn parse_message(
         &self,
         raw: &str,
@@ -62,19 +113,27 @@ impl ExchangeAdapter for BybitAdapter {
 
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
         // --------------------------------------------------
         // Control messages (subscribe ack, pong, etc.)
+        // { "success": false, "ret_msg": "...", "op": "subscribe" }
         // --------------------------------------------------
         if v.get("op").is_some() {
+            if v.get("success").and_then(|v| v.as_bool()) == Some(false) {
+                let msg = v.get("ret_msg").and_then(|v| v.as_str()).unwrap_or_default();
+                if is_subscription_limit_error(msg) {
+                    return ParseResult::SubscriptionLimit(msg.to_string());
+                }
+                return ParseResult::Error(ParseError::exchange(msg));
+            }
             return ParseResult::Control;
         }
 
         let topic = match v.get("topic").and_then(|t| t.as_str()) {
             Some(t) => t,
-            None => return ParseResult::Control,
+            None => return ParseResult::Ignored,
         };
 
         let data = match v.get("data") {
@@ -92,32 +151,23 @@ impl ExchangeAdapter for BybitAdapter {
                 _ => return ParseResult::Control,
             };
 
-            let t = &trades[0];
-
-            let msg = MarketMessage::Trade(TradeData {
-                exchange: exchange.to_string(),
-                symbol: util::symbol_from_exchange(
-                    exchange,
-                    t.get("s").and_then(|v| v.as_str()).unwrap_or_default()
-                ),
-                timestamp: t.get("T")
-                    .and_then(|v| v.as_i64())
-                    .unwrap_or_else(util::now_ms),
-                price: t.get("p")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("0")
-                    .to_string(),
-                amount: t.get("v")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("0")
-                    .to_string(),
-                side: t.get("S")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("unknown")
-                    .to_lowercase(),
-            });
-
-            return ParseResult::Market(msg);
+            // One frame may carry several trades
+            return ParseResult::from_messages(
+                trades
+                    .iter()
+                    .map(|t| MarketMessage::Trade(TradeData {
+                        exchange: exchange.to_string(),
+                        symbol: util::symbol_from_exchange(exchange, &json::str_or(t, "s", "")),
+                        timestamp: json::i64_or(t, "T", util::now_ms()),
+                        price: json::decimal_str(t, "p"),
+                        amount: json::decimal_str(t, "v"),
+                        side: json::str_or(t, "S", "unknown").to_lowercase(),
+                        trade_id: None,
+                        event_ts: None,
+                        source: None,
+                    }))
+                    .collect(),
+            );
         }
 
         // --------------------------------------------------
@@ -127,7 +177,7 @@ impl ExchangeAdapter for BybitAdapter {
 
             let symbol = match data.get("s").and_then(|v| v.as_str()) {
                 Some(s) => s,
-                None => return ParseResult::Error,
+                None => return ParseResult::Error(ParseError::missing_field("s")),
             };
 
             let asks = data.get("a")
@@ -162,11 +212,94 @@ impl ExchangeAdapter for BybitAdapter {
                     .unwrap_or_else(util::now_ms),
                 asks,
                 bids,
+                source: None,
+                snapshot: v.get("type").and_then(|t| t.as_str()) == Some("snapshot"),
             });
 
             return ParseResult::Market(msg);
         }
 
-        ParseResult::Control
+        // --------------------------------------------------
+        // PERPETUAL TICKERS (mark price / funding)
+        // Snapshot first, then deltas with changed fields only
+        // --------------------------------------------------
+        if topic.starts_with("tickers.") {
+
+            let symbol = match data.get("symbol").and_then(|v| v.as_str()) {
+                Some(s) => s,
+                None => return ParseResult::Error(ParseError::missing_field("symbol")),
+            };
+
+            let field = |k: &str| {
+                data.get(k)
+                    .and_then(|v| v.as_str())
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+            };
+
+            let mark_price = field("markPrice");
+            let funding_rate = field("fundingRate");
+
+            // Delta without mark price / funding changes
+            if mark_price.is_none() && funding_rate.is_none() {
+                return ParseResult::Control;
+            }
+
+            let msg = MarketMessage::Funding(FundingData {
+                exchange: exchange.to_string(),
+                symbol: util::symbol_from_exchange(exchange, symbol),
+                timestamp: v.get("ts")
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or_else(util::now_ms),
+                mark_price,
+                funding_rate,
+                next_funding_time: field("nextFundingTime")
+                    .and_then(|t| t.parse().ok()),
+                source: None,
+            });
+
+            return ParseResult::Market(msg);
+        }
+
+        ParseResult::Ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticker_funding_fields_are_funding() {
+        let snapshot = r#"{"topic":"tickers.BTCUSDT","type":"snapshot","ts":1700000000000,"data":{
+            "symbol":"BTCUSDT","markPrice":"42000.5","fundingRate":"0.0001","nextFundingTime":"1700006400000"}}"#;
+
+        let ParseResult::Market(MarketMessage::Funding(funding)) = BybitAdapter::linear().parse_message(snapshot, "bybitlinear") else {
+            panic!("expected funding");
+        };
+
+        assert_eq!(funding.symbol, "BTC/USDT");
+        assert_eq!(funding.timestamp, 1_700_000_000_000);
+        assert_eq!(funding.mark_price.as_deref(), Some("42000.5"));
+        assert_eq!(funding.funding_rate.as_deref(), Some("0.0001"));
+        assert_eq!(funding.next_funding_time, Some(1_700_006_400_000));
+
+        // Deltas without mark price / funding carry nothing to forward
+        let delta = r#"{"topic":"tickers.BTCUSDT","type":"delta","ts":1700000000100,"data":{
+            "symbol":"BTCUSDT","volume24h":"123.4"}}"#;
+        assert!(matches!(BybitAdapter::linear().parse_message(delta, "bybitlinear"), ParseResult::Control));
+    }
+
+    #[test]
+    fn each_market_uses_its_endpoint() {
+        let url = |name: &str, channel| crate::exchanges::get_adapter(name).unwrap().ws_url_for(&[channel]);
+
+        assert_eq!(url("bybit", ChannelType::Trades), "wss://stream.bybit.com/v5/public/spot");
+        assert_eq!(url("bybitlinear", ChannelType::Trades), "wss://stream.bybit.com/v5/public/linear");
+        assert_eq!(url("bybitinverse", ChannelType::Trades), "wss://stream.bybit.com/v5/public/inverse");
+
+        // Spot funding comes from the linear tickers, the others stay on their market
+        assert_eq!(url("bybit", ChannelType::Funding), LINEAR_WS_URL);
+        assert_eq!(url("bybitinverse", ChannelType::Funding), "wss://stream.bybit.com/v5/public/inverse");
     }
 }
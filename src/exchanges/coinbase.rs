@@ -6,7 +6,34 @@ use crate::{
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, is_subscription_limit_error, AdapterMetadata};
+
+/// Warns about channels the `subscriptions` confirmation lists
+/// without any accepted product.
+///
+/// Coinbase confirms every subscribe with the full channel list
+/// of the connection:
+/// { "type": "subscriptions", "channels": [ { "name": "matches", "product_ids": ["BTC-USD"] } ] }
+fn check_subscriptions(v: &Value) {
+    let channels = v.get("channels").and_then(|c| c.as_array());
+
+    if channels.is_none_or(|c| c.is_empty()) {
+        tracing::warn!("[COINBASE] WARNING: subscriptions confirmed without any channel");
+        return;
+    }
+
+    for channel in channels.into_iter().flatten() {
+        let name = channel.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+        let accepted = channel
+            .get("product_ids")
+            .and_then(|p| p.as_array())
+            .map_or(0, |p| p.len());
+
+        if accepted == 0 {
+            tracing::warn!("[COINBASE] WARNING: channel {name} confirmed without products");
+        }
+    }
+}
 
 /// Coinbase WebSocket adapter
 ///
@@ -29,6 +56,16 @@ impl ExchangeAdapter for CoinbaseAdapter {
         "wss://ws-feed.exchange.coinbase.com"
     }
 
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades, ChannelType::OrderBooks],
+            auth_required: false,
+            max_streams_per_connection: None,
+        }
+    }
+
     fn build_subscribe_message(
         &self,
         channel: ChannelType,
@@ -53,9 +90,26 @@ impl ExchangeAdapter for CoinbaseAdapter {
                 "product_ids": product_ids,
                 "channels": ["level2"]
             }),
+
+            // Coinbase Exchange lists no perpetuals (those are on
+            // Coinbase International) – no funding channel
+            ChannelType::Funding => json!({}),
         }
     }
 
+    fn supports_multiplex(&self) -> bool {
+        true
+    }
+
+    /// Aggregated top 50 levels; the WS `level2` snapshot frame is
+    /// not parsed
+    fn book_snapshot_url(&self, pair: &str, _depth: usize) -> Option<String> {
+        Some(format!(
+            "https://api.exchange.coinbase.com/products/{}/book?level=2",
+            util::symbol_to_exchange(self.name(), pair)
+        ))
+    }
+
     fn parse_message(
         &self,
         raw: &str,
@@ -64,12 +118,12 @@ impl ExchangeAdapter for CoinbaseAdapter {
 
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
         let msg_type = match v.get("type").and_then(|t| t.as_str()) {
             Some(t) => t,
-            None => return ParseResult::Control,
+            None => return ParseResult::Ignored,
         };
 
         match msg_type {
@@ -99,18 +153,21 @@ impl ExchangeAdapter for CoinbaseAdapter {
                         .and_then(|v| v.as_str())
                         .unwrap_or("unknown")
                         .to_string(),
+                    trade_id: None,
+                    event_ts: None,
+                    source: None,
                 });
 
                 ParseResult::Market(msg)
             }
 
             // --------------------------------------------------
-            // ORDER BOOK (L2 delta)
+            // ORDER BOOK (L2 delta, size 0 removes the level)
             // --------------------------------------------------
             "l2update" => {
                 let product_id = match v.get("product_id").and_then(|v| v.as_str()) {
                     Some(p) => p,
-                    None => return ParseResult::Error,
+                    None => return ParseResult::Error(ParseError::missing_field("product_id")),
                 };
 
                 let changes = match v.get("changes").and_then(|v| v.as_array()) {
@@ -137,10 +194,6 @@ impl ExchangeAdapter for CoinbaseAdapter {
                         None => continue,
                     };
 
-                    if qty == "0" {
-                        continue;
-                    }
-
                     match side {
                         "buy"  => bids.push([price, qty]),
                         "sell" => asks.push([price, qty]),
@@ -154,16 +207,82 @@ impl ExchangeAdapter for CoinbaseAdapter {
                     timestamp: util::now_ms(),
                     asks,
                     bids,
+                    source: None,
+                    snapshot: false,
                 });
 
                 ParseResult::Market(msg)
             }
 
             // --------------------------------------------------
-            // Everything else:
-            // subscriptions, heartbeat, errors, etc.
+            // Subscription ack
             // --------------------------------------------------
-            _ => ParseResult::Control,
+            "subscriptions" => {
+                check_subscriptions(&v);
+                ParseResult::Control
+            }
+
+            // --------------------------------------------------
+            // Errors (e.g. invalid product)
+            // { "type": "error", "message": "Failed to subscribe", "reason": "BTC-XYZ is not a valid product" }
+            // --------------------------------------------------
+            "error" => {
+                let message = v.get("message").and_then(|m| m.as_str()).unwrap_or_default();
+                let reason = v.get("reason").and_then(|r| r.as_str()).unwrap_or_default();
+
+                if is_subscription_limit_error(reason) {
+                    return ParseResult::SubscriptionLimit(reason.to_string());
+                }
+
+                tracing::warn!("[COINBASE] error: {message} ({reason})");
+                ParseResult::Error(ParseError::exchange(format!("{message} ({reason})")))
+            }
+
+            "heartbeat" => ParseResult::Control,
+
+            // --------------------------------------------------
+            // Everything else
+            // --------------------------------------------------
+            _ => ParseResult::Ignored,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_frames_are_exchange_errors() {
+        let invalid = json!({
+            "type": "error",
+            "message": "Failed to subscribe",
+            "reason": "BTC-XYZ is not a valid product"
+        })
+        .to_string();
+        let limited = json!({ "type": "error", "message": "Failed to subscribe", "reason": "too many subscriptions" }).to_string();
+
+        let ParseResult::Error(err) = CoinbaseAdapter.parse_message(&invalid, "coinbase") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err, ParseError::exchange("Failed to subscribe (BTC-XYZ is not a valid product)"));
+
+        assert!(matches!(
+            CoinbaseAdapter.parse_message(&limited, "coinbase"),
+            ParseResult::SubscriptionLimit(reason) if reason == "too many subscriptions"
+        ));
+    }
+
+    #[test]
+    fn subscriptions_frames_are_control() {
+        let confirmed = json!({
+            "type": "subscriptions",
+            "channels": [{ "name": "matches", "product_ids": ["BTC-USD"] }]
+        })
+        .to_string();
+        let empty = json!({ "type": "subscriptions", "channels": [] }).to_string();
+
+        assert!(matches!(CoinbaseAdapter.parse_message(&confirmed, "coinbase"), ParseResult::Control));
+        assert!(matches!(CoinbaseAdapter.parse_message(&empty, "coinbase"), ParseResult::Control));
+    }
+}
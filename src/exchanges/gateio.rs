@@ -6,7 +6,7 @@ use crate::{
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, is_subscription_limit_error, AdapterMetadata};
 
 /// Gate.io WebSocket adapter
 pub struct GateIoAdapter;
@@ -22,6 +22,16 @@ impl ExchangeAdapter for GateIoAdapter {
         "wss://api.gateio.ws/ws/v4/"
     }
 
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades, ChannelType::OrderBooks],
+            auth_required: false,
+            max_streams_per_connection: None,
+        }
+    }
+
     fn build_subscribe_message(
         &self,
         channel: ChannelType,
@@ -43,6 +53,10 @@ impl ExchangeAdapter for GateIoAdapter {
             }),
 
             ChannelType::OrderBooks => {
+                if pairs.is_empty() {
+                    return json!({});
+                }
+
                 let depth = config
                     .orderbook
                     .as_ref()
@@ -64,6 +78,9 @@ impl ExchangeAdapter for GateIoAdapter {
                     "payload": [symbol, depth.to_string(), interval]
                 })
             }
+
+            // Spot WS v4 – funding is on the separate futures WS, not implemented
+            ChannelType::Funding => json!({}),
         }
     }
 
@@ -75,12 +92,12 @@ impl ExchangeAdapter for GateIoAdapter {
 
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
         let channel = match v.get("channel").and_then(|v| v.as_str()) {
             Some(c) => c,
-            None => return ParseResult::Control,
+            None => return ParseResult::Ignored,
         };
 
         let event = match v.get("event").and_then(|v| v.as_str()) {
@@ -92,9 +109,32 @@ impl ExchangeAdapter for GateIoAdapter {
         // Control messages
         // --------------------------------------------------
         if event != "update" {
+            // Subscribe results carry `error: null` on success,
+            // `error: { code, message }` when rejected
+            let error = v.get("error").filter(|e| !e.is_null());
+
+            if let Some(error) = error {
+                let msg = error.get("message")
+                    .and_then(|m| m.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| error.to_string());
+
+                if is_subscription_limit_error(&msg) {
+                    return ParseResult::SubscriptionLimit(msg);
+                }
+
+                if event == "subscribe" {
+                    return ParseResult::Error(ParseError::subscription(format!("{channel}: {msg}")));
+                }
+
+                return ParseResult::Error(ParseError::exchange(msg));
+            }
+
             if event == "error" {
-                return ParseResult::Error;
+                return ParseResult::Error(ParseError::exchange(v.to_string()));
             }
+
+            // Subscribe acks, server pongs (`spot.pong`)
             return ParseResult::Control;
         }
 
@@ -127,6 +167,9 @@ impl ExchangeAdapter for GateIoAdapter {
                     .and_then(|v| v.as_str())
                     .unwrap_or("unknown")
                     .to_string(),
+                trade_id: None,
+                event_ts: None,
+                source: None,
             });
 
             return ParseResult::Market(msg);
@@ -175,11 +218,51 @@ impl ExchangeAdapter for GateIoAdapter {
                     .unwrap_or_else(util::now_ms),
                 asks,
                 bids,
+                source: None,
+                snapshot: true,
             });
 
             return ParseResult::Market(msg);
         }
 
-        ParseResult::Control
+        ParseResult::Ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejected_subscribes_are_subscription_errors() {
+        let raw = json!({
+            "time": 1700000000,
+            "channel": "spot.trades",
+            "event": "subscribe",
+            "error": { "code": 2, "message": "unknown currency pair: FOO_BAR" },
+            "result": null
+        })
+        .to_string();
+
+        let ParseResult::Error(err) = GateIoAdapter.parse_message(&raw, "gateio") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err, ParseError::subscription("spot.trades: unknown currency pair: FOO_BAR"));
+    }
+
+    #[test]
+    fn pongs_and_acks_are_control() {
+        let pong = json!({ "time": 1700000000, "channel": "spot.pong", "event": "", "result": null }).to_string();
+        let ack = json!({
+            "time": 1700000000,
+            "channel": "spot.trades",
+            "event": "subscribe",
+            "error": null,
+            "result": { "status": "success" }
+        })
+        .to_string();
+
+        assert!(matches!(GateIoAdapter.parse_message(&pong, "gateio"), ParseResult::Control));
+        assert!(matches!(GateIoAdapter.parse_message(&ack, "gateio"), ParseResult::Control));
     }
 }
@@ -1,6 +1,4 @@
 use serde_json::{Value, json};
-use std::collections::VecDeque;
-use std::sync::Mutex;
 
 use crate::{
     util,
@@ -8,7 +6,7 @@ use crate::{
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, AdapterMetadata};
 
 /// Kraken WebSocket v2 adapter (Spot)
 ///
@@ -16,18 +14,14 @@ use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
 /// wss://ws.kraken.com/v2
 ///
 /// Supports:
-/// - Trade batches
+/// - Trade batches (emitted in `trade_id` order)
 /// - Multiple symbols per WS
 /// - Future orderbook extension
-pub struct KrakenV2Adapter {
-    trade_buffer: Mutex<VecDeque<MarketMessage>>,
-}
+pub struct KrakenV2Adapter;
 
 impl KrakenV2Adapter {
     pub fn new() -> Self {
-        Self {
-            trade_buffer: Mutex::new(VecDeque::with_capacity(32)),
-        }
+        Self
     }
 }
 
@@ -64,6 +58,16 @@ impl ExchangeAdapter for KrakenV2Adapter {
         "wss://ws.kraken.com/v2"
     }
 
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades, ChannelType::OrderBooks],
+            auth_required: false,
+            max_streams_per_connection: None,
+        }
+    }
+
     fn build_subscribe_message(
         &self,
         channel: ChannelType,
@@ -94,6 +98,9 @@ impl ExchangeAdapter for KrakenV2Adapter {
                     "depth": 20
                 }
             }),
+
+            // Spot WS v2 – funding is on the Kraken Futures API, not implemented
+            ChannelType::Funding => json!({}),
         }
     }
 
@@ -102,18 +109,12 @@ impl ExchangeAdapter for KrakenV2Adapter {
         raw: &str,
         exchange: &str,
     ) -> ParseResult {
-
-        // 1️⃣ Emit buffered trades first
-        if let Some(msg) = self.trade_buffer.lock().unwrap().pop_front() {
-            return ParseResult::Market(msg);
-        }
-
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
-        // 2️⃣ Ignore heartbeats & control
+        // 1️⃣ Ignore heartbeats & control
         let channel = v.get("channel").and_then(|v| v.as_str());
         let msg_type = v.get("type").and_then(|v| v.as_str());
 
@@ -125,13 +126,15 @@ impl ExchangeAdapter for KrakenV2Adapter {
             return ParseResult::Control;
         }
 
-        // 3️⃣ Trades
+        // 2️⃣ Trades
         if channel == Some("trade") {
             let trades = match v.get("data").and_then(|v| v.as_array()) {
                 Some(d) if !d.is_empty() => d,
                 _ => return ParseResult::Control,
             };
-            let mut buffer = self.trade_buffer.lock().unwrap();
+            // Trades of one batch are emitted in `trade_id` order;
+            // batches are not guaranteed to arrive sorted.
+            let mut batch: Vec<(Option<i64>, MarketMessage)> = Vec::with_capacity(trades.len());
 
             for t in trades {
                 let symbol_raw = t.get("symbol").and_then(|v| v.as_str()).unwrap_or("");
@@ -151,23 +154,30 @@ impl ExchangeAdapter for KrakenV2Adapter {
                     .unwrap_or("unknown")
                     .to_string();
 
-                buffer.push_back(MarketMessage::Trade(TradeData {
+                let trade_id = t.get("trade_id").and_then(|v| v.as_i64());
+
+                batch.push((trade_id, MarketMessage::Trade(TradeData {
                     exchange: exchange.to_string(),
                     symbol,
                     timestamp: ts,
                     price,
                     amount,
                     side,
-                }));
+                    trade_id: trade_id.map(|id| id.to_string()),
+                    event_ts: None,
+                    source: None,
+                })));
             }
 
-            return buffer
-                .pop_front()
-                .map(ParseResult::Market)
-                .unwrap_or(ParseResult::Control);
+            // Stable: trades without id keep their relative order
+            batch.sort_by_key(|(trade_id, _)| *trade_id);
+
+            return ParseResult::from_messages(
+                batch.into_iter().map(|(_, msg)| msg).collect()
+            );
         }
 
-        // 4️⃣ Orderbook v2 placeholder
+        // 3️⃣ Orderbook v2 placeholder
         if channel == Some("book") {
             // TODO:
             // - snapshot vs update
@@ -176,6 +186,33 @@ impl ExchangeAdapter for KrakenV2Adapter {
             return ParseResult::Control;
         }
 
-        ParseResult::Control
+        ParseResult::Ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trade_batches_are_emitted_in_trade_id_order() {
+        let raw = r#"{"channel":"trade","type":"update","data":[
+            {"symbol":"BTC/USD","side":"buy","price":42000.5,"qty":0.1,"trade_id":12,"timestamp":"2023-11-14T22:13:20.000000Z"},
+            {"symbol":"BTC/USD","side":"sell","price":42000.4,"qty":0.2,"trade_id":10,"timestamp":"2023-11-14T22:13:20.000000Z"},
+            {"symbol":"BTC/USD","side":"buy","price":42000.6,"qty":0.3,"trade_id":11,"timestamp":"2023-11-14T22:13:20.001000Z"}
+        ]}"#;
+
+        let ParseResult::Batch(trades) = KrakenV2Adapter::new().parse_message(raw, "kraken") else {
+            panic!("expected a batch");
+        };
+        let ids: Vec<_> = trades
+            .iter()
+            .map(|m| match m {
+                MarketMessage::Trade(t) => t.trade_id.clone().unwrap(),
+                other => panic!("expected trades, got {other:?}"),
+            })
+            .collect();
+
+        assert_eq!(ids, ["10", "11", "12"]);
     }
 }
@@ -1,19 +1,54 @@
 use serde_json::{Value, json};
+use std::time::Duration;
+use tokio::sync::OnceCell;
 
 use crate::{
+    net,
     util,
     schema::{MarketMessage, TradeData},
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, AdapterMetadata, SymbolCase};
+
+/// WS URL including the public token, fetched once
+static WS_URL: OnceCell<String> = OnceCell::const_new();
+
+const BULLET_PUBLIC_URL: &str = "https://api.kucoin.com/api/v1/bullet-public";
+
+/// Requests a public token from `bullet_url` and builds the WS URL.
+async fn fetch_ws_url(bullet_url: &str, timeout: Duration) -> anyhow::Result<String> {
+    let res: Value = net::http_client()
+        .post(bullet_url)
+        .timeout(timeout)
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    ws_url_from_bullet(&res)
+}
+
+/// Builds the WS URL from a bullet-public response:
+/// `{ "data": { "token": …, "instanceServers": [{ "endpoint": … }] } }`
+fn ws_url_from_bullet(res: &Value) -> anyhow::Result<String> {
+    let token = res["data"]["token"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("KuCoin token missing"))?;
+
+    let endpoint = res["data"]["instanceServers"][0]["endpoint"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("KuCoin endpoint missing"))?;
+
+    Ok(format!("{endpoint}?token={token}"))
+}
 
 /// KuCoin WebSocket adapter
 ///
 /// IMPORTANT:
-/// - Does NOT fetch WS URL
-/// - Does NOT perform IO
-/// - Pure protocol → MarketMessage translation
+/// - The WS URL carries a token from the bullet-public REST
+///   endpoint (`connect_url`)
+/// - Answers the server's JSON pings (`pong_for`)
 ///
 /// Trades only.
 pub struct KucoinAdapter;
@@ -25,23 +60,52 @@ impl ExchangeAdapter for KucoinAdapter {
         "kucoin"
     }
 
+    fn symbol_case(&self) -> SymbolCase {
+        SymbolCase::Upper
+    }
+
     /// Not used for KuCoin
     ///
-    /// The real WS URL is injected by the runner
+    /// The real WS URL comes from `connect_url`
     fn ws_url(&self) -> &'static str {
         ""
     }
 
+    /// Fetches (once) the WS URL including the public token.
+    ///
+    /// A timeout is a normal failure: nothing is cached and the
+    /// runner's reconnect loop retries later.
+    async fn connect_url(&self, timeout: Duration) -> anyhow::Result<Option<String>> {
+        let url = WS_URL
+            .get_or_try_init(|| fetch_ws_url(BULLET_PUBLIC_URL, timeout))
+            .await?;
+
+        Ok(Some(url.clone()))
+    }
+
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades],
+            auth_required: false,
+            max_streams_per_connection: None,
+        }
+    }
+
     fn build_subscribe_message(
         &self,
         channel: ChannelType,
         pairs: &[String],
         _config: &ExchangeConfig,
     ) -> Value {
+        if pairs.is_empty() {
+            return json!({});
+        }
+
         match channel {
             ChannelType::Trades => {
-                let sym = util::symbol_to_exchange("kucoin", &pairs[0])
-                    .to_uppercase(); // BTC-USDT
+                let sym = self.subscribe_symbol(&pairs[0]); // BTC-USDT
 
                 json!({
                     "id": util::now_ms().to_string(),
@@ -53,7 +117,48 @@ impl ExchangeAdapter for KucoinAdapter {
             }
 
             ChannelType::OrderBooks => json!({}),
+
+            // Spot feed – funding is on the KuCoin Futures API, not implemented
+            ChannelType::Funding => json!({}),
+        }
+    }
+
+    /// KuCoin drops connections without a client ping within
+    /// `pingInterval` (20s), so ping at half of it.
+    fn ping_interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(10))
+    }
+
+    /// `{"type":"ping","id":…}` → `{"type":"pong","id":…}`
+    fn pong_for(&self, raw: &str) -> Option<Value> {
+        let v: Value = serde_json::from_str(raw).ok()?;
+
+        if v.get("type").and_then(|t| t.as_str()) != Some("ping") {
+            return None;
         }
+
+        Some(json!({
+            "type": "pong",
+            "id": v.get("id")
+        }))
+    }
+
+    /// Subscribes sent before the welcome frame are dropped
+    fn wait_for_welcome(&self) -> bool {
+        true
+    }
+
+    /// `{"id":…,"type":"welcome"}`
+    fn is_welcome(&self, raw: &str) -> bool {
+        serde_json::from_str::<Value>(raw)
+            .is_ok_and(|v| v.get("type").and_then(|t| t.as_str()) == Some("welcome"))
+    }
+
+    fn ping_message(&self) -> Option<Value> {
+        Some(json!({
+            "type": "ping",
+            "id": util::now_ms().to_string()
+        }))
     }
 
     fn parse_message(
@@ -64,12 +169,12 @@ impl ExchangeAdapter for KucoinAdapter {
 
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
         let msg_type = match v.get("type").and_then(|v| v.as_str()) {
             Some(t) => t,
-            None => return ParseResult::Control,
+            None => return ParseResult::Ignored,
         };
 
         // --------------------------------------------------
@@ -90,12 +195,14 @@ impl ExchangeAdapter for KucoinAdapter {
 
         let sym = match topic.split(':').nth(1) {
             Some(s) => s,
-            None => return ParseResult::Error,
+            None => {
+                return ParseResult::Error(ParseError::malformed(format!("topic without symbol: {topic}")));
+            }
         };
 
         let d = match v.get("data") {
             Some(d) => d,
-            None => return ParseResult::Error,
+            None => return ParseResult::Error(ParseError::missing_field("data")),
         };
 
         let timestamp = d.get("time")
@@ -120,8 +227,88 @@ impl ExchangeAdapter for KucoinAdapter {
                 .and_then(|v| v.as_str())
                 .unwrap_or("unknown")
                 .to_string(),
+            trade_id: None,
+            event_ts: None,
+            source: None,
         });
 
         ParseResult::Market(msg)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_pings_are_answered_with_their_id() {
+        assert_eq!(
+            KucoinAdapter.pong_for(r#"{"type":"ping","id":"1700000000000"}"#),
+            Some(json!({ "type": "pong", "id": "1700000000000" }))
+        );
+
+        assert_eq!(KucoinAdapter.pong_for(r#"{"type":"welcome","id":"x"}"#), None);
+        assert_eq!(KucoinAdapter.pong_for("not json"), None);
+    }
+
+    #[test]
+    fn keepalive_ping_carries_a_time_id() {
+        util::set_test_clock(Some(1_700_000_000_000));
+        let ping = KucoinAdapter.ping_message();
+        util::set_test_clock(None);
+
+        assert_eq!(ping, Some(json!({ "type": "ping", "id": "1700000000000" })));
+    }
+
+    #[test]
+    fn ws_url_is_endpoint_plus_token() {
+        let bullet = json!({
+            "code": "200000",
+            "data": {
+                "token": "2neAiuYvAU61ZD",
+                "instanceServers": [{
+                    "endpoint": "wss://ws-api-spot.kucoin.com/",
+                    "encrypt": true,
+                    "protocol": "websocket",
+                    "pingInterval": 18000,
+                    "pingTimeout": 10000
+                }]
+            }
+        });
+
+        assert_eq!(
+            ws_url_from_bullet(&bullet).unwrap(),
+            "wss://ws-api-spot.kucoin.com/?token=2neAiuYvAU61ZD"
+        );
+    }
+
+    #[test]
+    fn incomplete_bullet_responses_fail() {
+        let no_token = json!({ "data": { "instanceServers": [{ "endpoint": "wss://x" }] } });
+        let no_server = json!({ "data": { "token": "t", "instanceServers": [] } });
+
+        assert!(ws_url_from_bullet(&no_token).unwrap_err().to_string().contains("token"));
+        assert!(ws_url_from_bullet(&no_server).unwrap_err().to_string().contains("endpoint"));
+        assert!(ws_url_from_bullet(&json!({ "code": "400100", "msg": "error" })).is_err());
+    }
+
+    #[tokio::test]
+    async fn unresponsive_bullet_endpoints_time_out() {
+        // Accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/v1/bullet-public", listener.local_addr().unwrap());
+        let _server = tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+
+        let started = std::time::Instant::now();
+        let result = fetch_ws_url(&url, Duration::from_millis(200)).await;
+
+        let err = result.unwrap_err();
+        assert!(err.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_timeout()), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, AdapterMetadata, SymbolCase};
 
 /// MEXC WebSocket adapter (Futures deal stream)
 ///
@@ -18,6 +18,7 @@ use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
 /// - No token
 /// - Trades only
 /// - One symbol per WS connection (recommended)
+/// - Contract symbols: BASE_QUOTE (BTC_USDT, BTC_USDC, BTC_USD)
 pub struct MexcAdapter;
 
 #[async_trait::async_trait]
@@ -27,10 +28,24 @@ impl ExchangeAdapter for MexcAdapter {
         "mexc"
     }
 
+    fn symbol_case(&self) -> SymbolCase {
+        SymbolCase::Upper
+    }
+
     fn ws_url(&self) -> &'static str {
         "wss://contract.mexc.com/edge"
     }
 
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades],
+            auth_required: false,
+            max_streams_per_connection: None,
+        }
+    }
+
     fn build_subscribe_message(
         &self,
         channel: ChannelType,
@@ -38,14 +53,16 @@ impl ExchangeAdapter for MexcAdapter {
         _config: &ExchangeConfig,
     ) -> Value {
 
+        if pairs.is_empty() {
+            return json!({});
+        }
+
         match channel {
             ChannelType::Trades => {
                 let pair = &pairs[0];
 
-                // BTC/USDT -> BTC_USDT
-                let symbol = util::symbol_to_exchange("mexc", pair)
-                    .replace('-', "_")
-                    .to_uppercase();
+                // BTC/USDT -> BTC_USDT, BTC/USDC -> BTC_USDC
+                let symbol = self.subscribe_symbol(pair);
 
                 json!({
                     "method": "sub.deal",
@@ -56,6 +73,9 @@ impl ExchangeAdapter for MexcAdapter {
             }
 
             ChannelType::OrderBooks => json!({}),
+
+            // Funding not implemented (contract WS `sub.funding.rate`)
+            ChannelType::Funding => json!({}),
         }
     }
 
@@ -67,12 +87,12 @@ impl ExchangeAdapter for MexcAdapter {
 
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
         let channel = match v.get("channel").and_then(|v| v.as_str()) {
             Some(c) => c,
-            None => return ParseResult::Control,
+            None => return ParseResult::Ignored,
         };
 
         // --------------------------------------------------
@@ -84,7 +104,7 @@ impl ExchangeAdapter for MexcAdapter {
 
         let symbol_raw = match v.get("symbol").and_then(|v| v.as_str()) {
             Some(s) => s,
-            None => return ParseResult::Error,
+            None => return ParseResult::Error(ParseError::missing_field("symbol")),
         };
 
         let symbol = util::symbol_from_exchange(exchange, symbol_raw);
@@ -115,6 +135,9 @@ impl ExchangeAdapter for MexcAdapter {
                 .map(|v| v.to_string())
                 .unwrap_or_else(|| "0".to_string()),
             side,
+            trade_id: None,
+            event_ts: None,
+            source: None,
         });
 
         ParseResult::Market(msg)
@@ -0,0 +1,232 @@
+use serde_json::{Value, json};
+
+use crate::{
+    util,
+    schema::{MarketMessage, TradeData},
+    config::ExchangeConfig,
+};
+
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, AdapterMetadata, SymbolCase};
+
+/// MEXC Spot WebSocket adapter (v3 JSON deals stream)
+///
+/// WS:
+/// wss://wbs.mexc.com/ws
+///
+/// Notes:
+/// - No auth
+/// - Trades only
+/// - Multiple symbols per subscribe (max 30 streams per connection)
+/// - Symbols without separator: BTC/USDT -> BTCUSDT
+/// - Deal side `S`: 1 = buy, 2 = sell
+///
+/// The futures stream lives in `MexcAdapter` ("mexc").
+pub struct MexcSpotAdapter;
+
+#[async_trait::async_trait]
+impl ExchangeAdapter for MexcSpotAdapter {
+
+    fn name(&self) -> &'static str {
+        "mexcspot"
+    }
+
+    fn symbol_case(&self) -> SymbolCase {
+        SymbolCase::Upper
+    }
+
+    fn ws_url(&self) -> &'static str {
+        "wss://wbs.mexc.com/ws"
+    }
+
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades],
+            auth_required: false,
+            max_streams_per_connection: Some(30),
+        }
+    }
+
+    fn build_subscribe_message(
+        &self,
+        channel: ChannelType,
+        pairs: &[String],
+        _config: &ExchangeConfig,
+    ) -> Value {
+
+        match channel {
+            ChannelType::Trades => {
+                let params: Vec<String> = pairs
+                    .iter()
+                    .map(|p| format!("spot@public.deals.v3.api@{}", self.subscribe_symbol(p)))
+                    .collect();
+
+                json!({
+                    "method": "SUBSCRIPTION",
+                    "params": params
+                })
+            }
+
+            ChannelType::OrderBooks => json!({}),
+
+            // Spot stream – funding belongs to the futures adapter ("mexc")
+            ChannelType::Funding => json!({}),
+        }
+    }
+
+    fn parse_message(
+        &self,
+        raw: &str,
+        exchange: &str,
+    ) -> ParseResult {
+
+        let v: Value = match serde_json::from_str(raw) {
+            Ok(v) => v,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
+        };
+
+        // --------------------------------------------------
+        // Control messages (subscribe ack, PONG)
+        // { "id": 0, "code": 0, "msg": "spot@public.deals.v3.api@BTCUSDT" }
+        // --------------------------------------------------
+        let stream = match v.get("c").and_then(|v| v.as_str()) {
+            Some(c) => c,
+            None if v.get("msg").is_some() => return ParseResult::Control,
+            None => return ParseResult::Ignored,
+        };
+
+        if !stream.starts_with("spot@public.deals.v3.api@") {
+            return ParseResult::Ignored;
+        }
+
+        let symbol_raw = match v.get("s").and_then(|v| v.as_str()) {
+            Some(s) => s,
+            None => return ParseResult::Error(ParseError::missing_field("s")),
+        };
+
+        let trades = match v
+            .get("d")
+            .and_then(|d| d.get("deals"))
+            .and_then(|d| d.as_array())
+        {
+            Some(t) if !t.is_empty() => t,
+            _ => return ParseResult::Control,
+        };
+
+        let symbol = util::symbol_from_exchange(exchange, symbol_raw);
+
+        // One frame may carry several deals
+        ParseResult::from_messages(
+            trades
+                .iter()
+                .map(|t| {
+                    let side = match t.get("S").and_then(|v| v.as_i64()) {
+                        Some(1) => "buy",
+                        Some(2) => "sell",
+                        _ => "unknown",
+                    }.to_string();
+
+                    MarketMessage::Trade(TradeData {
+                        exchange: exchange.to_string(),
+                        symbol: symbol.clone(),
+                        timestamp: t.get("t")
+                            .and_then(|v| v.as_i64())
+                            .unwrap_or_else(util::now_ms),
+                        price: t.get("p")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("0")
+                            .to_string(),
+                        amount: t.get("v")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("0")
+                            .to_string(),
+                        side,
+                        trade_id: None,
+                        event_ts: None,
+                        source: None,
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_deal_of_a_frame_is_forwarded() {
+        let raw = r#"{"c":"spot@public.deals.v3.api@BTCUSDT","s":"BTCUSDT","t":1700000000100,
+            "d":{"deals":[{"S":1,"p":"42000.1","v":"0.5","t":1700000000000},
+                          {"S":2,"p":"42000.0","v":"0.25","t":1700000000050}],"e":"spot@public.deals.v3.api"}}"#;
+
+        let ParseResult::Batch(batch) = MexcSpotAdapter.parse_message(raw, "mexcspot") else {
+            panic!("expected a batch");
+        };
+
+        let trades: Vec<_> = batch
+            .iter()
+            .map(|m| match m {
+                MarketMessage::Trade(t) => {
+                    (t.symbol.as_str(), t.price.as_str(), t.amount.as_str(), t.side.as_str(), t.timestamp)
+                }
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+
+        assert_eq!(
+            trades,
+            [
+                ("BTC/USDT", "42000.1", "0.5", "buy", 1_700_000_000_000),
+                ("BTC/USDT", "42000.0", "0.25", "sell", 1_700_000_000_050),
+            ]
+        );
+    }
+
+    #[test]
+    fn deals_without_time_fall_back_to_the_clock() {
+        util::set_test_clock(Some(1_700_000_000_999));
+        let raw = r#"{"c":"spot@public.deals.v3.api@BTCUSDT","s":"BTCUSDT","d":{"deals":[{"S":1,"p":"1","v":"2"}]}}"#;
+        let parsed = MexcSpotAdapter.parse_message(raw, "mexcspot");
+        util::set_test_clock(None);
+
+        let ParseResult::Market(msg) = parsed else {
+            panic!("expected one trade");
+        };
+        assert_eq!(msg.timestamp(), 1_700_000_000_999);
+        assert!(util::now_ms() > 1_700_000_000_999);
+    }
+
+    #[test]
+    fn acks_are_control_and_unknown_frames_ignored() {
+        let parse = |raw| MexcSpotAdapter.parse_message(raw, "mexcspot");
+
+        let ack = r#"{"id":0,"code":0,"msg":"spot@public.deals.v3.api@BTCUSDT"}"#;
+        let kline = r#"{"c":"spot@public.kline.v3.api@BTCUSDT@Min1","s":"BTCUSDT","d":{}}"#;
+
+        assert!(matches!(parse(ack), ParseResult::Control));
+        assert!(matches!(parse(r#"{"id":0,"code":0,"msg":"PONG"}"#), ParseResult::Control));
+        assert!(matches!(parse(r#"{"foo":1}"#), ParseResult::Ignored));
+        assert!(matches!(parse(kline), ParseResult::Ignored));
+    }
+
+    #[test]
+    fn subscribes_upper_case_symbols() {
+        let cfg: ExchangeConfig = serde_json::from_value(json!({
+            "name": "mexcspot", "enabled": true,
+            "pairs": { "trades": [], "orderbooks": [] }
+        }))
+        .unwrap();
+        let pairs = ["btc/usdt".to_string(), "ETH/USDC".to_string()];
+
+        assert_eq!(
+            MexcSpotAdapter.build_subscribe_message(ChannelType::Trades, &pairs, &cfg),
+            json!({
+                "method": "SUBSCRIPTION",
+                "params": ["spot@public.deals.v3.api@BTCUSDT", "spot@public.deals.v3.api@ETHUSDC"]
+            })
+        );
+    }
+}
@@ -18,6 +18,7 @@ mod kucoin;
 mod coinbase;
 mod bybit;
 mod mexc;
+mod mexcspot;
 mod kraken_v2;
 mod bitstamp;
 mod bitfinex;
@@ -25,6 +26,27 @@ mod bitfinex;
 use std::sync::Arc;
 use adapter::ExchangeAdapter;
 
+/// Names of all exchanges resolved by `get_adapter`.
+///
+/// Must be kept in sync with the match below.
+pub const SUPPORTED: [&str; 15] = [
+    "gateio",
+    "binanceus",
+    "binance",
+    "okx",
+    "bitrue",
+    "kucoin",
+    "coinbase",
+    "bybit",
+    "bybitlinear",
+    "bybitinverse",
+    "mexc",
+    "mexcspot",
+    "kraken",
+    "bitstamp",
+    "bitfinex",
+];
+
 /// Returns an exchange adapter instance by name.
 ///
 /// This function acts as a **central factory / registry** for all
@@ -65,11 +87,251 @@ pub fn get_adapter(name: &str) -> Option<Arc<dyn ExchangeAdapter>> {
         "bitrue"     => Some(Arc::new(bitrue::BitrueAdapter)),
         "kucoin" => Some(Arc::new(kucoin::KucoinAdapter)),
         "coinbase" => Some(Arc::new(coinbase::CoinbaseAdapter)),
-        "bybit" => Some(Arc::new(bybit::BybitAdapter)),
+        "bybit" => Some(Arc::new(bybit::BybitAdapter::spot())),
+        "bybitlinear" => Some(Arc::new(bybit::BybitAdapter::linear())),
+        "bybitinverse" => Some(Arc::new(bybit::BybitAdapter::inverse())),
         "mexc" => Some(Arc::new(mexc::MexcAdapter)),
+        "mexcspot" => Some(Arc::new(mexcspot::MexcSpotAdapter)),
         "kraken" => Some(Arc::new(kraken_v2::KrakenV2Adapter::new())),
         "bitstamp" => Some(Arc::new(bitstamp::BitstampAdapter)),
         "bitfinex"  => Some(Arc::new(bitfinex::BitfinexAdapter::new())),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use super::*;
+    use crate::config::ExchangeConfig;
+    use adapter::{ChannelType, ParseResult, SymbolCase};
+
+    fn adapters() -> impl Iterator<Item = (&'static str, Arc<dyn ExchangeAdapter>)> {
+        SUPPORTED.iter().map(|&name| (name, get_adapter(name).expect("SUPPORTED name resolves")))
+    }
+
+    fn config(name: &str) -> ExchangeConfig {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "enabled": true,
+            "pairs": { "trades": ["BTC/USDT"], "orderbooks": ["BTC/USDT"] }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn names_are_lowercase_registry_keys() {
+        for (name, adapter) in adapters() {
+            assert_eq!(adapter.name(), name);
+            assert_eq!(name, name.to_lowercase());
+            assert_eq!(adapter.metadata().name, name);
+        }
+    }
+
+    #[test]
+    fn ws_urls_are_empty_or_secure() {
+        for (name, adapter) in adapters() {
+            let urls = [adapter.ws_url(), adapter.metadata().ws_url];
+
+            for url in urls {
+                assert!(url.is_empty() || url.starts_with("wss://"), "{name}: {url}");
+            }
+        }
+    }
+
+    #[test]
+    fn one_pair_subscribes_are_not_empty() {
+        let pairs = ["BTC/USDT".to_string()];
+
+        for (name, adapter) in adapters() {
+            let cfg = config(name);
+
+            for &channel in adapter.metadata().channels {
+                let sub = adapter.build_subscribe_message(channel, &pairs, &cfg);
+
+                assert!(
+                    !sub.is_null() && sub != serde_json::json!({}),
+                    "{name}: empty {channel:?} subscribe"
+                );
+            }
+        }
+    }
+
+    /// Adapters subscribing `pairs[0]` must not index into an empty
+    /// slice; all others may send an empty list, but must not panic.
+    #[test]
+    fn empty_pair_slices_do_not_panic() {
+        const INDEXING: [&str; 6] = ["bitrue", "kucoin", "mexc", "bitstamp", "bitfinex", "gateio"];
+
+        for (name, adapter) in adapters() {
+            let cfg = config(name);
+
+            for &channel in ChannelType::all() {
+                let sub = catch_unwind(AssertUnwindSafe(|| {
+                    adapter.build_subscribe_message(channel, &[], &cfg)
+                }));
+                let Ok(sub) = sub else {
+                    panic!("{name}: {channel:?} panicked on an empty slice");
+                };
+
+                let indexing = INDEXING.contains(&name)
+                    && !(name == "gateio" && channel == ChannelType::Trades);
+                if indexing {
+                    assert_eq!(sub, serde_json::json!({}), "{name}: {channel:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn garbage_is_a_parse_error() {
+        for (name, adapter) in adapters() {
+            for garbage in ["", "not json", "{\"unterminated\":"] {
+                assert!(
+                    matches!(adapter.parse_message(garbage, name), ParseResult::Error(_)),
+                    "{name}: {garbage:?}"
+                );
+            }
+        }
+    }
+
+    /// Data frames of every supported protocol; truncated and with
+    /// wrong field types below.
+    const FRAMES: &[&str] = &[
+        r#"{"e":"trade","E":1,"s":"BTCUSDT","t":1,"p":"1.0","q":"2.0","T":1,"m":true}"#,
+        r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","s":"BTCUSDT","b":[["1","2"]],"a":[["1","0"]]}}"#,
+        r#"{"e":"markPriceUpdate","E":1,"s":"BTCUSDT","p":"1","r":"0.0001","T":2}"#,
+        r#"{"arg":{"channel":"trades","instId":"BTC-USDT"},"data":[{"instId":"BTC-USDT","px":"1","sz":"2","side":"buy","ts":"1","tradeId":"7"}]}"#,
+        r#"{"arg":{"channel":"books5","instId":"BTC-USDT"},"data":[{"asks":[["1","2","0","1"]],"bids":[],"ts":"1"}]}"#,
+        r#"{"arg":{"channel":"funding-rate","instId":"BTC-USDT-SWAP"},"data":[{"fundingRate":"0.1","fundingTime":"1"}]}"#,
+        r#"{"topic":"publicTrade.BTCUSDT","type":"snapshot","data":[{"T":1,"s":"BTCUSDT","S":"Buy","v":"1","p":"2"}]}"#,
+        r#"{"topic":"orderbook.50.BTCUSDT","type":"delta","ts":1,"data":{"s":"BTCUSDT","b":[["1","2"]],"a":[]}}"#,
+        r#"{"topic":"tickers.BTCUSDT","data":{"symbol":"BTCUSDT","markPrice":"1","fundingRate":"0.1"}}"#,
+        r#"{"time":1,"channel":"spot.trades","event":"update","result":{"currency_pair":"BTC_USDT","price":"1","amount":"2","side":"buy","create_time_ms":"1"}}"#,
+        r#"{"time":1,"channel":"spot.order_book","event":"update","result":{"s":"BTC_USDT","t":1,"bids":[["1","2"]],"asks":[["3","4"]]}}"#,
+        r#"{"type":"message","topic":"/market/match:BTC-USDT","subject":"trade.l3match","data":{"symbol":"BTC-USDT","price":"1","size":"2","side":"buy","time":"1000000"}}"#,
+        r#"{"type":"ping","id":"1"}"#,
+        r#"{"type":"match","product_id":"BTC-USD","price":"1","size":"2","side":"buy","time":"2024-01-01T00:00:00.000000Z","trade_id":1}"#,
+        r#"{"type":"l2update","product_id":"BTC-USD","changes":[["buy","1","2"]],"time":"2024-01-01T00:00:00Z"}"#,
+        r#"{"channel":"push.deal","symbol":"BTC_USDT","data":[{"p":1,"v":2,"T":1,"t":1}],"ts":1}"#,
+        r#"{"c":"spot@public.deals.v3.api@BTCUSDT","s":"BTCUSDT","d":{"deals":[{"S":1,"p":"1","v":"2","t":1}]}}"#,
+        r#"{"channel":"trade","type":"update","data":[{"symbol":"BTC/USD","side":"buy","price":1.0,"qty":2.0,"timestamp":"2024-01-01T00:00:00.000000Z","trade_id":1}]}"#,
+        r#"{"event":"trade","channel":"live_trades_btcusd","data":{"id":1,"amount_str":"2","price_str":"1","type":0,"microtimestamp":"1000000"}}"#,
+        r#"{"event":"subscribed","channel":"trades","chanId":17,"symbol":"tBTCUSD","pair":"BTCUSD"}"#,
+        r#"[17,"te",[1,1000,0.5,42000]]"#,
+        r#"[17,[[1,1000,0.5,42000]]]"#,
+        r#"{"channel":"market_btcusdt_trade_ticker","tick":{"data":[{"side":"BUY","price":1,"vol":2,"ts":1}]},"ts":1}"#,
+    ];
+
+    /// Type-confused values substituted for every field of `FRAMES`.
+    const WRONG_TYPES: &[&str] = &["null", "7", "-1.5", "\"\"", "\"x\"", "[]", "[[]]", "{}", "true"];
+
+    fn hostile_inputs() -> Vec<String> {
+        let mut inputs: Vec<String> = [
+            "", " ", "{", "[", "}", "null", "0", "\"text\"", "[]", "{}", "[[]]", "[1]", "[1,2]",
+            "[1,\"hb\"]", "\u{0}", "not json", "{\"event\":\"error\"}", "{\"type\":\"error\"}",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        for frame in FRAMES {
+            // Every truncation, on char boundaries
+            inputs.extend(frame.char_indices().map(|(i, _)| frame[..i].to_string()));
+
+            // Each field replaced by every wrong type
+            let value: serde_json::Value = serde_json::from_str(frame).unwrap();
+            for path in field_paths(&value, Vec::new()) {
+                for wrong in WRONG_TYPES {
+                    let mut mutated = value.clone();
+                    *mutated.pointer_mut(&path).unwrap() = serde_json::from_str(wrong).unwrap();
+                    inputs.push(mutated.to_string());
+                }
+            }
+        }
+
+        inputs
+    }
+
+    /// JSON pointers of every value below the root.
+    fn field_paths(value: &serde_json::Value, prefix: Vec<String>) -> Vec<String> {
+        let children: Vec<(String, &serde_json::Value)> = match value {
+            serde_json::Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+            serde_json::Value::Array(items) => {
+                items.iter().enumerate().map(|(i, v)| (i.to_string(), v)).collect()
+            }
+            _ => return Vec::new(),
+        };
+
+        children
+            .into_iter()
+            .flat_map(|(key, child)| {
+                let mut path = prefix.clone();
+                path.push(key);
+
+                let mut paths = vec![format!("/{}", path.join("/"))];
+                paths.extend(field_paths(child, path));
+                paths
+            })
+            .collect()
+    }
+
+    #[test]
+    fn no_adapter_panics_on_hostile_frames() {
+        let inputs = hostile_inputs();
+        let mut panics = Vec::new();
+
+        for (name, adapter) in adapters() {
+            for input in &inputs {
+                let parsed = catch_unwind(AssertUnwindSafe(|| {
+                    adapter.parse_message(input, name);
+                    adapter.pong_for(input);
+                }));
+
+                if parsed.is_err() {
+                    panics.push(format!("{name}: {input}"));
+                }
+            }
+        }
+
+        assert!(panics.is_empty(), "adapters panicked on:\n{}", panics.join("\n"));
+    }
+
+    #[test]
+    fn subscribe_symbols_follow_the_symbol_case() {
+        let expected = [
+            ("binance", SymbolCase::Lower, "btcusdt"),
+            ("bitstamp", SymbolCase::Lower, "btcusdt"),
+            ("kucoin", SymbolCase::Upper, "BTC-USDT"),
+            ("mexc", SymbolCase::Upper, "BTC_USDT"),
+            ("okx", SymbolCase::AsIs, "BTC-USDT"),
+        ];
+
+        for (name, case, symbol) in expected {
+            let adapter = get_adapter(name).unwrap();
+
+            assert_eq!(adapter.symbol_case(), case, "{name}");
+            assert_eq!(adapter.subscribe_symbol("BTC/USDT"), symbol, "{name}");
+        }
+    }
+
+    #[test]
+    fn channel_types_are_listed_once_in_subscribe_order() {
+        let all = ChannelType::all();
+        assert_eq!(all, [ChannelType::Trades, ChannelType::OrderBooks, ChannelType::Funding]);
+
+        // No wildcard arm: a new variant breaks the build here, as a
+        // reminder to add it to all()
+        for channel in all {
+            match channel {
+                ChannelType::Trades | ChannelType::OrderBooks | ChannelType::Funding => {}
+            }
+        }
+
+        for (name, adapter) in adapters() {
+            for channel in adapter.metadata().channels {
+                assert!(all.contains(channel), "{name}: {channel:?}");
+            }
+        }
+    }
+}
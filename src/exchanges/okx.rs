@@ -1,18 +1,50 @@
 use serde_json::{Value, json};
+use std::time::Duration;
 
 use crate::{
-    util,
-    schema::{MarketMessage, TradeData},
+    net,
+    util::{self, json},
+    schema::{MarketMessage, TradeData, BookData, FundingData},
     config::ExchangeConfig,
 };
 
-use super::adapter::{ExchangeAdapter, ChannelType, ParseResult};
+use super::adapter::{ExchangeAdapter, ChannelType, ParseResult, ParseError, is_subscription_limit_error, AdapterMetadata};
+
+/// Extracts live spot symbols from a `/api/v5/public/instruments` response.
+///
+/// Only instruments with `state == "live"` are returned,
+/// converted to internal format (BASE/QUOTE).
+fn symbols_from_instruments(res: &Value) -> Vec<String> {
+    res.get("data")
+        .and_then(|v| v.as_array())
+        .map(|instruments| {
+            instruments
+                .iter()
+                .filter(|i| i.get("state").and_then(|v| v.as_str()) == Some("live"))
+                .filter_map(|i| {
+                    let base = i.get("baseCcy")?.as_str()?;
+                    let quote = i.get("quoteCcy")?.as_str()?;
+                    Some(format!("{}/{}", base.to_uppercase(), quote.to_uppercase()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// instId suffix of perpetual swaps (funding-rate channel)
+const SWAP_SUFFIX: &str = "-SWAP";
+
+/// Levels per side of the `books5` channel
+const BOOKS5_DEPTH: usize = 5;
 
 /// OKX WebSocket adapter
 ///
 /// OKX Spot WS v5:
 /// https://www.okx.com/docs-v5/en/#websocket-api-public-channel-trades
 ///
+/// Orderbooks use `books5` (top 5 snapshots); `orderbook.depth`
+/// above 5 is clamped to 5 with a warning.
+///
 /// DESIGN:
 /// - Pure protocol translation
 /// - No reconnect logic
@@ -31,11 +63,21 @@ impl ExchangeAdapter for OkxAdapter {
         "wss://ws.okx.com:8443/ws/v5/public"
     }
 
+    fn metadata(&self) -> AdapterMetadata {
+        AdapterMetadata {
+            name: self.name(),
+            ws_url: self.ws_url(),
+            channels: &[ChannelType::Trades, ChannelType::OrderBooks, ChannelType::Funding],
+            auth_required: false,
+            max_streams_per_connection: None,
+        }
+    }
+
     fn build_subscribe_message(
         &self,
         channel: ChannelType,
         pairs: &[String],
-        _config: &ExchangeConfig,
+        config: &ExchangeConfig,
     ) -> Value {
 
         match channel {
@@ -54,10 +96,105 @@ impl ExchangeAdapter for OkxAdapter {
                 })
             }
 
-            ChannelType::OrderBooks => json!({}),
+            // books5: top 5 levels as full snapshots
+            // (no checksum / sequencing). Deeper books would need
+            // the incremental `books` channel, which is not supported,
+            // so a larger `orderbook.depth` is clamped to 5.
+            ChannelType::OrderBooks => {
+                if pairs.is_empty() {
+                    return json!({});
+                }
+
+                if let Some(depth) = config.orderbook.as_ref().map(|o| o.depth)
+                    && depth > BOOKS5_DEPTH
+                {
+                    tracing::warn!(
+                        exchange = self.name(),
+                        "[OKX] orderbook.depth {depth} not supported – subscribing books5 ({BOOKS5_DEPTH} levels)"
+                    );
+                }
+
+                let args: Vec<Value> = pairs.iter().map(|p| {
+                    json!({
+                        "channel": "books5",
+                        "instId": util::symbol_to_exchange(self.name(), p)
+                    })
+                }).collect();
+
+                json!({
+                    "op": "subscribe",
+                    "args": args
+                })
+            }
+
+            // Perpetual swap of the pair, e.g. BTC-USDT-SWAP
+            ChannelType::Funding => {
+                let args: Vec<Value> = pairs.iter().map(|p| {
+                    json!({
+                        "channel": "funding-rate",
+                        "instId": format!("{}{}", util::symbol_to_exchange(self.name(), p), SWAP_SUFFIX)
+                    })
+                }).collect();
+
+                json!({
+                    "op": "subscribe",
+                    "args": args
+                })
+            }
         }
     }
 
+    async fn all_symbols(&self) -> anyhow::Result<Vec<String>> {
+        let res: Value = net::http_client()
+            .get("https://www.okx.com/api/v5/public/instruments?instType=SPOT")
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(symbols_from_instruments(&res))
+    }
+
+    /// `/api/v5/market/trades`: the last 500 trades
+    async fn recent_trades(&self, pair: &str, timeout: Duration) -> anyhow::Result<Vec<TradeData>> {
+        let url = format!(
+            "https://www.okx.com/api/v5/market/trades?instId={}&limit=500",
+            util::symbol_to_exchange(self.name(), pair)
+        );
+
+        let res: Value = net::http_client()
+            .get(url)
+            .timeout(timeout)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // { "data": [ { "tradeId": "242720720", "px": "0.01", "sz": "1", "side": "sell", "ts": "1654161646974" } ] }
+        Ok(res
+            .get("data")
+            .and_then(|d| d.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|t| {
+                let field = |k: &str| t.get(k).and_then(|v| v.as_str());
+
+                Some(TradeData {
+                    exchange: self.name().to_string(),
+                    symbol: pair.to_string(),
+                    timestamp: field("ts")?.parse().ok()?,
+                    price: field("px")?.to_string(),
+                    amount: field("sz")?.to_string(),
+                    side: field("side")?.to_lowercase(),
+                    trade_id: Some(field("tradeId")?.to_string()),
+                    event_ts: None,
+                    source: None,
+                })
+            })
+            .collect())
+    }
+
     fn parse_message(
         &self,
         raw: &str,
@@ -66,68 +203,262 @@ impl ExchangeAdapter for OkxAdapter {
 
         let v: Value = match serde_json::from_str(raw) {
             Ok(v) => v,
-            Err(_) => return ParseResult::Error,
+            Err(e) => return ParseResult::Error(ParseError::invalid_json(&e)),
         };
 
         // --------------------------------------------------
         // Control / error messages
         // --------------------------------------------------
+        // { "event": "error", "code": "60014", "msg": "Requests too frequent." }
         if let Some(event) = v.get("event").and_then(|v| v.as_str()) {
             if event == "error" {
-                return ParseResult::Error;
+                let msg = v.get("msg").and_then(|v| v.as_str()).unwrap_or_default();
+                if is_subscription_limit_error(msg) {
+                    return ParseResult::SubscriptionLimit(msg.to_string());
+                }
+                return ParseResult::Error(ParseError::exchange(msg));
             }
             return ParseResult::Control; // subscribe, unsubscribe, etc.
         }
 
         let arg = match v.get("arg") {
             Some(a) => a,
-            None => return ParseResult::Control,
+            None => return ParseResult::Ignored,
         };
 
         let channel = match arg.get("channel").and_then(|v| v.as_str()) {
             Some(c) => c,
-            None => return ParseResult::Control,
+            None => return ParseResult::Ignored,
         };
 
-        if channel != "trades" {
-            return ParseResult::Control;
+        if channel != "trades" && channel != "books5" && channel != "funding-rate" {
+            return ParseResult::Ignored;
         }
 
         let inst_id = match arg.get("instId").and_then(|v| v.as_str()) {
             Some(i) => i,
-            None => return ParseResult::Error,
+            None => return ParseResult::Error(ParseError::missing_field("arg.instId")),
         };
 
-        let symbol = util::symbol_from_exchange(exchange, inst_id);
+        let symbol = util::symbol_from_exchange(
+            exchange,
+            inst_id.strip_suffix(SWAP_SUFFIX).unwrap_or(inst_id),
+        );
+
+        // --------------------------------------------------
+        // funding-rate
+        // { "arg": {...}, "data": [ { "fundingRate": "0.0001", "nextFundingTime": "...", "ts": "..." } ] }
+        // --------------------------------------------------
+        if channel == "funding-rate" {
+            let f = match v.get("data").and_then(|d| d.get(0)) {
+                Some(f) => f,
+                None => return ParseResult::Control,
+            };
+
+            let ms = |k: &str| {
+                f.get(k)
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| s.parse::<i64>().ok())
+            };
+
+            return ParseResult::Market(MarketMessage::Funding(FundingData {
+                exchange: exchange.to_string(),
+                symbol,
+                timestamp: ms("ts").unwrap_or_else(util::now_ms),
+                mark_price: None,
+                funding_rate: f.get("fundingRate")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                next_funding_time: ms("fundingTime"),
+                source: None,
+            }));
+        }
+
+        // --------------------------------------------------
+        // books5 snapshot
+        // { "arg": {...}, "data": [ { "asks": [["px","sz","0","n"]], "bids": [...], "ts": "..." } ] }
+        // --------------------------------------------------
+        if channel == "books5" {
+            let book = match v.get("data").and_then(|d| d.get(0)) {
+                Some(b) => b,
+                None => return ParseResult::Control,
+            };
+
+            let levels = |side: &str| -> Vec<[String; 2]> {
+                book.get(side)
+                    .and_then(|v| v.as_array())
+                    .map(|levels| {
+                        levels
+                            .iter()
+                            .filter_map(|l| {
+                                Some([
+                                    l.get(0)?.as_str()?.to_string(),
+                                    l.get(1)?.as_str()?.to_string(),
+                                ])
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            };
+
+            return ParseResult::Market(MarketMessage::Book(BookData {
+                exchange: exchange.to_string(),
+                symbol,
+                timestamp: book.get("ts")
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| s.parse::<i64>().ok())
+                    .unwrap_or_else(util::now_ms),
+                asks: levels("asks"),
+                bids: levels("bids"),
+                source: None,
+                snapshot: true,
+            }));
+        }
 
         let trades = match v.get("data").and_then(|v| v.as_array()) {
             Some(t) if !t.is_empty() => t,
             _ => return ParseResult::Control,
         };
 
-        let t = &trades[0];
-
-        let msg = MarketMessage::Trade(TradeData {
-            exchange: exchange.to_string(),
-            symbol,
-            timestamp: t.get("ts")
-                .and_then(|v| v.as_str())
-                .and_then(|s| s.parse::<i64>().ok())
-                .unwrap_or_else(util::now_ms),
-            price: t.get("px")
-                .and_then(|v| v.as_str())
-                .unwrap_or("0")
-                .to_string(),
-            amount: t.get("sz")
-                .and_then(|v| v.as_str())
-                .unwrap_or("0")
-                .to_string(),
-            side: t.get("side")
-                .and_then(|v| v.as_str())
-                .unwrap_or("unknown")
-                .to_lowercase(),
+        // One frame may carry several trades
+        ParseResult::from_messages(
+            trades
+                .iter()
+                .map(|t| MarketMessage::Trade(TradeData {
+                    exchange: exchange.to_string(),
+                    symbol: symbol.clone(),
+                    timestamp: json::i64_or(t, "ts", util::now_ms()),
+                    price: json::decimal_str(t, "px"),
+                    amount: json::decimal_str(t, "sz"),
+                    side: json::str_or(t, "side", "unknown").to_lowercase(),
+                    trade_id: t.get("tradeId").and_then(|v| v.as_str()).map(str::to_string),
+                    event_ts: None,
+                    source: None,
+                }))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instruments_list_live_spot_pairs() {
+        let res = json!({
+            "code": "0",
+            "data": [
+                { "instId": "BTC-USDT", "baseCcy": "BTC", "quoteCcy": "USDT", "state": "live" },
+                { "instId": "ETH-USDC", "baseCcy": "ETH", "quoteCcy": "USDC", "state": "live" },
+                { "instId": "OLD-USDT", "baseCcy": "OLD", "quoteCcy": "USDT", "state": "suspend" }
+            ]
         });
 
-        ParseResult::Market(msg)
+        assert_eq!(symbols_from_instruments(&res), ["BTC/USDT", "ETH/USDC"]);
+    }
+
+    #[test]
+    fn funding_rate_frames_name_the_underlying_pair() {
+        let raw = json!({
+            "arg": { "channel": "funding-rate", "instId": "BTC-USDT-SWAP" },
+            "data": [{ "fundingRate": "0.0001", "fundingTime": "1700006400000", "ts": "1700000000000" }]
+        })
+        .to_string();
+
+        let ParseResult::Market(MarketMessage::Funding(funding)) = OkxAdapter.parse_message(&raw, "okx") else {
+            panic!("expected funding");
+        };
+
+        assert_eq!(funding.symbol, "BTC/USDT");
+        assert_eq!(funding.timestamp, 1_700_000_000_000);
+        assert_eq!(funding.funding_rate.as_deref(), Some("0.0001"));
+        assert_eq!(funding.next_funding_time, Some(1_700_006_400_000));
+        assert_eq!(funding.mark_price, None);
+    }
+
+    #[test]
+    fn books5_frames_are_book_snapshots() {
+        let raw = json!({
+            "arg": { "channel": "books5", "instId": "BTC-USDT" },
+            "data": [{
+                "asks": [["42001.5", "0.3", "0", "2"], ["42002", "1.1", "0", "1"]],
+                "bids": [["42000.9", "0.7", "0", "3"]],
+                "ts": "1700000000000"
+            }]
+        })
+        .to_string();
+
+        let ParseResult::Market(MarketMessage::Book(book)) = OkxAdapter.parse_message(&raw, "okx") else {
+            panic!("expected a book");
+        };
+
+        assert_eq!(book.symbol, "BTC/USDT");
+        assert_eq!(book.timestamp, 1_700_000_000_000);
+        assert_eq!(book.asks, [["42001.5", "0.3"], ["42002", "1.1"]].map(|l| l.map(String::from)));
+        assert_eq!(book.bids, [["42000.9", "0.7"]].map(|l| l.map(String::from)));
+        assert!(book.snapshot);
+    }
+
+    #[test]
+    fn deeper_books_are_clamped_to_books5() {
+        let config: ExchangeConfig = serde_json::from_value(json!({
+            "name": "okx",
+            "enabled": true,
+            "pairs": { "trades": [], "orderbooks": ["BTC/USDT"] },
+            "orderbook": { "depth": 20, "update_interval_ms": 100 }
+        }))
+        .unwrap();
+
+        let msg = OkxAdapter.build_subscribe_message(ChannelType::OrderBooks, &["BTC/USDT".to_string()], &config);
+
+        assert_eq!(msg, json!({ "op": "subscribe", "args": [{ "channel": "books5", "instId": "BTC-USDT" }] }));
+    }
+
+    #[test]
+    fn every_trade_of_a_frame_is_returned() {
+        let trade = |id: &str| json!({ "tradeId": id, "px": "42000.1", "sz": "0.01", "side": "buy", "ts": "1700000000000" });
+        let frame = |trades: Vec<Value>| {
+            json!({ "arg": { "channel": "trades", "instId": "BTC-USDT" }, "data": trades }).to_string()
+        };
+
+        let ParseResult::Batch(batch) = OkxAdapter.parse_message(&frame(vec![trade("1"), trade("2")]), "okx") else {
+            panic!("expected a batch");
+        };
+        let ids: Vec<_> = batch
+            .iter()
+            .map(|m| match m {
+                MarketMessage::Trade(t) => t.trade_id.clone().unwrap(),
+                other => panic!("expected trades, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(ids, ["1", "2"]);
+
+        assert!(matches!(
+            OkxAdapter.parse_message(&frame(vec![trade("3")]), "okx"),
+            ParseResult::Market(MarketMessage::Trade(_))
+        ));
+    }
+
+    #[test]
+    fn request_limit_errors_are_subscription_limits() {
+        let too_frequent = r#"{"event":"error","code":"60014","msg":"Requests too frequent."}"#;
+        assert!(matches!(OkxAdapter.parse_message(too_frequent, "okx"), ParseResult::SubscriptionLimit(_)));
+
+        let bad_channel = r#"{"event":"error","code":"60018","msg":"Wrong URL or channel:tickers"}"#;
+        assert!(matches!(OkxAdapter.parse_message(bad_channel, "okx"), ParseResult::Error(_)));
+    }
+
+    #[test]
+    fn unknown_channels_are_ignored() {
+        let tickers = json!({
+            "arg": { "channel": "tickers", "instId": "BTC-USDT" },
+            "data": [{ "instId": "BTC-USDT", "last": "42000" }]
+        })
+        .to_string();
+        let ack = json!({ "event": "subscribe", "arg": { "channel": "trades", "instId": "BTC-USDT" } }).to_string();
+
+        assert!(matches!(OkxAdapter.parse_message(&tickers, "okx"), ParseResult::Ignored));
+        assert!(matches!(OkxAdapter.parse_message(&ack, "okx"), ParseResult::Control));
     }
 }
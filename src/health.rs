@@ -0,0 +1,73 @@
+//! Startup liveness probe.
+//!
+//! A collector that never receives data (blocked network, broken
+//! subscriptions, expired endpoints) would otherwise look healthy to
+//! an orchestrator. The probe turns that into a process exit, so the
+//! container gets restarted.
+
+use std::sync::atomic::Ordering;
+
+use tokio::time::{sleep, Duration};
+
+use crate::config::HealthConfig;
+use crate::metrics::METRICS;
+
+/// Outcome of the startup probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Passed,
+    Failed,
+}
+
+/// Judges `received` messages against the configured threshold.
+pub fn check(received: usize, cfg: &HealthConfig) -> Verdict {
+    if received < cfg.min_messages() {
+        Verdict::Failed
+    } else {
+        Verdict::Passed
+    }
+}
+
+/// Waits for the startup grace period and judges the messages
+/// received by then.
+///
+/// Runs once. The caller exits the process on `Verdict::Failed`.
+pub async fn startup_probe(cfg: HealthConfig) -> Verdict {
+    sleep(Duration::from_millis(cfg.startup_grace_ms)).await;
+
+    let received = METRICS.trades_received.load(Ordering::Relaxed);
+    let verdict = check(received, &cfg);
+
+    match verdict {
+        Verdict::Failed => tracing::error!(
+            "[FATAL] only {} messages received within {}ms of startup (required: {}) – exiting",
+            received,
+            cfg.startup_grace_ms,
+            cfg.min_messages()
+        ),
+        Verdict::Passed => tracing::info!("[HEALTH] startup probe passed ({} messages)", received),
+    }
+
+    verdict
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(min_messages: Option<usize>) -> HealthConfig {
+        HealthConfig { startup_grace_ms: 30_000, min_messages }
+    }
+
+    #[test]
+    fn collectors_without_data_fail() {
+        assert_eq!(check(0, &health(None)), Verdict::Failed);
+        assert_eq!(check(1, &health(None)), Verdict::Passed);
+    }
+
+    #[test]
+    fn the_threshold_is_configurable() {
+        assert_eq!(check(99, &health(Some(100))), Verdict::Failed);
+        assert_eq!(check(100, &health(Some(100))), Verdict::Passed);
+    }
+}
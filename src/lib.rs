@@ -0,0 +1,40 @@
+//! Multi-exchange WebSocket collector.
+//!
+//! The binary (`main.rs`) wires these modules together. Code
+//! embedding the collector can consume forwarded messages
+//! in-process via `stream::subscribe`.
+
+// ------------------------------------------------------------
+// Module declarations
+// ------------------------------------------------------------
+//
+// Each module represents a well-defined responsibility:
+//
+// - config:        Configuration structs loaded from JSON
+// - schema:        Strongly typed market message definitions
+// - util:          Shared helper utilities (time, symbol handling, etc.)
+// - exchanges:     Exchange adapters and adapter registry
+// - master_sender: WebSocket client pool for sending data to the master
+// - collector:     Exchange runtime (connection + subscription logic)
+// - admin:         Optional HTTP endpoint for runtime operations
+// - net:           TLS / connection helpers (e.g. certificate pinning)
+// - health:        Startup liveness probe
+// - output:        Output sinks (master, UNIX socket, …)
+// - backoff:       Reconnect delay policies
+// - logging:       Log output format (text / JSON)
+//
+pub mod config;
+pub mod schema;
+pub mod util;
+pub mod exchanges;
+pub mod master_sender;
+pub mod collector;
+pub mod metrics;
+pub mod admin;
+pub mod net;
+pub mod health;
+pub mod output;
+pub mod backoff;
+pub mod logging;
+
+pub use collector::stream;
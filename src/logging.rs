@@ -0,0 +1,184 @@
+//! Log output (`log.format`).
+//!
+//! All collector logs are `tracing` events, rendered as:
+//! - text: the message only, as plain lines
+//! - json: one object per line with `level`, `msg` and the event
+//!   fields (`exchange`, `channel`, `conn_id`, …)
+//!
+//! Warnings and errors go to stderr, everything else to stdout.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, MakeWriter};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::config::LogFormat;
+use crate::util;
+
+/// Installs the global log subscriber. Call once, at startup.
+pub fn init(format: LogFormat) {
+    let writer = std::io::stderr
+        .with_max_level(Level::WARN)
+        .or_else(std::io::stdout);
+
+    if tracing::subscriber::set_global_default(subscriber(format, writer)).is_err() {
+        eprintln!("log subscriber already installed");
+    }
+}
+
+fn subscriber<W>(format: LogFormat, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(Level::INFO)
+        .with_writer(writer);
+
+    match format {
+        LogFormat::Text => Box::new(builder.event_format(TextFormat).finish()),
+        LogFormat::Json => Box::new(builder.event_format(JsonFormat).finish()),
+    }
+}
+
+/// The message alone; event fields are already part of it.
+struct TextFormat;
+
+impl<S, N> FormatEvent<S, N> for TextFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, _ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let fields = collect_fields(event);
+        let msg = fields.get("msg").and_then(|m| m.as_str()).unwrap_or_default();
+
+        writeln!(writer, "{msg}")
+    }
+}
+
+/// `{"ts":…,"level":"WARN","msg":"…","exchange":"binance",…}`
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, _ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let mut line = Map::new();
+        line.insert("ts".into(), util::now_ms().into());
+        line.insert("level".into(), event.metadata().level().as_str().into());
+        line.extend(collect_fields(event));
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+/// Event fields as JSON; the message is stored as `msg`.
+fn collect_fields(event: &Event<'_>) -> Map<String, Value> {
+    let mut visitor = JsonVisitor(Map::new());
+    event.record(&mut visitor);
+    visitor.0
+}
+
+struct JsonVisitor(Map<String, Value>);
+
+impl JsonVisitor {
+    fn insert(&mut self, field: &Field, value: Value) {
+        let key = match field.name() {
+            "message" => "msg",
+            name => name,
+        };
+        self.0.insert(key.to_string(), value);
+    }
+}
+
+impl Visit for JsonVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{value:?}").into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// Collects all written log lines
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn capture(format: LogFormat, log: impl FnOnce()) -> String {
+        let captured = Captured::default();
+        let writer = captured.clone();
+
+        tracing::subscriber::with_default(subscriber(format, move || writer.clone()), log);
+
+        String::from_utf8(captured.0.lock().unwrap().clone()).unwrap()
+    }
+
+    #[test]
+    fn json_lines_are_parseable_with_fields() {
+        let out = capture(LogFormat::Json, || {
+            tracing::warn!(exchange = "binance", channel = ?["trades"], conn_id = 7usize, "[WS IDLE] \"quoted\"\nsecond line");
+            tracing::info!("plain");
+        });
+
+        let lines: Vec<Value> = out
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("JSON log line"))
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "WARN");
+        assert_eq!(lines[0]["msg"], "[WS IDLE] \"quoted\"\nsecond line");
+        assert_eq!(lines[0]["exchange"], "binance");
+        assert_eq!(lines[0]["channel"], "[\"trades\"]");
+        assert_eq!(lines[0]["conn_id"], 7);
+        assert_eq!(lines[1]["msg"], "plain");
+    }
+
+    #[test]
+    fn text_lines_are_the_message() {
+        let out = capture(LogFormat::Text, || {
+            tracing::info!(exchange = "okx", "[STREAM UP][okx] data flowing");
+            tracing::debug!("below the level");
+        });
+
+        assert_eq!(out, "[STREAM UP][okx] data flowing\n");
+    }
+}
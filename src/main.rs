@@ -1,37 +1,23 @@
 // ------------------------------------------------------------
-// Module declarations
-// ------------------------------------------------------------
-//
-// Each module represents a well-defined responsibility:
-//
-// - config:        Configuration structs loaded from JSON
-// - schema:        Strongly typed market message definitions
-// - util:          Shared helper utilities (time, symbol handling, etc.)
-// - exchanges:     Exchange adapters and adapter registry
-// - master_sender: WebSocket client pool for sending data to the master
-// - collector:     Exchange runtime (connection + subscription logic)
-//
-mod config;
-mod schema;
-mod util;
-mod exchanges;
-mod master_sender;
-mod collector;
-mod metrics;
-// ------------------------------------------------------------
 // External dependencies
 // ------------------------------------------------------------
 
 use rustls::crypto::{CryptoProvider, ring};
 
+use ftsobest_websocket_multi_collector::{
+    admin, collector, config, exchanges, health, logging, master_sender, metrics, output,
+};
+
 use config::Config;
-use exchanges::get_adapter;
-use collector::runner::run_exchange;
+use collector::registry::ExchangeRegistry;
+use collector::runner::ConnectionBudget;
+use exchanges::adapter::ChannelType;
 use master_sender::MasterPool;
 use metrics::METRICS;
 
 use std::fs;
-use std::sync::atomic::Ordering;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
 
@@ -46,7 +32,7 @@ use tokio::time::sleep;
 // - Load configuration
 // - Create and manage the MasterPool
 // - Start enabled exchange collectors
-// - Keep the process alive indefinitely
+// - Keep the process alive until shutdown is requested
 //
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -64,11 +50,25 @@ async fn main() -> anyhow::Result<()> {
     // --------------------------------------------------------
     // Load configuration from disk
     //
+    // Default: config.json. Multiple `--config` files are
+    // deep-merged in order (base first, overrides last).
+    //
     // NOTE:
     // - The config file contains sensitive data (master key).
     // - It must not be committed to version control.
     // --------------------------------------------------------
-    let config: Config = load_config("config.json")?;
+    let args = parse_args()?;
+    let config: Config = load_config(&args.config_files)?;
+
+    // `--validate`: pre-deploy gate, exits without starting
+    if args.validate {
+        std::process::exit(if validate_config(&config) { 0 } else { 1 });
+    }
+
+    logging::init(config.log.format);
+
+    // Semantic problems (chunk size 0, malformed pairs, …) refuse startup
+    check_config(&config)?;
 
     // --------------------------------------------------------
     // Initialize the MasterPool
@@ -81,16 +81,28 @@ async fn main() -> anyhow::Result<()> {
     // - Load balancing
     // - Backpressure handling
     // - Optional demo mode (no data sent)
+    // - Optional message envelope
+    // - Optional TLS certificate pinning
     // --------------------------------------------------------
-    let master = MasterPool::new(
-        config.master.url.clone(),
-        config.master.key.clone(),
-        config.debug
-            .as_ref()
-            .map_or(false, |d| d.log.unwrap_or(false)),
-        config.master.connections,
-        config.master.demo.unwrap_or(false),
-    ).await;
+    let debug_log = config.debug
+        .as_ref()
+        .is_some_and(|d| d.log.unwrap_or(false));
+
+    let master = MasterPool::new(&config.master, debug_log).await?;
+
+    // --------------------------------------------------------
+    // Build the output sink
+    //
+    // The master pool, or all sinks from `output.sinks`
+    // (master, local UNIX socket, …) behind a fan-out, with
+    // optional validation / rounding in front.
+    // --------------------------------------------------------
+    let output = output::build(
+        master.clone(),
+        config.output.as_ref(),
+        config.normalize.as_ref(),
+        config.validate_output,
+    )?;
 
     // --------------------------------------------------------
     // Start metrics reporter (periodic, low-noise)
@@ -99,52 +111,105 @@ async fn main() -> anyhow::Result<()> {
         loop {
             sleep(Duration::from_secs(10)).await;
 
-            println!(
-                "[METRICS] ex={} ws={} tp={} ob={} recv={} sent={} dropped={} parse_err={} send_err={} reconnects={} sub_send={} sub_send_err={}",
-                METRICS.exchanges_active.load(Ordering::Relaxed),
-                METRICS.ws_connections_active.load(Ordering::Relaxed),
-                METRICS.trade_pairs_active.load(Ordering::Relaxed),
-                METRICS.orderbook_pairs_active.load(Ordering::Relaxed),
-                METRICS.trades_received.load(Ordering::Relaxed),
-                METRICS.trades_forwarded.load(Ordering::Relaxed),
-                METRICS.dropped_messages.load(Ordering::Relaxed),
-                METRICS.parse_errors.load(Ordering::Relaxed),
-                METRICS.send_errors.load(Ordering::Relaxed),
-                METRICS.ws_reconnects.load(Ordering::Relaxed),
-                METRICS.subscriptions_sent.load(Ordering::Relaxed),
-                METRICS.subscription_errors.load(Ordering::Relaxed),
-            );
+            tracing::info!("[METRICS] {}", METRICS.snapshot());
         }
     });
 
     // --------------------------------------------------------
-    // Start all enabled exchange collectors
+    // Register all exchanges, start the enabled ones
+    //
+    // Disabled exchanges stay registered so they can be
+    // enabled at runtime via the admin endpoint.
+    //
+    // `limits.max_connections` caps the WS connections of all
+    // exchanges together.
+    //
+    // Exchanges with `master_url` get a dedicated master pool,
+    // built when the exchange first starts.
     // --------------------------------------------------------
-    for exchange_cfg in config.exchanges.iter().filter(|e| e.enabled) {
-        let Some(adapter) = get_adapter(&exchange_cfg.name) else {
-            eprintln!("Exchange '{}' is not supported", exchange_cfg.name);
-            continue;
-        };
+    let outputs = output::MasterRoutes::new(
+        output,
+        config.master.clone(),
+        debug_log,
+        config.normalize.clone(),
+        config.validate_output,
+    );
+
+    let registry = Arc::new(ExchangeRegistry::new(
+        outputs,
+        ConnectionBudget::new(config.limits.as_ref().and_then(|l| l.max_connections)),
+    ));
+
+    for exchange_cfg in &config.exchanges {
+        if !registry.register(exchange_cfg.clone()).await {
+            tracing::error!(
+                "Exchange '{}' is not supported (supported: {})",
+                exchange_cfg.name,
+                exchanges::SUPPORTED.join(", ")
+            );
+        }
+    }
+
+    registry.start_enabled().await?;
 
-        println!("Starting {} collector", exchange_cfg.name);
+    // --------------------------------------------------------
+    // Start admin endpoint (optional)
+    // --------------------------------------------------------
+    if let Some(admin_cfg) = config.admin.clone() {
+        let registry = registry.clone();
+        let master = master.clone();
 
-        // 👇 METRIC: one exchange instance started
-        METRICS.exchanges_active.fetch_add(1, Ordering::Relaxed);
+        tokio::spawn(async move {
+            if let Err(e) = admin::serve(admin_cfg.bind, registry, master).await {
+                tracing::error!("Admin endpoint failed: {}", e);
+            }
+        });
+    }
 
-        run_exchange(
-            adapter,
-            exchange_cfg.clone(),
-            master.clone(),
-        ).await?;
+    // --------------------------------------------------------
+    // Start health probe (optional)
+    //
+    // Exits the process if no data flows after startup.
+    // --------------------------------------------------------
+    if let Some(health_cfg) = config.health.clone() {
+        tokio::spawn(async move {
+            if health::startup_probe(health_cfg).await == health::Verdict::Failed {
+                std::process::exit(1);
+            }
+        });
     }
 
     // --------------------------------------------------------
-    // Keep the process alive forever
+    // Run until Ctrl+C / SIGINT / SIGTERM
     //
-    // All collectors run in background tasks.
-    // This future never resolves.
+    // All collectors run in background tasks. On shutdown they
+    // are stopped and every output queue (shared master,
+    // dedicated master pools, per-sink queues) is drained
+    // before the process exits.
     // --------------------------------------------------------
-    futures_util::future::pending::<()>().await;
+    shutdown_signal().await?;
+
+    tracing::info!("Shutdown requested – draining output queues");
+    registry.shutdown().await;
+    master.shutdown().await;
+
+    Ok(())
+}
+
+// ------------------------------------------------------------
+// Shutdown signal
+// ------------------------------------------------------------
+//
+// Ctrl+C / SIGINT for interactive runs, SIGTERM for
+// `docker stop`, systemd and Kubernetes.
+//
+async fn shutdown_signal() -> anyhow::Result<()> {
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => res?,
+        _ = sigterm.recv() => {}
+    }
 
     Ok(())
 }
@@ -153,16 +218,342 @@ async fn main() -> anyhow::Result<()> {
 // Configuration loader
 // ------------------------------------------------------------
 //
-// Reads a JSON configuration file from disk and deserializes
-// it into the strongly typed `Config` structure.
+// Reads one or more JSON configuration files from disk and
+// deserializes them into the strongly typed `Config` structure.
+//
+// Files are applied in order; later files deep-merge over
+// earlier ones (see `merge_json`). This allows a shared base
+// config plus per-environment overrides:
+//
+//   --config base.json --config prod.json
+//
+// `--config-dir` adds all `*.json` files of a directory in
+// lexical order. Their `exchanges` lists are appended instead
+// of replaced, so each exchange can live in its own file:
+//
+//   --config base.json --config-dir conf.d/
 //
 // TODO:
 // - Support loading from environment variables
-// - Support CLI override (e.g. --config path)
-// - Validate config semantics (e.g. empty pair lists)
 //
-fn load_config(path: &str) -> anyhow::Result<Config> {
-    let data = fs::read_to_string(path)?;
-    let cfg = serde_json::from_str(&data)?;
-    Ok(cfg)
+fn load_config(files: &[ConfigFile]) -> anyhow::Result<Config> {
+    let mut merged = serde_json::Value::Null;
+
+    for file in files {
+        let path = file.path.display();
+        let data = fs::read_to_string(&file.path)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+        let mut value: serde_json::Value = serde_json::from_str(&data)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+
+        if file.append_exchanges
+            && let Some(serde_json::Value::Array(exchanges)) =
+                value.as_object_mut().and_then(|o| o.remove("exchanges"))
+        {
+            append_exchanges(&mut merged, exchanges);
+        }
+
+        merge_json(&mut merged, value);
+    }
+
+    serde_json::from_value(merged)
+        .map_err(|e| anyhow::anyhow!("merged config: {}", e))
+}
+
+// Appends to the top-level `exchanges` list of `merged`
+fn append_exchanges(merged: &mut serde_json::Value, exchanges: Vec<serde_json::Value>) {
+    if !merged.is_object() {
+        *merged = serde_json::json!({});
+    }
+
+    let list = merged
+        .as_object_mut()
+        .expect("merged config is an object")
+        .entry("exchanges")
+        .or_insert_with(|| serde_json::json!([]));
+
+    match list {
+        serde_json::Value::Array(list) => list.extend(exchanges),
+        other => *other = serde_json::Value::Array(exchanges),
+    }
+}
+
+// ------------------------------------------------------------
+// JSON deep merge
+// ------------------------------------------------------------
+//
+// - Objects are merged key by key (recursively)
+// - Everything else, including arrays, replaces the old value
+//
+fn merge_json(base: &mut serde_json::Value, over: serde_json::Value) {
+    match (base, over) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(over)) => {
+            for (key, value) in over {
+                merge_json(base.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+// ------------------------------------------------------------
+// Config validation (`--validate`)
+// ------------------------------------------------------------
+//
+// Runs `Config::validate`, resolves every exchange adapter and
+// checks that each configured channel is supported by it.
+// Prints all problems plus a summary; returns true if valid.
+//
+fn validate_config(config: &Config) -> bool {
+    let problems = config_problems(config);
+
+    for problem in &problems {
+        eprintln!("INVALID: {problem}");
+    }
+
+    let enabled = config.exchanges.iter().filter(|e| e.enabled).count();
+
+    if problems.is_empty() {
+        println!(
+            "config OK: {} exchanges ({} enabled)",
+            config.exchanges.len(),
+            enabled
+        );
+        true
+    } else {
+        println!("config INVALID: {} problem(s)", problems.len());
+        false
+    }
+}
+
+/// Problems reported by `--validate`.
+fn config_problems(config: &Config) -> Vec<String> {
+    let mut problems = config.validate();
+
+    for ex in &config.exchanges {
+        let Some(adapter) = exchanges::get_adapter(&ex.name) else {
+            problems.push(format!(
+                "exchange {}: not supported (supported: {})",
+                ex.name,
+                exchanges::SUPPORTED.join(", ")
+            ));
+            continue;
+        };
+
+        let supported = adapter.metadata().channels;
+
+        for &channel in ChannelType::all() {
+            // REST polling replaces the orderbook WebSocket
+            if channel == ChannelType::OrderBooks && ex.rest_poll.is_some() {
+                continue;
+            }
+
+            let pairs = ex.pairs.for_channel(channel).len();
+            if pairs > 0 && !supported.contains(&channel) {
+                problems.push(format!(
+                    "exchange {}: {:?} not supported by the adapter ({} pairs)",
+                    ex.name, channel, pairs
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+// ------------------------------------------------------------
+// Startup config check
+// ------------------------------------------------------------
+//
+// `Config::validate` problems abort startup with all problems
+// listed. Unsupported exchanges are not checked here; they are
+// logged when registering.
+//
+fn check_config(config: &Config) -> anyhow::Result<()> {
+    let problems = config.validate();
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    Err(anyhow::anyhow!(
+        "invalid config, refusing to start:\n   {}",
+        problems.join("\n   ")
+    ))
+}
+
+// ------------------------------------------------------------
+// Command line
+// ------------------------------------------------------------
+//
+// - `--config <path>`     → config file
+// - `--config-dir <dir>`  → all `*.json` files, sorted by name
+// - `--validate`          → check the config and exit
+//
+// Config files keep command line order; without any,
+// `config.json` is used.
+//
+struct CliArgs {
+    config_files: Vec<ConfigFile>,
+    validate: bool,
+}
+
+struct ConfigFile {
+    path: PathBuf,
+
+    /// Append `exchanges` instead of replacing (`--config-dir`)
+    append_exchanges: bool,
+}
+
+fn parse_args() -> anyhow::Result<CliArgs> {
+    let mut files = Vec::new();
+    let mut validate = false;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => files.push(ConfigFile {
+                path: args.next()
+                    .ok_or_else(|| anyhow::anyhow!("--config requires a path"))?
+                    .into(),
+                append_exchanges: false,
+            }),
+            "--config-dir" => {
+                let dir = args.next()
+                    .ok_or_else(|| anyhow::anyhow!("--config-dir requires a path"))?;
+                files.extend(dir_config_files(&dir)?);
+            }
+            "--validate" => validate = true,
+            other => return Err(anyhow::anyhow!("unknown argument: {}", other)),
+        }
+    }
+
+    if files.is_empty() {
+        files.push(ConfigFile {
+            path: "config.json".into(),
+            append_exchanges: false,
+        });
+    }
+
+    Ok(CliArgs {
+        config_files: files,
+        validate,
+    })
+}
+
+fn dir_config_files(dir: &str) -> anyhow::Result<Vec<ConfigFile>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("{}: {}", dir, e))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+
+    if paths.is_empty() {
+        return Err(anyhow::anyhow!("{}: no *.json files", dir));
+    }
+
+    paths.sort();
+
+    Ok(paths
+        .into_iter()
+        .map(|path| ConfigFile {
+            path,
+            append_exchanges: true,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(trades_per_connection: u32) -> Config {
+        serde_json::from_value(serde_json::json!({
+            "master": { "url": "wss://master.example", "connections": 1, "key": "k" },
+            "exchanges": [{
+                "name": "binance",
+                "enabled": true,
+                "pairs": { "trades": ["BTC/USDT"], "orderbooks": [] },
+                "chunking": { "trades_per_connection": trades_per_connection }
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn valid_config_starts() {
+        assert!(check_config(&config(10)).is_ok());
+    }
+
+    #[test]
+    fn invalid_config_refuses_to_start() {
+        let err = check_config(&config(0)).unwrap_err().to_string();
+
+        assert!(err.contains("exchange binance: chunking.trades_per_connection must be at least 1"));
+    }
+
+    #[test]
+    fn validate_reports_unknown_exchanges_and_unsupported_channels() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "master": { "url": "wss://master.example", "connections": 1, "key": "k" },
+            "exchanges": [
+                { "name": "nosuchex", "enabled": true, "pairs": { "trades": ["BTC/USDT"], "orderbooks": [] } },
+                { "name": "bitstamp", "enabled": true, "pairs": { "trades": ["BTC/USD"], "orderbooks": ["BTC/USD"] } }
+            ]
+        }))
+        .unwrap();
+
+        let problems = config_problems(&config);
+
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].starts_with("exchange nosuchex: not supported (supported: "));
+        assert_eq!(problems[1], "exchange bitstamp: OrderBooks not supported by the adapter (1 pairs)");
+        assert!(!validate_config(&config));
+    }
+
+    #[test]
+    fn later_configs_override_earlier_ones_key_by_key() {
+        let mut base = serde_json::json!({
+            "master": { "url": "wss://base.example", "connections": 2 },
+            "exchanges": [{ "name": "binance" }, { "name": "okx" }]
+        });
+
+        merge_json(&mut base, serde_json::json!({
+            "master": { "url": "wss://prod.example" },
+            "exchanges": [{ "name": "kraken" }]
+        }));
+
+        assert_eq!(
+            base,
+            serde_json::json!({
+                "master": { "url": "wss://prod.example", "connections": 2 },
+                "exchanges": [{ "name": "kraken" }]
+            })
+        );
+    }
+
+    #[test]
+    fn config_dir_appends_exchanges_in_file_order() {
+        let dir = std::env::temp_dir().join(format!("collector-conf-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let exchange = |name: &str| serde_json::json!({ "exchanges": [{
+            "name": name, "enabled": true, "pairs": { "trades": ["BTC/USDT"], "orderbooks": [] }
+        }] });
+        let files = [
+            ("00-master.json", serde_json::json!({ "master": { "url": "wss://master.example", "connections": 1, "key": "k" } })),
+            ("20-okx.json", exchange("okx")),
+            ("10-binance.json", exchange("binance")),
+        ];
+        for (file, value) in &files {
+            fs::write(dir.join(file), value.to_string()).unwrap();
+        }
+        fs::write(dir.join("README.txt"), "not a config").unwrap();
+
+        let loaded = dir_config_files(dir.to_str().unwrap()).and_then(|files| load_config(&files));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<_> = loaded.unwrap().exchanges.into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["binance", "okx"]);
+    }
 }
@@ -1,19 +1,42 @@
 use futures_util::{SinkExt, StreamExt};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio_tungstenite::tungstenite::Message;
 
 use std::sync::{
     Arc,
-    atomic::{AtomicUsize, Ordering},
+    atomic::{AtomicU64, AtomicUsize, Ordering},
 };
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use flate2::{Compression, write::GzEncoder};
 
 use serde_json::Value;
 use anyhow::Result;
 
-use tokio::time::{Duration, sleep};
-use tokio::sync::{mpsc, OnceCell, Mutex, Notify};
+use tokio::time::{Duration, Instant, sleep, timeout_at};
+use tokio::sync::{mpsc, watch, OnceCell, Mutex, Notify};
 
 use rand::random_range;
 
+use crate::{config::{LoginFormat, MasterConfig}, metrics::METRICS, net, output::SendMeta, util};
+use crate::backoff::{ExponentialBackoff, Outcome, ReconnectPolicy};
+
+/// Maximum time spent flushing queued messages on shutdown
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default size limit of the dead-letter file
+const DEFAULT_DEAD_LETTER_MAX_BYTES: u64 = 100 * 1024 * 1024;
+
+/// First reconnect delay after a lost master connection
+const MASTER_MIN_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Upper bound of the master reconnect delay
+const MASTER_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Random share taken off each reconnect delay, so all master
+/// connections do not reconnect in lockstep
+const MASTER_BACKOFF_JITTER: f64 = 0.2;
+
 /// ============================================================
 /// MasterSender
 /// ============================================================
@@ -26,6 +49,7 @@ use rand::random_range;
 /// - Send periodic JSON pings
 /// - Detect connection loss
 /// - Reconnect automatically
+/// - Drain the queue on graceful shutdown
 ///
 /// Design constraints:
 /// - Must never block collectors
@@ -44,6 +68,12 @@ pub struct MasterSender {
     ///
     /// Used to prevent sending before the first successful login.
     connected: Arc<OnceCell<()>>,
+
+    /// Set to `true` to request a graceful shutdown (drain + close).
+    shutdown: Arc<watch::Sender<bool>>,
+
+    /// Becomes `true` once the background loop has fully stopped.
+    stopped: watch::Receiver<bool>,
 }
 
 impl MasterSender {
@@ -62,23 +92,34 @@ impl MasterSender {
         master_url: String,
         login_msg: String,
         debug: bool,
+        tls: net::TlsSettings,
     ) -> Self {
         let (tx, _) = mpsc::channel::<Value>(10_000);
         let queue = Arc::new(Mutex::new(tx));
         let connected = Arc::new(OnceCell::new());
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (stopped_tx, stopped_rx) = watch::channel(false);
 
         let sender = Self {
             queue: queue.clone(),
             connected: connected.clone(),
+            shutdown: Arc::new(shutdown_tx),
+            stopped: stopped_rx,
         };
 
         // Background reconnect loop
         tokio::spawn({
             let queue = queue.clone();
             let connected = connected.clone();
+            let mut shutdown = shutdown_rx;
 
             async move {
-                loop {
+                let mut backoff = ExponentialBackoff::new(MASTER_MIN_BACKOFF, MASTER_MAX_BACKOFF)
+                    .with_jitter(MASTER_BACKOFF_JITTER);
+
+                while !*shutdown.borrow() {
+                    let started = Instant::now();
+
                     // Create a fresh queue per connection
                     let (tx, rx) = mpsc::channel::<Value>(10_000);
                     {
@@ -91,15 +132,29 @@ impl MasterSender {
                         master_url.clone(),
                         login_msg.clone(),
                         debug,
+                        tls.clone(),
                         rx,
                         connected.clone(),
+                        shutdown.clone(),
                     ).await {
-                        eprintln!("Master connection lost: {}", e);
+                        tracing::warn!("Master connection lost: {}", e);
                     }
 
-                    // Backoff before reconnect
-                    sleep(Duration::from_secs(30)).await;
+                    // A connection that lasted restarts the backoff
+                    let outcome = if started.elapsed() > MASTER_MAX_BACKOFF {
+                        Outcome::Stable
+                    } else {
+                        Outcome::Failed
+                    };
+
+                    // Backoff before reconnect (interrupted by shutdown)
+                    tokio::select! {
+                        _ = sleep(backoff.next_delay(outcome)) => {}
+                        _ = shutdown.changed() => {}
+                    }
                 }
+
+                let _ = stopped_tx.send(true);
             }
         });
 
@@ -116,14 +171,17 @@ impl MasterSender {
     ///
     /// TERMINATION:
     /// - Returns an error when the connection is closed
+    /// - Returns Ok after a graceful shutdown drain
     async fn try_connect(
         master_url: String,
         login_msg: String,
         debug: bool,
+        tls: net::TlsSettings,
         mut rx: mpsc::Receiver<Value>,
         connected: Arc<OnceCell<()>>,
+        mut shutdown: watch::Receiver<bool>,
     ) -> Result<()> {
-        let (ws, _) = connect_async(&master_url).await?;
+        let ws = net::connect_ws(&master_url, &tls, &[]).await?;
         let (mut write, mut read) = ws.split();
 
         // Used to notify the writer when the reader detects EOF
@@ -138,7 +196,7 @@ impl MasterSender {
         // ------------------------------------------------------------
         write.send(Message::Text(login_msg.clone().into())).await?;
         if debug {
-            println!("Login message sent: {}", login_msg);
+            tracing::info!("Login message sent: {}", login_msg);
         }
 
         let mut ping_interval = tokio::time::interval(Duration::from_secs(30));
@@ -155,15 +213,15 @@ impl MasterSender {
 
             async move {
                 while let Some(Ok(msg)) = read.next().await {
-                    if let Message::Text(text) = msg {
-                        if debug {
-                            println!("[Master RECV] {}", text);
-                        }
+                    if let Message::Text(text) = msg
+                        && debug
+                    {
+                        tracing::info!("[Master RECV] {}", text);
                     }
                 }
 
                 if debug {
-                    println!("Master reader ended (EOF)");
+                    tracing::info!("Master reader ended (EOF)");
                 }
 
                 closed.notify_waiters();
@@ -179,8 +237,9 @@ impl MasterSender {
                 Some(msg) = rx.recv() => {
                     let json = serde_json::to_string(&msg)?;
                     if debug {
-                        println!("[Master SEND] {}", json);
+                        tracing::info!("[Master SEND] {}", json);
                     }
+                    METRICS.bytes_sent_master.fetch_add(json.len(), Ordering::Relaxed);
                     write.send(Message::Text(json.into())).await?;
                 }
 
@@ -188,7 +247,7 @@ impl MasterSender {
                 _ = ping_interval.tick() => {
                     let ping = r#"{"op":"ping"}"#;
                     if debug {
-                        println!("Master ping");
+                        tracing::info!("Master ping");
                     }
                     write.send(Message::Text(ping.into())).await?;
                 }
@@ -196,10 +255,44 @@ impl MasterSender {
                 // Reader detected connection close
                 _ = closed.notified() => {
                     if debug {
-                        println!("Writer stopping: connection closed by master");
+                        tracing::info!("Writer stopping: connection closed by master");
                     }
                     return Err(anyhow::anyhow!("Master closed connection"));
                 }
+
+                // Graceful shutdown: stop accepting, flush, close
+                _ = shutdown.changed() => {
+                    rx.close();
+
+                    let deadline = Instant::now() + DRAIN_TIMEOUT;
+                    let mut flushed = 0usize;
+
+                    loop {
+                        match timeout_at(deadline, rx.recv()).await {
+                            Ok(Some(msg)) => {
+                                let json = serde_json::to_string(&msg)?;
+                                METRICS.bytes_sent_master.fetch_add(json.len(), Ordering::Relaxed);
+                                write.send(Message::Text(json.into())).await?;
+                                flushed += 1;
+                            }
+                            Ok(None) => break,
+                            Err(_) => {
+                                tracing::warn!(
+                                    "Master drain timed out – {} messages dropped",
+                                    rx.len()
+                                );
+                                break;
+                            }
+                        }
+                    }
+
+                    if debug {
+                        tracing::info!("Master drained {} messages on shutdown", flushed);
+                    }
+
+                    let _ = write.send(Message::Close(None)).await;
+                    return Ok(());
+                }
             }
         }
     }
@@ -222,6 +315,25 @@ impl MasterSender {
             Err(e) => Err(anyhow::anyhow!("Send error: {}", e)),
         }
     }
+
+    /// Number of messages enqueued but not yet written to the socket.
+    pub async fn queue_depth(&self) -> usize {
+        let tx = self.queue.lock().await;
+        tx.max_capacity() - tx.capacity()
+    }
+
+    /// Requests a graceful shutdown and waits until it completed.
+    ///
+    /// Behavior:
+    /// - New messages are rejected from now on
+    /// - Queued messages are flushed (bounded by `DRAIN_TIMEOUT`)
+    /// - The connection is closed and not re-established
+    pub async fn shutdown(&self) {
+        let _ = self.shutdown.send(true);
+
+        let mut stopped = self.stopped.clone();
+        let _ = stopped.wait_for(|s| *s).await;
+    }
 }
 
 /// ============================================================
@@ -241,6 +353,170 @@ pub struct MasterPool {
     senders: Vec<MasterSender>,
     counter: AtomicUsize,
     demo: bool,
+    envelope: Option<Arc<Envelope>>,
+    dead_letter: Option<Arc<DeadLetter>>,
+
+    /// Route by source connection (`sticky_routing`)
+    sticky: bool,
+}
+
+/// Envelope template plus the collector metadata it may reference.
+struct Envelope {
+    template: Value,
+    collector_id: Value,
+    shards: Option<u32>,
+}
+
+/// Append-only NDJSON file for messages no master connection accepted.
+///
+/// Bounded by `dead_letter_max_bytes`. Once full, the file is either
+/// rotated into a gzipped segment (`dead_letter_rotate_segments`) or
+/// further messages are only counted (`dead_letter_dropped`).
+/// Written synchronously: it is only hit when all master connections
+/// are failing.
+struct DeadLetter {
+    path: PathBuf,
+    file: std::sync::Mutex<std::fs::File>,
+    size: AtomicU64,
+    max_bytes: u64,
+
+    /// Gzipped segments to keep; None: no rotation
+    rotate_segments: Option<usize>,
+
+    /// Rotations so far; keeps same-millisecond segment names apart
+    rotations: AtomicU64,
+}
+
+impl DeadLetter {
+    fn open(path: &str, max_bytes: u64, rotate_segments: Option<usize>) -> Result<Self> {
+        let file = open_append(Path::new(path))
+            .map_err(|e| anyhow::anyhow!("dead letter file {}: {}", path, e))?;
+
+        Ok(Self {
+            path: PathBuf::from(path),
+            size: AtomicU64::new(file.metadata()?.len()),
+            file: std::sync::Mutex::new(file),
+            max_bytes,
+            rotate_segments,
+            rotations: AtomicU64::new(0),
+        })
+    }
+
+    fn write(&self, msg: &Value) {
+        let mut line = msg.to_string();
+        line.push('\n');
+
+        let len = line.len() as u64;
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+
+        if self.size.load(Ordering::Relaxed) + len > self.max_bytes {
+            let rotated = match self.rotate_segments {
+                Some(keep) => self.rotate(&mut file, keep),
+                None => false,
+            };
+
+            if !rotated {
+                METRICS.dead_letter_dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+
+        match file.write_all(line.as_bytes()) {
+            Ok(()) => {
+                self.size.fetch_add(len, Ordering::Relaxed);
+                METRICS.dead_lettered.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                tracing::error!("Dead letter write failed: {}", e);
+                METRICS.dead_letter_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Moves the full file aside as `<path>.<ms>.<seq>` and starts a new
+    /// one. The segment is gzipped in the background.
+    ///
+    /// Returns false if the file could not be rotated.
+    fn rotate(&self, file: &mut std::fs::File, keep: usize) -> bool {
+        let seq = self.rotations.fetch_add(1, Ordering::Relaxed);
+        let segment = PathBuf::from(format!("{}.{}.{:06}", self.path.display(), util::now_ms(), seq));
+
+        let reopened = file.flush()
+            .and_then(|_| std::fs::rename(&self.path, &segment))
+            .and_then(|_| open_append(&self.path));
+
+        match reopened {
+            Ok(new_file) => {
+                *file = new_file;
+                self.size.store(0, Ordering::Relaxed);
+
+                let path = self.path.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = gzip_segment(&segment) {
+                        tracing::error!("Dead letter gzip of {} failed: {}", segment.display(), e);
+                    }
+                    prune_segments(&path, keep);
+                });
+
+                true
+            }
+            Err(e) => {
+                tracing::error!("Dead letter rotation failed: {}", e);
+                false
+            }
+        }
+    }
+}
+
+fn open_append(path: &Path) -> std::io::Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+}
+
+/// Compresses `segment` into `segment.gz` and removes the original.
+fn gzip_segment(segment: &Path) -> std::io::Result<()> {
+    let gz_path = PathBuf::from(format!("{}.gz", segment.display()));
+
+    let mut input = std::fs::File::open(segment)?;
+    let mut encoder = GzEncoder::new(std::fs::File::create(&gz_path)?, Compression::default());
+
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+
+    std::fs::remove_file(segment)
+}
+
+/// Deletes the oldest `<path>.<ms>.<seq>.gz` segments beyond `keep`.
+fn prune_segments(path: &Path, keep: usize) {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return;
+    };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let prefix = format!("{}.", name.to_string_lossy());
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut segments: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .map(|n| n.to_string_lossy())
+                .is_some_and(|n| n.starts_with(&prefix) && n.ends_with(".gz"))
+        })
+        .collect();
+
+    // `<ms>` and the padded `<seq>` have fixed widths, so names sort by age
+    segments.sort();
+
+    let excess = segments.len().saturating_sub(keep);
+    for old in &segments[..excess] {
+        let _ = std::fs::remove_file(old);
+    }
 }
 
 impl MasterPool {
@@ -248,65 +524,161 @@ impl MasterPool {
     /// Creates a pool of master connections.
     ///
     /// LOGIN FORMAT:
-    /// - key=<API_KEY>&role=collector
+    /// - key=<API_KEY>&role=<ROLE>&client_id=<CLIENT_ID>
+    /// - ROLE defaults to "collector"
     ///
     /// DEMO MODE:
     /// - No network connections
     /// - Messages are printed to stdout
-    pub async fn new(
-        master_url: String,
-        login_msg: String,
-        debug: bool,
-        count: usize,
-        demo: bool,
-    ) -> Self {
+    ///
+    /// ENVELOPE:
+    /// - Optional template applied to every message (see `wrap_envelope`)
+    ///
+    /// TLS PINNING:
+    /// - If `pinned_cert_sha256` is set, only that certificate is accepted
+    ///
+    /// SNI:
+    /// - `network.sni_override` replaces the SNI host name
+    ///
+    /// DEAD LETTER:
+    /// - If `dead_letter_path` is set, messages failing all send
+    ///   attempts are appended there as NDJSON
+    ///
+    /// ERRORS:
+    /// - Invalid pinning fingerprint
+    /// - Dead-letter file cannot be opened
+    pub async fn new(cfg: &MasterConfig, debug: bool) -> Result<Self> {
+        let demo = cfg.demo.unwrap_or(false);
+
         if demo {
-            eprintln!("MasterPool running in DEMO mode");
+            tracing::info!("MasterPool running in DEMO mode");
         }
 
-        let mut senders = Vec::with_capacity(count);
+        let mut tls = net::TlsSettings::from_network(cfg.network.as_ref());
+        tls.connector = cfg.pinned_cert_sha256
+            .as_deref()
+            .map(net::pinned_cert_connector)
+            .transpose()?;
+
+        let dead_letter = cfg.dead_letter_path
+            .as_deref()
+            .map(|path| {
+                DeadLetter::open(
+                    path,
+                    cfg.dead_letter_max_bytes.unwrap_or(DEFAULT_DEAD_LETTER_MAX_BYTES),
+                    cfg.dead_letter_rotate_segments,
+                )
+            })
+            .transpose()?
+            .map(Arc::new);
+
+        let mut senders = Vec::with_capacity(cfg.connections);
 
         if !demo {
-            for _ in 0..count {
-                let login = format!("key={}&role=collector", login_msg);
+            for _ in 0..cfg.connections {
                 let sender = MasterSender::connect_loop(
-                    master_url.clone(),
-                    login,
+                    cfg.url.clone(),
+                    login_message(cfg),
                     debug,
+                    tls.clone(),
                 ).await;
                 senders.push(sender);
             }
         }
 
-        Self {
+        Ok(Self {
             senders,
             counter: AtomicUsize::new(0),
             demo,
-        }
+            envelope: cfg.envelope_template.clone().map(|template| {
+                Arc::new(Envelope {
+                    template,
+                    collector_id: Value::String(cfg.client_id()),
+                    shards: cfg.shards,
+                })
+            }),
+            dead_letter,
+            sticky: cfg.sticky_routing.unwrap_or(false),
+        })
     }
 
     /// Sends a message using a randomly selected sender.
     ///
+    /// With `sticky_routing`, the first attempt uses the sender pinned
+    /// to the connection the message came from (`meta.conn_id`).
+    ///
     /// Behavior:
     /// - Up to 3 retry attempts
     /// - Backoff between retries
     /// - Fails gracefully if all senders are unavailable
-    pub async fn send(&self, msg: Value) -> Result<()> {
+    ///   (the message goes to the dead-letter file, if configured)
+    pub async fn send(&self, msg: Value, meta: SendMeta) -> Result<()> {
+        let msg = match &self.envelope {
+            Some(envelope) => {
+                let shard = match (envelope.shards, msg["symbol"].as_str()) {
+                    (Some(shards), Some(symbol)) => Value::from(shard_of(symbol, shards)),
+                    _ => Value::Null,
+                };
+
+                wrap_envelope(&envelope.template, &msg, &envelope.collector_id, meta.received_at, &shard)
+            }
+            None => msg,
+        };
+
         if self.demo {
             println!("DEMO → {}", serde_json::to_string(&msg)?);
             return Ok(());
         }
 
-        for _ in 0..3 {
-            let idx = random_range(0..self.senders.len());
+        for attempt in 0..3 {
+            let idx = match self.sticky_index(meta.conn_id) {
+                Some(idx) if attempt == 0 => idx,
+                _ => random_range(0..self.senders.len()),
+            };
             if self.senders[idx].send(msg.clone()).await.is_ok() {
                 return Ok(());
             }
             sleep(Duration::from_millis(100)).await;
         }
 
+        if let Some(dead_letter) = &self.dead_letter {
+            dead_letter.write(&msg);
+        }
+
         Err(anyhow::anyhow!("All master connections busy"))
     }
+
+    /// Sender pinned to connection `conn_id`, None without
+    /// `sticky_routing`.
+    ///
+    /// Messages without a connection (REST pollers) and demo mode
+    /// have nothing to pin. Consecutive connection ids spread evenly
+    /// over the senders.
+    fn sticky_index(&self, conn_id: Option<usize>) -> Option<usize> {
+        if !self.sticky || self.senders.is_empty() {
+            return None;
+        }
+
+        Some(conn_id? % self.senders.len())
+    }
+
+    /// Queue depth of every master connection, in connection order.
+    ///
+    /// A connection with a growing depth is the bottleneck.
+    pub async fn queue_depths(&self) -> Vec<usize> {
+        futures_util::future::join_all(
+            self.senders.iter().map(|s| s.queue_depth())
+        ).await
+    }
+
+    /// Gracefully shuts down all master connections.
+    ///
+    /// Each sender drains its queue in parallel before closing.
+    pub async fn shutdown(&self) {
+        futures_util::future::join_all(
+            self.senders.iter().map(|s| s.shutdown())
+        ).await;
+    }
 }
 
 impl Clone for MasterPool {
@@ -315,6 +687,354 @@ impl Clone for MasterPool {
             senders: self.senders.clone(),
             counter: AtomicUsize::new(self.counter.load(Ordering::Relaxed)),
             demo: self.demo,
+            envelope: self.envelope.clone(),
+            dead_letter: self.dead_letter.clone(),
+            sticky: self.sticky,
+        }
+    }
+}
+
+#[cfg(test)]
+impl MasterPool {
+    /// Sticky pool of connected senders without sockets; the
+    /// receivers stand in for the master.
+    pub(crate) fn detached(connections: usize) -> (Self, Vec<mpsc::Receiver<Value>>) {
+        let (senders, receivers) = (0..connections)
+            .map(|_| {
+                let (tx, rx) = mpsc::channel(16);
+                let connected = Arc::new(OnceCell::new());
+                connected.set(()).unwrap();
+                let (shutdown, _) = watch::channel(false);
+                let (_, stopped) = watch::channel(true);
+
+                let sender = MasterSender {
+                    queue: Arc::new(Mutex::new(tx)),
+                    connected,
+                    shutdown: Arc::new(shutdown),
+                    stopped,
+                };
+                (sender, rx)
+            })
+            .unzip();
+
+        let pool = Self {
+            senders,
+            counter: AtomicUsize::new(0),
+            demo: false,
+            envelope: None,
+            dead_letter: None,
+            sticky: true,
+        };
+        (pool, receivers)
+    }
+}
+
+/// Builds the login message sent first on every master connection.
+///
+/// - `query`: `key=…&role=…&client_id=…`, every value URL-encoded
+///   (a client id containing `&` or `=` stays one value)
+/// - `json`:  `{"key":…,"role":…,"client_id":…}`
+fn login_message(cfg: &MasterConfig) -> String {
+    let client_id = cfg.client_id();
+
+    match cfg.login_format {
+        LoginFormat::Query => form_urlencoded::Serializer::new(String::new())
+            .append_pair("key", &cfg.key)
+            .append_pair("role", cfg.role())
+            .append_pair("client_id", &client_id)
+            .finish(),
+
+        LoginFormat::Json => serde_json::json!({
+            "key": cfg.key,
+            "role": cfg.role(),
+            "client_id": client_id,
+        })
+        .to_string(),
+    }
+}
+
+/// Placeholder replaced by the message inside an envelope template
+const ENVELOPE_PLACEHOLDER: &str = "$message";
+
+/// Placeholder replaced by the collector instance id (`client_id`)
+const COLLECTOR_ID_PLACEHOLDER: &str = "$collector_id";
+
+/// Placeholder replaced by the receive time in milliseconds
+const RECEIVED_AT_PLACEHOLDER: &str = "$received_at";
+
+/// Placeholder replaced by the shard hint of the message symbol
+const SHARD_PLACEHOLDER: &str = "$shard";
+
+/// Stable shard of a symbol: FNV-1a hash modulo `shards`.
+///
+/// Not `DefaultHasher`: the value must match across collectors and
+/// Rust versions.
+fn shard_of(symbol: &str, shards: u32) -> u32 {
+    let hash = symbol.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+
+    (hash % u64::from(shards.max(1))) as u32
+}
+
+/// Builds an envelope around `msg` from the configured template.
+///
+/// Every string (at any nesting level) equal to
+/// - `$message`      is replaced by the message
+/// - `$collector_id` is replaced by the collector id
+/// - `$received_at`  is replaced by `received_at` (ms, number)
+/// - `$shard`        is replaced by `shard` (number, null without `shards`)
+///
+/// Everything else is copied as-is.
+///
+/// Example:
+/// - template: {"collector_id":"$collector_id","received_at":"$received_at","message":"$message"}
+/// - result:   {"collector_id":"fra-1","received_at":1700000000000,"message":{"type":"trade",...}}
+fn wrap_envelope(
+    template: &Value,
+    msg: &Value,
+    collector_id: &Value,
+    received_at: i64,
+    shard: &Value,
+) -> Value {
+    match template {
+        Value::String(s) if s == ENVELOPE_PLACEHOLDER => msg.clone(),
+        Value::String(s) if s == COLLECTOR_ID_PLACEHOLDER => collector_id.clone(),
+        Value::String(s) if s == RECEIVED_AT_PLACEHOLDER => Value::from(received_at),
+        Value::String(s) if s == SHARD_PLACEHOLDER => shard.clone(),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), wrap_envelope(v, msg, collector_id, received_at, shard)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|v| wrap_envelope(v, msg, collector_id, received_at, shard))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Connected sender without a socket; messages land in the receiver.
+    fn sticky_pool(connections: usize) -> (MasterPool, Vec<mpsc::Receiver<Value>>) {
+        MasterPool::detached(connections)
+    }
+
+    fn master_config(extra: Value) -> MasterConfig {
+        let mut cfg = serde_json::json!({ "url": "wss://master.example", "connections": 1, "key": "k3y" });
+        cfg.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(cfg).unwrap()
+    }
+
+    #[test]
+    fn login_carries_an_encoded_client_id() {
+        let cfg = master_config(serde_json::json!({ "client_id": "fra-1&role=admin" }));
+
+        assert_eq!(login_message(&cfg), "key=k3y&role=collector&client_id=fra-1%26role%3Dadmin");
+    }
+
+    #[test]
+    fn login_sends_the_configured_role() {
+        let cfg = master_config(serde_json::json!({ "client_id": "fra-1", "role": "backfill" }));
+
+        assert_eq!(login_message(&cfg), "key=k3y&role=backfill&client_id=fra-1");
+    }
+
+    #[test]
+    fn json_login_carries_the_client_id() {
+        let cfg = master_config(serde_json::json!({ "client_id": "fra-1", "login_format": "json" }));
+        let login: Value = serde_json::from_str(&login_message(&cfg)).unwrap();
+
+        assert_eq!(login, serde_json::json!({ "key": "k3y", "role": "collector", "client_id": "fra-1" }));
+    }
+
+    #[tokio::test]
+    async fn sticky_routing_pins_each_connection_to_one_sender() {
+        let (pool, mut receivers) = sticky_pool(3);
+
+        for seq in 0..5 {
+            for conn_id in [4, 5] {
+                let msg = serde_json::json!({ "conn": conn_id, "seq": seq });
+                pool.send(msg, SendMeta::live(conn_id)).await.unwrap();
+            }
+        }
+
+        let received: Vec<Vec<Value>> = receivers
+            .iter_mut()
+            .map(|rx| std::iter::from_fn(|| rx.try_recv().ok()).collect())
+            .collect();
+
+        // conn 4 → sender 1, conn 5 → sender 2, each in order
+        assert!(received[0].is_empty());
+
+        for (conn_id, idx) in [(4, 1), (5, 2)] {
+            let seqs: Vec<_> = received[idx]
+                .iter()
+                .map(|m| {
+                    assert_eq!(m["conn"], conn_id);
+                    m["seq"].as_i64().unwrap()
+                })
+                .collect();
+            assert_eq!(seqs, [0, 1, 2, 3, 4]);
         }
     }
+
+    #[tokio::test]
+    async fn envelope_carries_collector_metadata_around_the_message() {
+        let (pool, mut receivers) = sticky_pool(1);
+        let pool = MasterPool {
+            envelope: Some(Arc::new(Envelope {
+                template: serde_json::json!({
+                    "collector_id": "$collector_id",
+                    "received_at": "$received_at",
+                    "shard": "$shard",
+                    "message": "$message",
+                }),
+                collector_id: Value::from("fra-1"),
+                shards: Some(4),
+            })),
+            ..pool
+        };
+
+        let msg: crate::schema::MarketMessage = serde_json::from_value(serde_json::json!({
+            "type": "trade", "exchange": "binance", "symbol": "BTC/USDT",
+            "timestamp": 1_700_000_000_000_i64, "price": "42000.1", "amount": "0.5", "side": "buy",
+        }))
+        .unwrap();
+        let meta = SendMeta { received_at: 1_700_000_000_123, ..SendMeta::live(1) };
+
+        crate::output::OutputSink::send(&pool, &msg, meta).await.unwrap();
+
+        assert_eq!(
+            receivers[0].try_recv().unwrap(),
+            serde_json::json!({
+                "collector_id": "fra-1",
+                "received_at": 1_700_000_000_123_i64,
+                "shard": shard_of("BTC/USDT", 4),
+                "message": {
+                    "type": "trade", "exchange": "binance", "symbol": "BTC/USDT",
+                    "timestamp": 1_700_000_000_000_i64, "price": "42000.1", "amount": "0.5",
+                    "side": "buy",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn envelope_placeholders_are_replaced_at_any_depth() {
+        let template = serde_json::json!({
+            "v": 2,
+            "meta": { "from": "$collector_id", "at": ["$received_at", "$shard"], "tag": "$other" },
+            "payload": ["$message"],
+            "note": "message: $message",
+        });
+        let msg = serde_json::json!({ "type": "trade" });
+
+        let wrapped = wrap_envelope(&template, &msg, &Value::from("fra-1"), 7, &Value::Null);
+
+        // Only whole-string placeholders are replaced
+        assert_eq!(
+            wrapped,
+            serde_json::json!({
+                "v": 2,
+                "meta": { "from": "fra-1", "at": [7, null], "tag": "$other" },
+                "payload": [{ "type": "trade" }],
+                "note": "message: $message",
+            })
+        );
+    }
+
+    #[test]
+    fn shards_are_a_stable_fnv_hash_of_the_symbol() {
+        // FNV-1a 64: "" is the offset basis, BTC/USDT 0x39d686218c28e341
+        assert_eq!(shard_of("", 16), (0xcbf2_9ce4_8422_2325_u64 % 16) as u32);
+        assert_eq!(shard_of("BTC/USDT", 16), 1);
+        assert_eq!(shard_of("BTC/USDT", 7), 5);
+        assert_eq!(shard_of("ETH/USDT", 16), 5);
+
+        // Zero shards behaves like one instead of dividing by zero
+        assert_eq!(shard_of("BTC/USDT", 0), 0);
+        assert_eq!(shard_of("BTC/USDT", 1), 0);
+    }
+
+    #[tokio::test]
+    async fn messages_without_connection_are_not_pinned() {
+        let (pool, _receivers) = sticky_pool(3);
+
+        assert_eq!(pool.sticky_index(Some(5)), Some(2));
+        assert_eq!(pool.sticky_index(None), None);
+
+        let unpinned = MasterPool { sticky: false, ..pool };
+        assert_eq!(unpinned.sticky_index(Some(5)), None);
+    }
+
+    #[tokio::test]
+    async fn unsendable_messages_are_dead_lettered() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dead.ndjson");
+
+        // Every sender's connection is gone
+        let (pool, receivers) = sticky_pool(2);
+        drop(receivers);
+        let pool = MasterPool {
+            dead_letter: Some(Arc::new(DeadLetter::open(path.to_str().unwrap(), 1 << 20, None).unwrap())),
+            ..pool
+        };
+
+        let msg = serde_json::json!({ "type": "trade", "symbol": "BTC/USDT", "price": "1" });
+        assert!(pool.send(msg.clone(), SendMeta::rest(None)).await.is_err());
+
+        let lines = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(lines, format!("{msg}\n"));
+    }
+
+    #[test]
+    fn full_dead_letter_files_rotate_into_gzipped_segments() {
+        use std::io::Read;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dead.ndjson");
+
+        let lines: Vec<Value> = (0..5)
+            .map(|i| serde_json::json!({ "type": "trade", "seq": i }))
+            .collect();
+        let line_len = lines[0].to_string().len() as u64 + 1;
+
+        // Two lines per file; both rotations land in the same millisecond
+        let dead_letter = DeadLetter::open(path.to_str().unwrap(), 2 * line_len, Some(5)).unwrap();
+        util::set_test_clock(Some(1_700_000_000_000));
+        for line in &lines {
+            dead_letter.write(line);
+        }
+        util::set_test_clock(None);
+
+        let segment = |seq: u32| PathBuf::from(format!("{}.1700000000000.{:06}", path.display(), seq));
+        let gunzip = |seq: u32| {
+            let gz = PathBuf::from(format!("{}.gz", segment(seq).display()));
+            let start = std::time::Instant::now();
+            while segment(seq).exists() || !gz.exists() {
+                assert!(start.elapsed() < std::time::Duration::from_secs(5), "segment {seq} not gzipped");
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+
+            let mut text = String::new();
+            flate2::read::GzDecoder::new(std::fs::File::open(gz).unwrap())
+                .read_to_string(&mut text)
+                .unwrap();
+            text
+        };
+
+        let expected = |range: std::ops::Range<usize>| {
+            lines[range].iter().map(|l| format!("{l}\n")).collect::<String>()
+        };
+        assert_eq!(gunzip(0), expected(0..2));
+        assert_eq!(gunzip(1), expected(2..4));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), expected(4..5));
+    }
 }
@@ -1,7 +1,12 @@
-use std::sync::atomic::{AtomicUsize};
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write as _};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
 
 /// Global runtime metrics for the collector.
 ///
@@ -22,24 +27,531 @@ pub struct RuntimeMetrics {
 
     // WebSocket level
     pub ws_connections_active: AtomicUsize,
+    pub ws_never_connected: AtomicUsize,
 
     // Markets
     pub trade_pairs_active: AtomicUsize,
     pub orderbook_pairs_active: AtomicUsize,
+    pub funding_pairs_active: AtomicUsize,
 
     // Throughput
     pub trades_received: AtomicUsize,
     pub trades_forwarded: AtomicUsize,
+    pub trades_backfilled: AtomicUsize,
+    pub books_deduped: AtomicUsize,
+    pub rate_capped: AtomicUsize,
 
+    // Bandwidth (raw exchange frames / serialized master messages)
+    pub bytes_received: AtomicUsize,
+    pub bytes_sent_master: AtomicUsize,
+
+    pub control_messages: AtomicUsize,
+    pub ignored_messages: AtomicUsize,
     pub parse_errors: AtomicUsize,
+    pub missing_fields: AtomicUsize,
     pub send_errors: AtomicUsize,
+
+    // Reconnects (lost established connections, by cause;
+    // recycles are `ws_recycled`, connect failures are not counted
+    // in `ws_reconnects`)
     pub ws_reconnects: AtomicUsize,
+    pub reconnects_clean_close: AtomicUsize,
+    pub reconnects_error: AtomicUsize,
+    pub reconnects_connect_failed: AtomicUsize,
+    pub reconnects_idle_timeout: AtomicUsize,
+    pub subscribe_timeouts: AtomicUsize,
+    pub rate_limit_backoffs: AtomicUsize,
+    pub ws_recycled: AtomicUsize,
+    pub ws_protocol_errors: AtomicUsize,
+    pub ws_io_errors: AtomicUsize,
+
     pub dropped_messages: AtomicUsize,
 
     pub subscriptions_sent: AtomicUsize,
     pub subscription_errors: AtomicUsize,
+    pub subscription_limit_errors: AtomicUsize,
+
+    // Additional output sinks (e.g. UNIX socket clients)
+    pub output_dropped: AtomicUsize,
+
+    // Master dead-letter file (written / dropped when full)
+    pub dead_lettered: AtomicUsize,
+    pub dead_letter_dropped: AtomicUsize,
+
+    // Dropped by `validate_output`
+    pub invalid_messages: AtomicUsize,
+    pub crossed_books: AtomicUsize,
+
+    // Supervision
+    pub task_panics: AtomicUsize,
+
+    // Local clock vs. exchange timestamps
+    pub clock_skew: ClockSkew,
+
+    // Latency
+    /// Frame receipt → `parse_message` done
+    pub parse_latency: LatencyHistogram,
+    /// Parse done → accepted by the output (master enqueue)
+    pub forward_latency: LatencyHistogram,
+}
+
+/// Point-in-time copy of all `RuntimeMetrics` (see `snapshot`).
+///
+/// Serializes to the flat JSON object served by the admin
+/// `/metrics` and `/metrics.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSnapshot {
+    // High-level
+    pub exchanges_active: usize,
+
+    // WebSocket level
+    pub ws_connections_active: usize,
+    pub ws_never_connected: usize,
+
+    // Markets
+    pub trade_pairs_active: usize,
+    pub orderbook_pairs_active: usize,
+    pub funding_pairs_active: usize,
+
+    // Throughput
+    pub trades_received: usize,
+    pub trades_forwarded: usize,
+    pub trades_backfilled: usize,
+    pub books_deduped: usize,
+    pub rate_capped: usize,
+
+    // Bandwidth (raw exchange frames / serialized master messages)
+    pub bytes_received: usize,
+    pub bytes_sent_master: usize,
+
+    pub control_messages: usize,
+    pub ignored_messages: usize,
+    pub parse_errors: usize,
+    pub missing_fields: usize,
+    pub send_errors: usize,
+
+    // Reconnects (lost established connections, by cause;
+    // recycles are `ws_recycled`, connect failures are not counted
+    // in `ws_reconnects`)
+    pub ws_reconnects: usize,
+    pub reconnects_clean_close: usize,
+    pub reconnects_error: usize,
+    pub reconnects_connect_failed: usize,
+    pub reconnects_idle_timeout: usize,
+    pub subscribe_timeouts: usize,
+    pub rate_limit_backoffs: usize,
+    pub ws_recycled: usize,
+    pub ws_protocol_errors: usize,
+    pub ws_io_errors: usize,
+
+    pub dropped_messages: usize,
+
+    pub subscriptions_sent: usize,
+    pub subscription_errors: usize,
+    pub subscription_limit_errors: usize,
+
+    // Additional output sinks (e.g. UNIX socket clients)
+    pub output_dropped: usize,
+
+    // Master dead-letter file (written / dropped when full)
+    pub dead_lettered: usize,
+    pub dead_letter_dropped: usize,
+
+    // Dropped by `validate_output`
+    pub invalid_messages: usize,
+    pub crossed_books: usize,
+
+    // Supervision
+    pub task_panics: usize,
+
+    // Local clock vs. exchange timestamps
+    #[serde(rename = "collector_clock_skew_ms")]
+    pub clock_skew_ms: BTreeMap<String, i64>,
+
+    // Latency
+    /// Frame receipt → `parse_message` done
+    #[serde(rename = "parse_latency_seconds")]
+    pub parse_latency: Value,
+    /// Parse done → accepted by the output (master enqueue)
+    #[serde(rename = "forward_latency_seconds")]
+    pub forward_latency: Value,
+}
+
+/// One-line summary of the key counters (periodic `[METRICS]` log).
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ex={} ws={} ws_never={} tp={} ob={} recv={} sent={} rx_bytes={} tx_bytes={} dropped={} ctrl={} ignored={} parse_err={} send_err={} reconnects={} sub_send={} sub_send_err={} sub_limit={} out_drop={} panics={}",
+            self.exchanges_active,
+            self.ws_connections_active,
+            self.ws_never_connected,
+            self.trade_pairs_active,
+            self.orderbook_pairs_active,
+            self.trades_received,
+            self.trades_forwarded,
+            self.bytes_received,
+            self.bytes_sent_master,
+            self.dropped_messages,
+            self.control_messages,
+            self.ignored_messages,
+            self.parse_errors,
+            self.send_errors,
+            self.ws_reconnects,
+            self.subscriptions_sent,
+            self.subscription_errors,
+            self.subscription_limit_errors,
+            self.output_dropped,
+            self.task_panics,
+        )
+    }
+}
+
+impl RuntimeMetrics {
+    /// Loads all counters into a `MetricsSnapshot` (admin `/metrics`).
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let get = |m: &AtomicUsize| m.load(Ordering::Relaxed);
+
+        MetricsSnapshot {
+            exchanges_active: get(&self.exchanges_active),
+            ws_connections_active: get(&self.ws_connections_active),
+            ws_never_connected: get(&self.ws_never_connected),
+            trade_pairs_active: get(&self.trade_pairs_active),
+            orderbook_pairs_active: get(&self.orderbook_pairs_active),
+            funding_pairs_active: get(&self.funding_pairs_active),
+            trades_received: get(&self.trades_received),
+            trades_forwarded: get(&self.trades_forwarded),
+            trades_backfilled: get(&self.trades_backfilled),
+            books_deduped: get(&self.books_deduped),
+            rate_capped: get(&self.rate_capped),
+            bytes_received: get(&self.bytes_received),
+            bytes_sent_master: get(&self.bytes_sent_master),
+            control_messages: get(&self.control_messages),
+            ignored_messages: get(&self.ignored_messages),
+            parse_errors: get(&self.parse_errors),
+            missing_fields: get(&self.missing_fields),
+            send_errors: get(&self.send_errors),
+            ws_reconnects: get(&self.ws_reconnects),
+            reconnects_clean_close: get(&self.reconnects_clean_close),
+            reconnects_error: get(&self.reconnects_error),
+            reconnects_connect_failed: get(&self.reconnects_connect_failed),
+            reconnects_idle_timeout: get(&self.reconnects_idle_timeout),
+            subscribe_timeouts: get(&self.subscribe_timeouts),
+            rate_limit_backoffs: get(&self.rate_limit_backoffs),
+            ws_recycled: get(&self.ws_recycled),
+            ws_protocol_errors: get(&self.ws_protocol_errors),
+            ws_io_errors: get(&self.ws_io_errors),
+            dropped_messages: get(&self.dropped_messages),
+            subscriptions_sent: get(&self.subscriptions_sent),
+            subscription_errors: get(&self.subscription_errors),
+            subscription_limit_errors: get(&self.subscription_limit_errors),
+            output_dropped: get(&self.output_dropped),
+            dead_lettered: get(&self.dead_lettered),
+            dead_letter_dropped: get(&self.dead_letter_dropped),
+            invalid_messages: get(&self.invalid_messages),
+            crossed_books: get(&self.crossed_books),
+            task_panics: get(&self.task_panics),
+            clock_skew_ms: self.clock_skew.snapshot(),
+            parse_latency: self.parse_latency.snapshot(),
+            forward_latency: self.forward_latency.snapshot(),
+        }
+    }
+
+    /// Renders all metrics in the Prometheus text format.
+    ///
+    /// Counters become `collector_<name>`, the clock skew a gauge
+    /// labeled by exchange, the latencies histograms.
+    pub fn prometheus(&self) -> String {
+        let mut out = String::new();
+
+        if let Ok(Value::Object(metrics)) = serde_json::to_value(self.snapshot()) {
+            for (name, value) in metrics {
+                if let Some(v) = value.as_u64() {
+                    let _ = writeln!(out, "collector_{name} {v}");
+                }
+            }
+        }
+
+        let _ = writeln!(out, "# TYPE collector_clock_skew_ms gauge");
+        for (exchange, ms) in self.clock_skew.snapshot() {
+            let _ = writeln!(out, "collector_clock_skew_ms{{exchange=\"{exchange}\"}} {ms}");
+        }
+
+        self.parse_latency.prometheus("collector_parse_latency_seconds", &mut out);
+        self.forward_latency.prometheus("collector_forward_latency_seconds", &mut out);
+
+        out
+    }
+}
+
+/// Upper bucket bounds in microseconds (10µs … 100ms)
+const LATENCY_BOUNDS_US: [u64; 12] = [
+    10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 100_000,
+];
+
+/// Lock-free latency histogram with fixed buckets.
+///
+/// Buckets are stored non-cumulative and summed up when rendered;
+/// the last bucket is `+Inf`.
+#[derive(Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; LATENCY_BOUNDS_US.len() + 1],
+    sum_us: AtomicU64,
+    count: AtomicU64,
+}
+
+impl LatencyHistogram {
+    /// Adds one sample.
+    pub fn observe(&self, latency: Duration) {
+        let us = latency.as_micros() as u64;
+        let bucket = LATENCY_BOUNDS_US
+            .iter()
+            .position(|&bound| us <= bound)
+            .unwrap_or(LATENCY_BOUNDS_US.len());
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(us, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Cumulative counts as `(le, count)`, ending with `+Inf`.
+    fn cumulative(&self) -> Vec<(String, u64)> {
+        let mut total = 0;
+
+        self.buckets
+            .iter()
+            .enumerate()
+            .map(|(i, bucket)| {
+                total += bucket.load(Ordering::Relaxed);
+                let le = LATENCY_BOUNDS_US
+                    .get(i)
+                    .map(|&us| (us as f64 / 1e6).to_string())
+                    .unwrap_or_else(|| "+Inf".to_string());
+                (le, total)
+            })
+            .collect()
+    }
+
+    fn sum_seconds(&self) -> f64 {
+        self.sum_us.load(Ordering::Relaxed) as f64 / 1e6
+    }
+
+    /// Returns `{ buckets: { le: count }, sum, count }` (seconds).
+    pub fn snapshot(&self) -> Value {
+        json!({
+            "buckets": self
+                .cumulative()
+                .into_iter()
+                .map(|(le, count)| (le, json!(count)))
+                .collect::<serde_json::Map<_, _>>(),
+            "sum": self.sum_seconds(),
+            "count": self.count.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Appends the histogram in the Prometheus text format.
+    fn prometheus(&self, name: &str, out: &mut String) {
+        let _ = writeln!(out, "# TYPE {name} histogram");
+
+        for (le, count) in self.cumulative() {
+            let _ = writeln!(out, "{name}_bucket{{le=\"{le}\"}} {count}");
+        }
+
+        let _ = writeln!(out, "{name}_sum {}", self.sum_seconds());
+        let _ = writeln!(out, "{name}_count {}", self.count.load(Ordering::Relaxed));
+    }
+}
+
+/// Weight of a new sample in the rolling average
+const SKEW_ALPHA: f64 = 0.01;
+
+/// Samples before the average is trusted enough to warn
+const SKEW_MIN_SAMPLES: u64 = 100;
+
+/// Average below this: local clock is behind the exchange
+const SKEW_WARN_BEHIND_MS: f64 = -1_000.0;
+
+/// Average above this: local clock ahead, or data heavily delayed
+const SKEW_WARN_AHEAD_MS: f64 = 30_000.0;
+
+/// Minimum time between two warnings per exchange
+const SKEW_WARN_EVERY: Duration = Duration::from_secs(60);
+
+/// Rolling clock skew per exchange: `now_ms() - exchange timestamp`.
+///
+/// Positive values are mostly network latency; consistently
+/// negative values mean the local clock is behind the exchange.
+/// Served as `collector_clock_skew_ms` (per exchange).
+#[derive(Default)]
+pub struct ClockSkew {
+    exchanges: Mutex<HashMap<String, SkewState>>,
+}
+
+struct SkewState {
+    avg_ms: f64,
+    samples: u64,
+    last_warn: Option<Instant>,
+}
+
+impl ClockSkew {
+    /// Adds one sample to the exchange average.
+    ///
+    /// RETURNS:
+    /// - Some(average) if it is implausible and should be logged
+    ///   (at most once per `SKEW_WARN_EVERY` per exchange)
+    pub fn record(&self, exchange: &str, skew_ms: i64) -> Option<i64> {
+        let mut exchanges = self.exchanges.lock().unwrap_or_else(|e| e.into_inner());
+
+        let state = exchanges
+            .entry(exchange.to_string())
+            .or_insert(SkewState {
+                avg_ms: skew_ms as f64,
+                samples: 0,
+                last_warn: None,
+            });
+
+        state.avg_ms += SKEW_ALPHA * (skew_ms as f64 - state.avg_ms);
+        state.samples += 1;
+
+        let implausible = state.avg_ms < SKEW_WARN_BEHIND_MS || state.avg_ms > SKEW_WARN_AHEAD_MS;
+        let warned_recently = state.last_warn.is_some_and(|t| t.elapsed() < SKEW_WARN_EVERY);
+
+        if state.samples < SKEW_MIN_SAMPLES || !implausible || warned_recently {
+            return None;
+        }
+
+        state.last_warn = Some(Instant::now());
+        Some(state.avg_ms as i64)
+    }
+
+    /// Returns the current averages as `{ exchange: ms }`.
+    pub fn snapshot(&self) -> BTreeMap<String, i64> {
+        let exchanges = self.exchanges.lock().unwrap_or_else(|e| e.into_inner());
+
+        exchanges
+            .iter()
+            .map(|(name, state)| (name.clone(), state.avg_ms as i64))
+            .collect()
+    }
 }
 
 /// Global metrics registry (singleton)
 pub static METRICS: Lazy<Arc<RuntimeMetrics>> =
     Lazy::new(|| Arc::new(RuntimeMetrics::default()));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_skew_warns_only_past_the_thresholds() {
+        let skew = ClockSkew::default();
+
+        // Plain network latency never warns
+        assert!((0..500).all(|_| skew.record("latency", 150).is_none()));
+
+        // Local clock 5s behind: warns once enough samples are in
+        let warnings: Vec<_> = (0..SKEW_MIN_SAMPLES)
+            .filter_map(|_| skew.record("behind", -5_000))
+            .collect();
+        assert_eq!(warnings, [-5_000]);
+
+        // …then not again within `SKEW_WARN_EVERY`
+        assert!(skew.record("behind", -5_000).is_none());
+
+        // Just inside the bounds stays quiet
+        assert!((0..500).all(|_| skew.record("edge", -900).is_none()));
+        assert!((0..500).all(|_| skew.record("late", 29_000).is_none()));
+        assert!((0..500).any(|_| skew.record("ahead", 31_000).is_some()));
+
+        assert_eq!(skew.snapshot()["behind"], -5_000);
+    }
+
+    #[test]
+    fn snapshots_serialize_every_counter() {
+        let metrics = RuntimeMetrics::default();
+        let counters = [
+            ("exchanges_active", &metrics.exchanges_active),
+            ("ws_connections_active", &metrics.ws_connections_active),
+            ("ws_never_connected", &metrics.ws_never_connected),
+            ("trade_pairs_active", &metrics.trade_pairs_active),
+            ("orderbook_pairs_active", &metrics.orderbook_pairs_active),
+            ("funding_pairs_active", &metrics.funding_pairs_active),
+            ("trades_received", &metrics.trades_received),
+            ("trades_forwarded", &metrics.trades_forwarded),
+            ("trades_backfilled", &metrics.trades_backfilled),
+            ("books_deduped", &metrics.books_deduped),
+            ("rate_capped", &metrics.rate_capped),
+            ("bytes_received", &metrics.bytes_received),
+            ("bytes_sent_master", &metrics.bytes_sent_master),
+            ("control_messages", &metrics.control_messages),
+            ("ignored_messages", &metrics.ignored_messages),
+            ("parse_errors", &metrics.parse_errors),
+            ("missing_fields", &metrics.missing_fields),
+            ("send_errors", &metrics.send_errors),
+            ("ws_reconnects", &metrics.ws_reconnects),
+            ("reconnects_clean_close", &metrics.reconnects_clean_close),
+            ("reconnects_error", &metrics.reconnects_error),
+            ("reconnects_connect_failed", &metrics.reconnects_connect_failed),
+            ("reconnects_idle_timeout", &metrics.reconnects_idle_timeout),
+            ("subscribe_timeouts", &metrics.subscribe_timeouts),
+            ("rate_limit_backoffs", &metrics.rate_limit_backoffs),
+            ("ws_recycled", &metrics.ws_recycled),
+            ("ws_protocol_errors", &metrics.ws_protocol_errors),
+            ("ws_io_errors", &metrics.ws_io_errors),
+            ("dropped_messages", &metrics.dropped_messages),
+            ("subscriptions_sent", &metrics.subscriptions_sent),
+            ("subscription_errors", &metrics.subscription_errors),
+            ("subscription_limit_errors", &metrics.subscription_limit_errors),
+            ("output_dropped", &metrics.output_dropped),
+            ("dead_lettered", &metrics.dead_lettered),
+            ("dead_letter_dropped", &metrics.dead_letter_dropped),
+            ("invalid_messages", &metrics.invalid_messages),
+            ("crossed_books", &metrics.crossed_books),
+            ("task_panics", &metrics.task_panics),
+        ];
+
+        // A distinct value per counter catches fields loaded from the wrong atomic
+        for (i, (_, counter)) in counters.iter().enumerate() {
+            counter.store(i + 1, Ordering::Relaxed);
+        }
+        metrics.clock_skew.record("binance", 120);
+        metrics.parse_latency.observe(Duration::from_micros(20));
+
+        let Value::Object(json) = serde_json::to_value(metrics.snapshot()).unwrap() else {
+            panic!("expected an object");
+        };
+
+        for (i, (name, _)) in counters.iter().enumerate() {
+            assert_eq!(json[*name], i + 1, "{name}");
+        }
+        assert_eq!(json["collector_clock_skew_ms"], json!({ "binance": 120 }));
+        assert_eq!(json["parse_latency_seconds"]["count"], 1);
+        assert_eq!(json["forward_latency_seconds"]["count"], 0);
+
+        // Nothing serialized that is not checked above
+        assert_eq!(json.len(), counters.len() + 3);
+    }
+
+    #[test]
+    fn histograms_render_cumulative_buckets() {
+        let histogram = LatencyHistogram::default();
+        for us in [5, 30, 40, 200_000] {
+            histogram.observe(Duration::from_micros(us));
+        }
+
+        let mut out = String::new();
+        histogram.prometheus("collector_parse_latency_seconds", &mut out);
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines[0], "# TYPE collector_parse_latency_seconds histogram");
+        assert_eq!(lines[1], "collector_parse_latency_seconds_bucket{le=\"0.00001\"} 1");
+        assert_eq!(lines[2], "collector_parse_latency_seconds_bucket{le=\"0.000025\"} 1");
+        assert_eq!(lines[3], "collector_parse_latency_seconds_bucket{le=\"0.00005\"} 3");
+        assert_eq!(lines[12], "collector_parse_latency_seconds_bucket{le=\"0.1\"} 3");
+        assert_eq!(lines[13], "collector_parse_latency_seconds_bucket{le=\"+Inf\"} 4");
+        assert_eq!(lines[14], "collector_parse_latency_seconds_sum 0.200075");
+        assert_eq!(lines[15], "collector_parse_latency_seconds_count 4");
+        assert_eq!(lines.len(), 16);
+    }
+}
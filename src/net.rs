@@ -0,0 +1,512 @@
+//! Network / TLS helpers for outgoing WebSocket connections.
+//!
+//! This module provides:
+//! - The shared HTTP client for REST calls (tokens, symbol lists)
+//! - Custom `tokio_tungstenite::Connector`s for cases the default
+//!   TLS setup does not cover
+//! - `connect_ws`, the WebSocket dial used by collectors and master
+//!   (honours TLS settings such as an SNI override)
+//!
+//! IMPORTANT:
+//! - No exchange- or master-specific logic lives here.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use rustls::{
+    ClientConfig, DigitallySignedStruct, SignatureScheme,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{CryptoProvider, ring, verify_tls12_signature, verify_tls13_signature},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+};
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::{TcpStream, lookup_host};
+use tokio_rustls::TlsConnector;
+use tokio_tungstenite::{
+    Connector, MaybeTlsStream, WebSocketStream, client_async, client_async_tls_with_config,
+    connect_async_tls_with_config,
+    tungstenite::{client::IntoClientRequest, http::header::SEC_WEBSOCKET_PROTOCOL},
+};
+
+use crate::config::{IpFamily, NetworkConfig};
+
+/// WebSocket stream returned by `connect_ws`
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Connect timeout for REST requests
+const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Overall timeout for REST requests (connect + response)
+const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Process-wide HTTP client (lazy, built once)
+///
+/// Sharing one client reuses DNS lookups, TLS sessions and pooled
+/// connections across REST calls, and bounds every call by the
+/// timeouts above.
+static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .connect_timeout(HTTP_CONNECT_TIMEOUT)
+        .timeout(HTTP_REQUEST_TIMEOUT)
+        .build()
+        .expect("failed to build HTTP client")
+});
+
+/// Returns the shared HTTP client for all REST requests.
+pub fn http_client() -> &'static reqwest::Client {
+    &HTTP_CLIENT
+}
+
+/// TLS / connection settings of one connection target (master or
+/// exchange).
+#[derive(Clone, Default)]
+pub struct TlsSettings {
+    /// Custom connector (e.g. certificate pinning); default roots if unset
+    pub connector: Option<Connector>,
+
+    /// Host name sent as SNI instead of the URL host
+    pub sni_override: Option<String>,
+
+    /// Address family to connect over
+    pub ip_family: IpFamily,
+
+    /// TCP keepalive idle time; OS default if unset
+    pub tcp_keepalive: Option<Duration>,
+}
+
+impl TlsSettings {
+    /// Settings from an optional `network` config section.
+    pub fn from_network(network: Option<&NetworkConfig>) -> Self {
+        Self {
+            connector: None,
+            sni_override: network.and_then(|n| n.sni_override.clone()),
+            ip_family: network.map(|n| n.ip_family).unwrap_or_default(),
+            tcp_keepalive: network
+                .and_then(|n| n.tcp_keepalive_secs)
+                .map(Duration::from_secs),
+        }
+    }
+}
+
+/// Opens a WebSocket connection using the given TLS settings.
+///
+/// Without an SNI override, IP family or keepalive this is a plain
+/// `connect_async_tls_with_config`. Otherwise the TCP connection is
+/// opened here (`connect_tcp`). With an override the TLS layer is set
+/// up here as well, so the handshake uses the override name while the
+/// HTTP `Host` header still uses the URL host (CDN fronting).
+///
+/// Non-empty `subprotocols` are sent as `Sec-WebSocket-Protocol`.
+pub async fn connect_ws(
+    url: &str,
+    tls: &TlsSettings,
+    subprotocols: &[&str],
+) -> anyhow::Result<WsStream> {
+    let mut request = url.into_client_request()?;
+    if !subprotocols.is_empty() {
+        request
+            .headers_mut()
+            .insert(SEC_WEBSOCKET_PROTOCOL, subprotocols.join(", ").parse()?);
+    }
+
+    if tls.sni_override.is_none() && tls.ip_family == IpFamily::Auto && tls.tcp_keepalive.is_none() {
+        let (ws, _) = connect_async_tls_with_config(request, None, false, tls.connector.clone()).await?;
+        return Ok(ws);
+    }
+
+    let uri: hyper::Uri = url.parse()?;
+    let secure = uri.scheme_str() == Some("wss");
+
+    let host = uri.host().ok_or_else(|| anyhow::anyhow!("URL without host: {}", url))?;
+    let port = uri.port_u16().unwrap_or(if secure { 443 } else { 80 });
+
+    // Checked before dialing: a bad override never opens a socket
+    let sni = match &tls.sni_override {
+        Some(_) if !secure => return Err(anyhow::anyhow!("sni_override requires a wss:// URL: {}", url)),
+        Some(sni) => Some((sni_connector(tls.connector.as_ref())?, ServerName::try_from(sni.clone())?)),
+        None => None,
+    };
+
+    let tcp = connect_tcp(host, port, tls).await?;
+
+    let Some((connector, sni)) = sni else {
+        let (ws, _) = client_async_tls_with_config(request, tcp, None, tls.connector.clone()).await?;
+        return Ok(ws);
+    };
+
+    let tls_stream = connector.connect(sni, tcp).await?;
+
+    let (ws, _) = client_async(request, MaybeTlsStream::Rustls(tls_stream)).await?;
+    Ok(ws)
+}
+
+/// Connects to the first reachable address of `host` in the
+/// configured family and applies the socket options.
+///
+/// Addresses are tried in resolver order; the last error is
+/// returned if none connects.
+async fn connect_tcp(host: &str, port: u16, tls: &TlsSettings) -> anyhow::Result<TcpStream> {
+    let family = tls.ip_family;
+
+    // IPv6 literals come bracketed from the URI
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    let mut last_err = None;
+
+    for addr in lookup_host((host, port)).await?.filter(|a| family.allows(a)) {
+        match TcpStream::connect(addr).await {
+            Ok(tcp) => {
+                if let Some(idle) = tls.tcp_keepalive {
+                    SockRef::from(&tcp).set_tcp_keepalive(&TcpKeepalive::new().with_time(idle))?;
+                }
+                return Ok(tcp);
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    Err(match last_err {
+        Some(e) => e.into(),
+        None => anyhow::anyhow!("no {:?} address for {}", family, host),
+    })
+}
+
+/// rustls connector for an SNI override.
+///
+/// Reuses the rustls config of a custom connector (e.g. pinning),
+/// otherwise validates against the native root store.
+fn sni_connector(connector: Option<&Connector>) -> anyhow::Result<TlsConnector> {
+    let config = match connector {
+        Some(Connector::Rustls(config)) => config.clone(),
+        Some(_) => return Err(anyhow::anyhow!("sni_override requires a rustls connector")),
+        None => NATIVE_ROOTS_CONFIG.clone(),
+    };
+
+    Ok(TlsConnector::from(config))
+}
+
+/// rustls client config with the native root certificates (lazy)
+static NATIVE_ROOTS_CONFIG: Lazy<Arc<ClientConfig>> = Lazy::new(|| {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+
+    Arc::new(
+        ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    )
+});
+
+/// Builds a TLS connector that only accepts a server certificate
+/// with the given SHA-256 fingerprint.
+///
+/// INPUT:
+/// - Hex-encoded SHA-256 of the DER certificate
+///   (colons and case are ignored, e.g. "AB:CD:..." or "abcd...")
+///
+/// DESIGN NOTES:
+/// - The pinned certificate replaces chain validation, so
+///   self-signed master certificates work.
+/// - Handshake signatures are still verified against the
+///   presented (pinned) certificate.
+pub fn pinned_cert_connector(fingerprint_hex: &str) -> anyhow::Result<Connector> {
+    let verifier = PinnedCertVerifier {
+        fingerprint: parse_fingerprint(fingerprint_hex)?,
+        provider: Arc::new(ring::default_provider()),
+    };
+
+    let config = ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+
+    Ok(Connector::Rustls(Arc::new(config)))
+}
+
+/// Parses a hex SHA-256 fingerprint into 32 raw bytes.
+///
+/// Anything but hex digits, colons and whitespace is rejected up
+/// front, so the byte slicing below stays on char boundaries.
+fn parse_fingerprint(hex: &str) -> anyhow::Result<[u8; 32]> {
+    let clean: String = hex
+        .chars()
+        .filter(|c| *c != ':' && !c.is_whitespace())
+        .collect();
+
+    if let Some(bad) = clean.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(anyhow::anyhow!("invalid character {:?} in fingerprint: {}", bad, hex));
+    }
+
+    if clean.len() != 64 {
+        return Err(anyhow::anyhow!(
+            "SHA-256 fingerprint must be 64 hex characters, got {}",
+            clean.len()
+        ));
+    }
+
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&clean[i * 2..i * 2 + 2], 16)
+            .map_err(|_| anyhow::anyhow!("invalid hex in fingerprint: {}", hex))?;
+    }
+
+    Ok(out)
+}
+
+/// rustls verifier accepting exactly one certificate fingerprint.
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    fingerprint: [u8; 32],
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let digest = ::ring::digest::digest(&::ring::digest::SHA256, end_entity.as_ref());
+
+        if digest.as_ref() == self.fingerprint {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "server certificate does not match pinned SHA-256 fingerprint".into(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand-in DER bytes: the verifier only hashes them.
+    const CERT: &[u8] = b"pinned master certificate";
+
+    fn fingerprint_of(der: &[u8]) -> String {
+        ::ring::digest::digest(&::ring::digest::SHA256, der)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    /// `main` installs the provider; tests building configs need it too.
+    fn install_crypto_provider() {
+        let _ = CryptoProvider::install_default(ring::default_provider());
+    }
+
+    fn verify(fingerprint: &str, der: &[u8]) -> Result<ServerCertVerified, rustls::Error> {
+        let verifier = PinnedCertVerifier {
+            fingerprint: parse_fingerprint(fingerprint).unwrap(),
+            provider: Arc::new(ring::default_provider()),
+        };
+
+        verifier.verify_server_cert(
+            &CertificateDer::from(der.to_vec()),
+            &[],
+            &ServerName::try_from("master.example").unwrap(),
+            &[],
+            UnixTime::now(),
+        )
+    }
+
+    #[test]
+    fn only_the_pinned_certificate_is_accepted() {
+        let pinned = fingerprint_of(CERT);
+
+        assert!(verify(&pinned, CERT).is_ok());
+        assert!(verify(&pinned.to_lowercase().replace(':', ""), CERT).is_ok());
+        assert!(verify(&pinned, b"another certificate").is_err());
+    }
+
+    #[test]
+    fn malformed_fingerprints_are_rejected() {
+        let pinned = fingerprint_of(CERT).replace(':', "");
+
+        // Non-ASCII of the right byte length must not panic
+        let multibyte = format!("é{}", &pinned[2..]);
+        assert_eq!(multibyte.len(), 64);
+        assert!(parse_fingerprint(&multibyte).is_err());
+
+        assert!(parse_fingerprint(&format!("zz{}", &pinned[2..])).is_err());
+        assert!(parse_fingerprint(&pinned[2..]).is_err());
+        assert!(parse_fingerprint(&format!("{pinned}00")).is_err());
+    }
+
+    #[tokio::test]
+    async fn tcp_keepalive_sets_so_keepalive() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let with_keepalive = TlsSettings { tcp_keepalive: Some(Duration::from_secs(30)), ..Default::default() };
+        let tcp = connect_tcp("127.0.0.1", port, &with_keepalive).await.unwrap();
+        assert!(SockRef::from(&tcp).keepalive().unwrap());
+
+        let tcp = connect_tcp("127.0.0.1", port, &TlsSettings::default()).await.unwrap();
+        assert!(!SockRef::from(&tcp).keepalive().unwrap());
+    }
+
+    #[tokio::test]
+    async fn ip_family_filters_resolved_addresses() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let family = |ip_family| TlsSettings { ip_family, ..Default::default() };
+
+        assert!(connect_tcp("127.0.0.1", port, &family(IpFamily::V4)).await.is_ok());
+
+        let err = connect_tcp("127.0.0.1", port, &family(IpFamily::V6)).await.unwrap_err();
+        assert_eq!(err.to_string(), "no V6 address for 127.0.0.1");
+
+        // Bracketed IPv6 literals resolve, then are filtered out
+        let err = connect_tcp("[::1]", port, &family(IpFamily::V4)).await.unwrap_err();
+        assert_eq!(err.to_string(), "no V4 address for ::1");
+    }
+
+    #[tokio::test]
+    async fn sni_overrides_are_checked_before_dialing() {
+        install_crypto_provider();
+
+        // Port 9 (discard) is never dialed: every case fails up front
+        let sni = |name: &str, connector| TlsSettings {
+            sni_override: Some(name.to_string()),
+            connector,
+            ..Default::default()
+        };
+
+        let err = connect_ws("ws://127.0.0.1:9", &sni("cdn.example", None), &[]).await.unwrap_err();
+        assert!(err.to_string().starts_with("sni_override requires a wss:// URL"));
+
+        let err = connect_ws("wss://127.0.0.1:9", &sni("cdn.example", Some(Connector::Plain)), &[]).await.unwrap_err();
+        assert_eq!(err.to_string(), "sni_override requires a rustls connector");
+
+        assert!(connect_ws("wss://127.0.0.1:9", &sni("not a host name", None), &[]).await.is_err());
+    }
+
+    #[test]
+    fn sni_connectors_reuse_a_pinning_config() {
+        install_crypto_provider();
+        let pinned = pinned_cert_connector(&fingerprint_of(CERT)).unwrap();
+
+        assert!(sni_connector(None).is_ok());
+        assert!(sni_connector(Some(&pinned)).is_ok());
+        assert!(sni_connector(Some(&Connector::Plain)).is_err());
+    }
+
+    // The handshake callback signature is tungstenite's
+    #[allow(clippy::result_large_err)]
+    #[tokio::test]
+    async fn subprotocols_are_requested_in_the_handshake() {
+        use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // Echoes the requested protocols back, like a server accepting them
+        let server = tokio::spawn(async move {
+            let mut requested = Vec::new();
+
+            for _ in 0..2 {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut header = None;
+
+                let callback = |req: &Request, mut resp: Response| -> Result<Response, ErrorResponse> {
+                    if let Some(value) = req.headers().get(SEC_WEBSOCKET_PROTOCOL) {
+                        header = Some(value.to_str().unwrap().to_string());
+                        resp.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, value.clone());
+                    }
+                    Ok(resp)
+                };
+                let _ws = tokio_tungstenite::accept_hdr_async(tcp, callback).await.unwrap();
+
+                requested.push(header);
+            }
+
+            requested
+        });
+
+        connect_ws(&url, &TlsSettings::default(), &["v1.feed"]).await.unwrap();
+        connect_ws(&url, &TlsSettings::default(), &[]).await.unwrap();
+
+        assert_eq!(server.await.unwrap(), [Some("v1.feed".to_string()), None]);
+    }
+
+    #[tokio::test]
+    async fn rest_calls_reuse_pooled_connections() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        assert!(std::ptr::eq(http_client(), http_client()));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let accepted = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        // Keep-alive server: answers every request on the connection
+        tokio::spawn({
+            let accepted = accepted.clone();
+            async move {
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    accepted.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    tokio::spawn(async move {
+                        let mut request = [0u8; 4096];
+                        while socket.read(&mut request).await.is_ok_and(|n| n > 0) {
+                            let response = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n{}";
+                            if socket.write_all(response.as_bytes()).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+            }
+        });
+
+        for _ in 0..3 {
+            let body = http_client().get(&url).send().await.unwrap().text().await.unwrap();
+            assert_eq!(body, "{}");
+        }
+
+        assert_eq!(accepted.load(std::sync::atomic::Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+
+use super::{OutputSink, SendMeta};
+use crate::schema::MarketMessage;
+
+/// Prints messages to stdout instead of forwarding them
+/// (`exchange.demo`).
+///
+/// Same output as the master pool in demo mode, but per exchange:
+/// a newly added exchange can be validated while the others stay
+/// live.
+pub struct DemoSink;
+
+#[async_trait]
+impl OutputSink for DemoSink {
+    async fn send(&self, msg: &MarketMessage, _meta: SendMeta) -> anyhow::Result<()> {
+        println!("DEMO → {}", serde_json::to_string(msg)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+use async_trait::async_trait;
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::task::JoinHandle;
+
+use super::{OutputSink, SendMeta};
+use crate::metrics::METRICS;
+use crate::schema::MarketMessage;
+
+/// Messages buffered per sink before new ones are dropped
+const SINK_QUEUE: usize = 10_000;
+
+/// Forwards every message to all wrapped sinks.
+///
+/// DESIGN NOTES:
+/// - Every sink has its own bounded queue and worker task, so a slow
+///   sink never stalls the others or the collector.
+/// - A full queue drops the message for that sink only
+///   (`output_dropped`).
+/// - Errors of a wrapped sink are counted as `send_errors`.
+/// - `shutdown` stops accepting messages, lets every worker drain its
+///   queue, then shuts the wrapped sinks down.
+pub struct FanOutSink {
+    queues: Vec<mpsc::Sender<(MarketMessage, SendMeta)>>,
+    sinks: Vec<Arc<dyn OutputSink>>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+    closing: watch::Sender<bool>,
+}
+
+impl FanOutSink {
+    pub fn new(sinks: Vec<Arc<dyn OutputSink>>) -> Self {
+        let (closing, _) = watch::channel(false);
+        let mut workers = Vec::with_capacity(sinks.len());
+
+        let queues = sinks
+            .iter()
+            .map(|sink| {
+                let (tx, rx) = mpsc::channel::<(MarketMessage, SendMeta)>(SINK_QUEUE);
+                workers.push(tokio::spawn(drain_into(sink.clone(), rx, closing.subscribe())));
+                tx
+            })
+            .collect();
+
+        Self {
+            queues,
+            sinks,
+            workers: Mutex::new(workers),
+            closing,
+        }
+    }
+}
+
+/// Worker of one sink: forwards queued messages until shutdown, then
+/// closes the queue and forwards what is left in it.
+async fn drain_into(
+    sink: Arc<dyn OutputSink>,
+    mut rx: mpsc::Receiver<(MarketMessage, SendMeta)>,
+    mut closing: watch::Receiver<bool>,
+) {
+    loop {
+        let queued = tokio::select! {
+            queued = rx.recv() => queued,
+            _ = closing.wait_for(|c| *c) => break,
+        };
+
+        match queued {
+            Some((msg, meta)) => forward(&*sink, &msg, meta).await,
+            None => return,
+        }
+    }
+
+    rx.close();
+    while let Some((msg, meta)) = rx.recv().await {
+        forward(&*sink, &msg, meta).await;
+    }
+}
+
+async fn forward(sink: &dyn OutputSink, msg: &MarketMessage, meta: SendMeta) {
+    if sink.send(msg, meta).await.is_err() {
+        METRICS.send_errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[async_trait]
+impl OutputSink for FanOutSink {
+    async fn send(&self, msg: &MarketMessage, meta: SendMeta) -> anyhow::Result<()> {
+        for queue in &self.queues {
+            if queue.try_send((msg.clone(), meta)).is_err() {
+                METRICS.output_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn shutdown(&self) {
+        let _ = self.closing.send(true);
+
+        let workers = std::mem::take(&mut *self.workers.lock().await);
+        futures_util::future::join_all(workers).await;
+
+        futures_util::future::join_all(self.sinks.iter().map(|s| s.shutdown())).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex as StdMutex;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    /// Sink that takes a while per message and records what it got.
+    #[derive(Default)]
+    struct SlowSink {
+        received: StdMutex<Vec<String>>,
+        shut_down: AtomicBool,
+    }
+
+    #[async_trait]
+    impl OutputSink for SlowSink {
+        async fn send(&self, msg: &MarketMessage, _meta: SendMeta) -> anyhow::Result<()> {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            self.received.lock().unwrap().push(msg.symbol().to_string());
+            Ok(())
+        }
+
+        async fn shutdown(&self) {
+            assert_eq!(self.received.lock().unwrap().len(), 20, "shut down before drained");
+            self.shut_down.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn trade(symbol: &str) -> MarketMessage {
+        serde_json::from_value(serde_json::json!({
+            "type": "trade", "exchange": "binance", "symbol": symbol,
+            "timestamp": 0, "price": "1", "amount": "1", "side": "buy",
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn shutdown_drains_every_queue_before_closing_the_sinks() {
+        let sinks = [Arc::new(SlowSink::default()), Arc::new(SlowSink::default())];
+        let fanout = FanOutSink::new(sinks.iter().map(|s| s.clone() as Arc<dyn OutputSink>).collect());
+
+        for i in 0..20 {
+            fanout.send(&trade(&format!("S{i}")), SendMeta::rest(None)).await.unwrap();
+        }
+        fanout.shutdown().await;
+
+        let expected: Vec<_> = (0..20).map(|i| format!("S{i}")).collect();
+        for sink in &sinks {
+            assert_eq!(*sink.received.lock().unwrap(), expected);
+            assert!(sink.shut_down.load(Ordering::SeqCst));
+        }
+
+        // Closed queues drop instead of buffering
+        fanout.send(&trade("late"), SendMeta::rest(None)).await.unwrap();
+        assert_eq!(sinks[0].received.lock().unwrap().len(), 20);
+    }
+
+    /// Sink whose sends never complete.
+    struct StuckSink;
+
+    #[async_trait]
+    impl OutputSink for StuckSink {
+        async fn send(&self, _msg: &MarketMessage, _meta: SendMeta) -> anyhow::Result<()> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn a_stuck_sink_does_not_block_the_others() {
+        let fast = Arc::new(crate::output::recording::RecordingSink::default());
+        let fanout = FanOutSink::new(vec![Arc::new(StuckSink), fast.clone()]);
+
+        for i in 0..3 {
+            fanout.send(&trade(&format!("S{i}")), SendMeta::rest(None)).await.unwrap();
+        }
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while fast.messages().len() < 3 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("fast sink starved by the stuck one");
+    }
+}
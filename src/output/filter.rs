@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::{OutputSink, SendMeta};
+use crate::schema::MarketMessage;
+
+/// Forwards only selected message types to the wrapped sink.
+///
+/// Types use the serialized `type` names ("trade", "book", "ticker").
+/// Other messages are skipped silently and reported as accepted.
+pub struct FilterSink {
+    inner: Arc<dyn OutputSink>,
+    types: Vec<String>,
+}
+
+impl FilterSink {
+    /// Fails on unknown type names, so typos do not silently
+    /// filter everything.
+    pub fn new(inner: Arc<dyn OutputSink>, types: Vec<String>) -> anyhow::Result<Self> {
+        if let Some(unknown) = types
+            .iter()
+            .find(|t| !MarketMessage::KINDS.contains(&t.as_str()))
+        {
+            return Err(anyhow::anyhow!(
+                "unknown message type '{}' (expected one of {:?})",
+                unknown,
+                MarketMessage::KINDS
+            ));
+        }
+
+        Ok(Self { inner, types })
+    }
+}
+
+#[async_trait]
+impl OutputSink for FilterSink {
+    async fn send(&self, msg: &MarketMessage, meta: SendMeta) -> anyhow::Result<()> {
+        if !self.types.iter().any(|t| t == msg.kind()) {
+            return Ok(());
+        }
+
+        self.inner.send(msg, meta).await
+    }
+
+    async fn shutdown(&self) {
+        self.inner.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::recording::RecordingSink;
+
+    #[tokio::test]
+    async fn only_selected_types_are_forwarded() {
+        let recording = Arc::new(RecordingSink::default());
+        let filter = FilterSink::new(recording.clone(), vec!["trade".into()]).unwrap();
+
+        let trade: MarketMessage = serde_json::from_value(serde_json::json!({
+            "type": "trade", "exchange": "binance", "symbol": "BTC/USDT",
+            "timestamp": 0, "price": "1", "amount": "1", "side": "buy",
+        }))
+        .unwrap();
+        let book: MarketMessage = serde_json::from_value(serde_json::json!({
+            "type": "book", "exchange": "binance", "symbol": "BTC/USDT",
+            "timestamp": 0, "asks": [["2", "1"]], "bids": [["1", "1"]],
+        }))
+        .unwrap();
+
+        filter.send(&book, SendMeta::rest(None)).await.unwrap();
+        filter.send(&trade, SendMeta::rest(None)).await.unwrap();
+
+        let kinds: Vec<_> = recording.messages().iter().map(|m| m.kind()).collect();
+        assert_eq!(kinds, ["trade"]);
+    }
+
+    #[test]
+    fn unknown_types_are_rejected() {
+        let err = FilterSink::new(Arc::new(RecordingSink::default()), vec!["trades".into()]).err().unwrap();
+        assert!(err.to_string().starts_with("unknown message type 'trades'"));
+    }
+}
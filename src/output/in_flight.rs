@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Semaphore;
+
+use super::{OutputSink, SendMeta};
+use crate::schema::MarketMessage;
+
+/// Caps the concurrent sends of one exchange (`max_in_flight`).
+///
+/// Wraps the shared output per exchange, so a flooding exchange
+/// waits for its own permits instead of filling the master queues
+/// the other exchanges send into.
+pub struct InFlightSink {
+    inner: Arc<dyn OutputSink>,
+    permits: Semaphore,
+}
+
+impl InFlightSink {
+    pub fn new(inner: Arc<dyn OutputSink>, max: usize) -> Self {
+        Self {
+            inner,
+            permits: Semaphore::new(max.max(1)),
+        }
+    }
+}
+
+#[async_trait]
+impl OutputSink for InFlightSink {
+    async fn send(&self, msg: &MarketMessage, meta: SendMeta) -> anyhow::Result<()> {
+        let _permit = self.permits.acquire().await?;
+        self.inner.send(msg, meta).await
+    }
+
+    async fn shutdown(&self) {
+        self.inner.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Sink that records the highest number of concurrent sends.
+    #[derive(Default)]
+    struct ConcurrencyProbe {
+        current: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl OutputSink for ConcurrencyProbe {
+        async fn send(&self, _msg: &MarketMessage, _meta: SendMeta) -> anyhow::Result<()> {
+            let now = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            self.current.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_sends_never_exceed_the_limit() {
+        let probe = Arc::new(ConcurrencyProbe::default());
+        let sink = Arc::new(InFlightSink::new(probe.clone(), 3));
+
+        let trade: MarketMessage = serde_json::from_value(serde_json::json!({
+            "type": "trade", "exchange": "binance", "symbol": "BTC/USDT",
+            "timestamp": 0, "price": "1", "amount": "1", "side": "buy",
+        }))
+        .unwrap();
+
+        let sends = (0..20).map(|_| {
+            let (sink, trade) = (sink.clone(), trade.clone());
+            tokio::spawn(async move { sink.send(&trade, SendMeta::rest(None)).await })
+        });
+        for send in futures_util::future::join_all(sends).await {
+            send.unwrap().unwrap();
+        }
+
+        assert_eq!(probe.peak.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,150 @@
+//! Output layer.
+//!
+//! Everything the collector produces leaves the process through an
+//! `OutputSink`. By default that is the master pool; `output.sinks`
+//! configures several destinations (master, local UNIX socket, …).
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::config::{NormalizeConfig, OutputConfig, SinkConfig, SinkEntry};
+use crate::master_sender::MasterPool;
+use crate::schema::MarketMessage;
+use crate::util;
+
+pub mod demo;
+pub mod fanout;
+pub mod filter;
+pub mod in_flight;
+pub mod normalize;
+#[cfg(test)]
+pub mod recording;
+pub mod routes;
+pub mod unix;
+pub mod validate;
+
+pub use demo::DemoSink;
+pub use fanout::FanOutSink;
+pub use filter::FilterSink;
+pub use in_flight::InFlightSink;
+pub use normalize::NormalizeSink;
+pub use routes::MasterRoutes;
+pub use unix::UnixSocketSink;
+pub use validate::ValidateSink;
+
+/// Collector context of one message, passed along with it through
+/// the sink chain.
+#[derive(Debug, Clone, Copy)]
+pub struct SendMeta {
+    /// Registry id of the WS connection the message came from
+    /// (sticky routing key); None for REST pollers
+    pub conn_id: Option<usize>,
+
+    /// Parsed from a live WS frame, not fetched via REST (backfill,
+    /// book snapshots, pollers); only these are clock skew samples
+    pub live: bool,
+
+    /// Time the frame / REST response reached the collector (ms,
+    /// `$received_at` of the master envelope)
+    pub received_at: i64,
+}
+
+impl SendMeta {
+    /// Context of a live frame received just now on WS connection
+    /// `conn_id`.
+    pub fn live(conn_id: usize) -> Self {
+        Self { conn_id: Some(conn_id), live: true, received_at: util::now_ms() }
+    }
+
+    /// Context of a REST response received just now, on behalf of WS
+    /// connection `conn_id` if any.
+    pub fn rest(conn_id: Option<usize>) -> Self {
+        Self { conn_id, live: false, received_at: util::now_ms() }
+    }
+}
+
+/// Destination for normalized market messages.
+///
+/// CONTRACT:
+/// - `send` must not block the collector for long; sinks buffer
+///   internally and drop under backpressure.
+/// - An `Err` means the message was not accepted (counted as a send
+///   error by the caller).
+/// - Wrapping sinks pass `meta` on unchanged.
+/// - `shutdown` flushes what the sink buffered and closes it;
+///   wrapping sinks pass it on to the wrapped sink.
+#[async_trait]
+pub trait OutputSink: Send + Sync {
+    async fn send(&self, msg: &MarketMessage, meta: SendMeta) -> anyhow::Result<()>;
+
+    /// Drains buffered messages before the process exits.
+    async fn shutdown(&self) {}
+}
+
+#[async_trait]
+impl OutputSink for MasterPool {
+    async fn send(&self, msg: &MarketMessage, meta: SendMeta) -> anyhow::Result<()> {
+        MasterPool::send(self, serde_json::to_value(msg)?, meta).await
+    }
+
+    async fn shutdown(&self) {
+        MasterPool::shutdown(self).await
+    }
+}
+
+/// Builds the output sink from the master pool, `output.sinks` and
+/// the optional `validate_output` / `normalize` steps in front of
+/// everything (validation first).
+pub fn build(
+    master: MasterPool,
+    cfg: Option<&OutputConfig>,
+    normalize: Option<&NormalizeConfig>,
+    validate: bool,
+) -> anyhow::Result<Arc<dyn OutputSink>> {
+    let mut sink = build_sinks(master, cfg)?;
+
+    if let Some(n) = normalize {
+        sink = Arc::new(NormalizeSink::new(sink, n.clone()));
+    }
+
+    if validate {
+        sink = Arc::new(ValidateSink::new(sink));
+    }
+
+    Ok(sink)
+}
+
+/// Without an `output` section the master pool is used directly.
+/// Several sinks are wrapped in a `FanOutSink`.
+fn build_sinks(master: MasterPool, cfg: Option<&OutputConfig>) -> anyhow::Result<Arc<dyn OutputSink>> {
+    let Some(cfg) = cfg else {
+        return Ok(Arc::new(master));
+    };
+
+    let mut sinks = cfg
+        .sinks
+        .iter()
+        .map(|sink_cfg| build_sink(&master, sink_cfg))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    match sinks.len() {
+        0 => Err(anyhow::anyhow!("output.sinks must not be empty")),
+        1 => Ok(sinks.remove(0)),
+        _ => Ok(Arc::new(FanOutSink::new(sinks))),
+    }
+}
+
+/// Builds one configured sink, wrapped in a `FilterSink` if
+/// `types` is set.
+fn build_sink(master: &MasterPool, cfg: &SinkEntry) -> anyhow::Result<Arc<dyn OutputSink>> {
+    let sink: Arc<dyn OutputSink> = match &cfg.sink {
+        SinkConfig::Master => Arc::new(master.clone()),
+        SinkConfig::Unix { path } => Arc::new(UnixSocketSink::bind(path)?),
+    };
+
+    match &cfg.types {
+        Some(types) => Ok(Arc::new(FilterSink::new(sink, types.clone())?)),
+        None => Ok(sink),
+    }
+}
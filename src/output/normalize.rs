@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::{OutputSink, SendMeta};
+use crate::config::NormalizeConfig;
+use crate::schema::MarketMessage;
+use crate::util::round_decimal_str;
+
+/// Rounds prices / amounts of every message before forwarding it.
+///
+/// Applied once in front of all sinks, so every destination sees the
+/// same precision regardless of the source exchange. Fields without
+/// configured decimals are passed through untouched.
+pub struct NormalizeSink {
+    inner: Arc<dyn OutputSink>,
+    cfg: NormalizeConfig,
+}
+
+impl NormalizeSink {
+    pub fn new(inner: Arc<dyn OutputSink>, cfg: NormalizeConfig) -> Self {
+        Self { inner, cfg }
+    }
+
+    fn normalize(&self, msg: &mut MarketMessage) {
+        let price = |v: &mut String| round(v, self.cfg.price_decimals);
+        let amount = |v: &mut String| round(v, self.cfg.amount_decimals);
+
+        match msg {
+            MarketMessage::Trade(t) => {
+                price(&mut t.price);
+                amount(&mut t.amount);
+            }
+
+            MarketMessage::Book(b) => {
+                for [p, a] in b.asks.iter_mut().chain(b.bids.iter_mut()) {
+                    price(p);
+                    amount(a);
+                }
+            }
+
+            MarketMessage::Ticker(t) => {
+                for p in [&mut t.bid, &mut t.ask, &mut t.last].into_iter().flatten() {
+                    price(p);
+                }
+                if let Some(v) = &mut t.vol_24h {
+                    amount(v);
+                }
+            }
+
+            MarketMessage::Funding(f) => {
+                if let Some(p) = &mut f.mark_price {
+                    price(p);
+                }
+            }
+        }
+    }
+}
+
+fn round(value: &mut String, decimals: Option<u32>) {
+    if let Some(decimals) = decimals {
+        *value = round_decimal_str(value, decimals);
+    }
+}
+
+#[async_trait]
+impl OutputSink for NormalizeSink {
+    async fn send(&self, msg: &MarketMessage, meta: SendMeta) -> anyhow::Result<()> {
+        let mut msg = msg.clone();
+        self.normalize(&mut msg);
+
+        self.inner.send(&msg, meta).await
+    }
+
+    async fn shutdown(&self) {
+        self.inner.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::recording::RecordingSink;
+
+    #[test]
+    fn decimals_round_to_plain_notation() {
+        assert_eq!(round_decimal_str("0.123456789", 4), "0.1235");
+        assert_eq!(round_decimal_str("0.00005", 4), "0.0001");
+        assert_eq!(round_decimal_str("1.2e-5", 4), "0.0000");
+        assert_eq!(round_decimal_str("42.1", 4), "42.1");
+        assert_eq!(round_decimal_str("n/a", 4), "n/a");
+    }
+
+    #[tokio::test]
+    async fn trades_are_rounded_per_field() {
+        let recorder = Arc::new(RecordingSink::default());
+        let cfg = NormalizeConfig { price_decimals: Some(2), amount_decimals: None };
+        let sink = NormalizeSink::new(recorder.clone(), cfg);
+
+        let trade: MarketMessage = serde_json::from_value(serde_json::json!({
+            "type": "trade", "exchange": "binance", "symbol": "BTC/USDT",
+            "timestamp": 1, "price": "42000.123456789", "amount": "0.123456789", "side": "buy",
+        }))
+        .unwrap();
+
+        sink.send(&trade, SendMeta::rest(None)).await.unwrap();
+
+        let MarketMessage::Trade(sent) = &recorder.messages()[0] else {
+            panic!("expected a trade");
+        };
+        assert_eq!(sent.price, "42000.12");
+        assert_eq!(sent.amount, "0.123456789");
+    }
+}
@@ -0,0 +1,33 @@
+//! In-memory sink for tests: keeps every message it is sent.
+
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use super::{OutputSink, SendMeta};
+use crate::schema::MarketMessage;
+
+#[derive(Default)]
+pub struct RecordingSink {
+    sent: Mutex<Vec<(MarketMessage, SendMeta)>>,
+}
+
+impl RecordingSink {
+    /// Messages received so far, in order.
+    pub fn messages(&self) -> Vec<MarketMessage> {
+        self.sent.lock().unwrap().iter().map(|(msg, _)| msg.clone()).collect()
+    }
+
+    /// Context of every message received so far, in order.
+    pub fn metas(&self) -> Vec<SendMeta> {
+        self.sent.lock().unwrap().iter().map(|(_, meta)| *meta).collect()
+    }
+}
+
+#[async_trait]
+impl OutputSink for RecordingSink {
+    async fn send(&self, msg: &MarketMessage, meta: SendMeta) -> anyhow::Result<()> {
+        self.sent.lock().unwrap().push((msg.clone(), meta));
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use super::OutputSink;
+use crate::config::{MasterConfig, NormalizeConfig};
+use crate::master_sender::MasterPool;
+
+/// Picks the output of an exchange (`exchange.master_url`).
+///
+/// Exchanges without an override share the main output. For every
+/// other master URL a dedicated `MasterPool` is built on first use,
+/// from the main `master` settings with the URL replaced, and shared
+/// by all exchanges naming the same URL. Dedicated pools get the
+/// `validate_output` / `normalize` steps, but not `output.sinks`.
+pub struct MasterRoutes {
+    shared: Arc<dyn OutputSink>,
+    template: MasterConfig,
+    debug: bool,
+    normalize: Option<NormalizeConfig>,
+    validate: bool,
+
+    /// master URL → its dedicated pool
+    dedicated: Mutex<HashMap<String, DedicatedPool>>,
+}
+
+/// Dedicated master pool plus the output built on it
+struct DedicatedPool {
+    pool: MasterPool,
+    output: Arc<dyn OutputSink>,
+}
+
+impl MasterRoutes {
+    pub fn new(
+        shared: Arc<dyn OutputSink>,
+        template: MasterConfig,
+        debug: bool,
+        normalize: Option<NormalizeConfig>,
+        validate: bool,
+    ) -> Self {
+        Self {
+            shared,
+            template,
+            debug,
+            normalize,
+            validate,
+            dedicated: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the output for an exchange with the given override.
+    ///
+    /// ERRORS:
+    /// - The dedicated pool cannot be built (e.g. dead-letter file)
+    pub async fn output_for(&self, master_url: Option<&str>) -> anyhow::Result<Arc<dyn OutputSink>> {
+        let url = match master_url {
+            Some(url) if url != self.template.url => url,
+            _ => return Ok(self.shared.clone()),
+        };
+
+        let mut dedicated = self.dedicated.lock().await;
+
+        if let Some(route) = dedicated.get(url) {
+            return Ok(route.output.clone());
+        }
+
+        tracing::info!("Connecting dedicated master pool to {}", url);
+
+        let cfg = MasterConfig {
+            url: url.to_string(),
+            ..self.template.clone()
+        };
+        let pool = MasterPool::new(&cfg, self.debug).await?;
+        let output = super::build(pool.clone(), None, self.normalize.as_ref(), self.validate)?;
+
+        dedicated.insert(url.to_string(), DedicatedPool { pool, output: output.clone() });
+        Ok(output)
+    }
+
+    /// Queue depths of every dedicated pool, by master URL.
+    ///
+    /// The shared master is not included.
+    pub async fn dedicated_queue_depths(&self) -> BTreeMap<String, Vec<usize>> {
+        let pools: Vec<(String, MasterPool)> = self.dedicated
+            .lock()
+            .await
+            .iter()
+            .map(|(url, route)| (url.clone(), route.pool.clone()))
+            .collect();
+
+        let mut depths = BTreeMap::new();
+        for (url, pool) in pools {
+            depths.insert(url, pool.queue_depths().await);
+        }
+        depths
+    }
+
+    /// Drains the shared output and every dedicated pool in parallel.
+    pub async fn shutdown(&self) {
+        let dedicated: Vec<_> = self.dedicated.lock().await.values().map(|r| r.output.clone()).collect();
+
+        futures_util::future::join_all(
+            std::iter::once(&self.shared).chain(&dedicated).map(|o| o.shutdown())
+        ).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::recording::RecordingSink;
+
+    fn routes(shared: Arc<dyn OutputSink>) -> MasterRoutes {
+        // Demo pools open no connections
+        let template = serde_json::from_value(serde_json::json!({
+            "url": "wss://master.example", "connections": 2, "key": "k", "demo": true
+        }))
+        .unwrap();
+
+        MasterRoutes::new(shared, template, false, None, false)
+    }
+
+    #[tokio::test]
+    async fn exchanges_share_the_pool_of_their_master_url() {
+        let shared: Arc<dyn OutputSink> = Arc::new(RecordingSink::default());
+        let routes = routes(shared.clone());
+
+        assert!(Arc::ptr_eq(&routes.output_for(None).await.unwrap(), &shared));
+        assert!(Arc::ptr_eq(&routes.output_for(Some("wss://master.example")).await.unwrap(), &shared));
+
+        let eu = routes.output_for(Some("wss://eu.example")).await.unwrap();
+        assert!(!Arc::ptr_eq(&eu, &shared));
+        assert!(Arc::ptr_eq(&routes.output_for(Some("wss://eu.example")).await.unwrap(), &eu));
+
+        routes.output_for(Some("wss://us.example")).await.unwrap();
+
+        let urls: Vec<_> = routes.dedicated_queue_depths().await.into_keys().collect();
+        assert_eq!(urls, ["wss://eu.example", "wss://us.example"]);
+    }
+}
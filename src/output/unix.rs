@@ -0,0 +1,130 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+use tokio::net::UnixListener;
+use tokio::sync::mpsc;
+
+use super::{OutputSink, SendMeta};
+use crate::metrics::METRICS;
+use crate::schema::MarketMessage;
+
+/// Messages buffered per client before new ones are dropped
+const CLIENT_BUFFER: usize = 1024;
+
+/// Broadcasts NDJSON `MarketMessage`s to all clients of a UNIX socket.
+///
+/// DESIGN NOTES:
+/// - Every client has its own bounded buffer and writer task.
+/// - A slow client only loses its own messages (`output_dropped`);
+///   other clients and the collector are never blocked.
+/// - Disconnected clients are removed on the next send.
+pub struct UnixSocketSink {
+    clients: Arc<Mutex<Vec<mpsc::Sender<Arc<str>>>>>,
+}
+
+impl UnixSocketSink {
+    /// Binds the socket and starts accepting clients.
+    ///
+    /// A stale socket file from a previous run is removed first.
+    pub fn bind(path: &str) -> anyhow::Result<Self> {
+        if Path::new(path).exists() {
+            std::fs::remove_file(path)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        let clients: Arc<Mutex<Vec<mpsc::Sender<Arc<str>>>>> = Arc::default();
+
+        tracing::info!("UNIX socket output listening on {}", path);
+
+        let accept_clients = clients.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        tracing::warn!("UNIX socket accept failed: {}", e);
+                        continue;
+                    }
+                };
+
+                let (tx, mut rx) = mpsc::channel::<Arc<str>>(CLIENT_BUFFER);
+                accept_clients.lock().unwrap().push(tx);
+
+                tokio::spawn(async move {
+                    while let Some(line) = rx.recv().await {
+                        if stream.write_all(line.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(Self { clients })
+    }
+}
+
+#[async_trait]
+impl OutputSink for UnixSocketSink {
+    async fn send(&self, msg: &MarketMessage, _meta: SendMeta) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(msg)?;
+        line.push('\n');
+        let line: Arc<str> = line.into();
+
+        self.clients.lock().unwrap().retain(|client| {
+            match client.try_send(line.clone()) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    METRICS.output_dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::AsyncBufReadExt;
+
+    #[tokio::test]
+    async fn clients_receive_ndjson_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("collector.sock");
+        let sink = UnixSocketSink::bind(path.to_str().unwrap()).unwrap();
+
+        let client = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let mut lines = tokio::io::BufReader::new(client).lines();
+
+        // Messages sent before the client is accepted are not replayed
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while sink.clients.lock().unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let trade: MarketMessage = serde_json::from_value(serde_json::json!({
+            "type": "trade", "exchange": "binance", "symbol": "BTC/USDT",
+            "timestamp": 0, "price": "1", "amount": "1", "side": "buy",
+        }))
+        .unwrap();
+        sink.send(&trade, SendMeta::rest(None)).await.unwrap();
+
+        let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(line, serde_json::to_string(&trade).unwrap());
+    }
+}
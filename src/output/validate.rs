@@ -0,0 +1,174 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+
+use super::{OutputSink, SendMeta};
+use crate::metrics::METRICS;
+use crate::schema::{BookData, MarketMessage};
+
+/// Drops malformed messages before they reach any output.
+///
+/// Catches adapter bugs (e.g. empty symbols from a failed symbol
+/// conversion) at the collector instead of the master. Dropped
+/// messages are counted in `invalid_messages` and are not send
+/// errors.
+///
+/// Crossed books (best bid >= best ask, typically swapped sides in
+/// an adapter) are counted in `crossed_books` and logged, but still
+/// forwarded: incremental updates may cross briefly.
+pub struct ValidateSink {
+    inner: Arc<dyn OutputSink>,
+}
+
+impl ValidateSink {
+    pub fn new(inner: Arc<dyn OutputSink>) -> Self {
+        Self { inner }
+    }
+}
+
+/// Returns the first problem of `msg`, if any.
+///
+/// Rules:
+/// - exchange and symbol are non-empty
+/// - trades: price, amount and side are non-empty
+/// - funding: at least mark price or funding rate is set
+fn problem(msg: &MarketMessage) -> Option<&'static str> {
+    if msg.exchange().is_empty() {
+        return Some("empty exchange");
+    }
+
+    let symbol = match msg {
+        MarketMessage::Trade(t) => &t.symbol,
+        MarketMessage::Book(b) => &b.symbol,
+        MarketMessage::Ticker(t) => &t.symbol,
+        MarketMessage::Funding(f) => &f.symbol,
+    };
+
+    if symbol.is_empty() {
+        return Some("empty symbol");
+    }
+
+    match msg {
+        MarketMessage::Trade(t) if t.price.is_empty() => Some("empty price"),
+        MarketMessage::Trade(t) if t.amount.is_empty() => Some("empty amount"),
+        MarketMessage::Trade(t) if t.side.is_empty() => Some("empty side"),
+        MarketMessage::Funding(f) if f.mark_price.is_none() && f.funding_rate.is_none() => {
+            Some("neither mark price nor funding rate")
+        }
+        _ => None,
+    }
+}
+
+/// Returns (best bid, best ask) if the book is crossed.
+///
+/// Prices are compared as decimals; unparsable levels are ignored,
+/// as are delta levels with a zero amount (they remove a price, the
+/// removed bid may well be above the best ask).
+fn crossed(book: &BookData) -> Option<(Decimal, Decimal)> {
+    let prices = |levels: &[[String; 2]]| {
+        levels
+            .iter()
+            .filter(|[_, amount]| Decimal::from_str(amount).map_or(true, |a| !a.is_zero()))
+            .filter_map(|[price, _]| Decimal::from_str(price).ok())
+            .collect::<Vec<_>>()
+    };
+
+    let best_bid = prices(&book.bids).into_iter().max()?;
+    let best_ask = prices(&book.asks).into_iter().min()?;
+
+    (best_bid >= best_ask).then_some((best_bid, best_ask))
+}
+
+#[async_trait]
+impl OutputSink for ValidateSink {
+    async fn send(&self, msg: &MarketMessage, meta: SendMeta) -> anyhow::Result<()> {
+        if let Some(problem) = problem(msg) {
+            // Log only the first few, adapters repeat the same bug
+            if METRICS.invalid_messages.fetch_add(1, Ordering::Relaxed) < 10 {
+                tracing::warn!("[VALIDATE] dropped {} message from {}: {}", msg.kind(), msg.exchange(), problem);
+            }
+            return Ok(());
+        }
+
+        if let MarketMessage::Book(book) = msg
+            && let Some((bid, ask)) = crossed(book)
+            && METRICS.crossed_books.fetch_add(1, Ordering::Relaxed) < 10
+        {
+            tracing::warn!(
+                "[VALIDATE] crossed book from {} {}: best bid {} >= best ask {} – bids / asks swapped?",
+                book.exchange, book.symbol, bid, ask
+            );
+        }
+
+        self.inner.send(msg, meta).await
+    }
+
+    async fn shutdown(&self) {
+        self.inner.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::recording::RecordingSink;
+
+    fn book(bids: &[[&str; 2]], asks: &[[&str; 2]]) -> BookData {
+        let levels = |side: &[[&str; 2]]| side.iter().map(|l| l.map(String::from)).collect();
+
+        BookData {
+            exchange: "binance".into(),
+            symbol: "BTC/USDT".into(),
+            timestamp: 0,
+            asks: levels(asks),
+            bids: levels(bids),
+            source: None,
+            snapshot: false,
+        }
+    }
+
+    #[test]
+    fn crossed_compares_best_bid_and_ask() {
+        assert_eq!(crossed(&book(&[["100", "1"]], &[["101", "1"]])), None);
+        assert_eq!(
+            crossed(&book(&[["101.0", "1"]], &[["101", "2"]])),
+            Some((Decimal::from(101), Decimal::from(101)))
+        );
+    }
+
+    #[test]
+    fn crossed_skips_removed_levels() {
+        // The bid at 102 was removed after the price moved down
+        assert_eq!(crossed(&book(&[["102", "0.000"], ["100", "1"]], &[["101", "1"]])), None);
+        assert_eq!(crossed(&book(&[["100", "1"]], &[["99", "0"]])), None);
+    }
+
+    #[tokio::test]
+    async fn trades_without_price_or_side_are_dropped() {
+        let trade = |price: &str, side: &str| -> MarketMessage {
+            serde_json::from_value(serde_json::json!({
+                "type": "trade", "exchange": "binance", "symbol": "BTC/USDT", "timestamp": 1,
+                "price": price, "amount": "1", "side": side,
+            }))
+            .unwrap()
+        };
+        let recording = Arc::new(RecordingSink::default());
+        let sink = ValidateSink::new(recording.clone());
+        let invalid_before = METRICS.invalid_messages.load(Ordering::Relaxed);
+
+        for (price, side) in [("", "buy"), ("1", ""), ("1", "sell")] {
+            sink.send(&trade(price, side), SendMeta::rest(None)).await.unwrap();
+        }
+
+        assert_eq!(problem(&trade("", "buy")), Some("empty price"));
+        assert_eq!(problem(&trade("1", "")), Some("empty side"));
+
+        let forwarded = recording.messages();
+        assert_eq!(forwarded.len(), 1);
+        assert_eq!(serde_json::to_value(&forwarded[0]).unwrap()["side"], "sell");
+        assert!(METRICS.invalid_messages.load(Ordering::Relaxed) >= invalid_before + 2);
+    }
+}
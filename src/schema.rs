@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Serialize, Deserialize};
 
 /// Central message enum used across the entire data pipeline.
@@ -25,6 +27,64 @@ pub enum MarketMessage {
     Trade(TradeData),
     Book(BookData),
     Ticker(TickerData),
+    Funding(FundingData),
+}
+
+impl MarketMessage {
+    /// All values of the serialized `type` field
+    pub const KINDS: [&'static str; 4] = ["trade", "book", "ticker", "funding"];
+
+    /// Returns the exchange identifier of this message.
+    pub fn exchange(&self) -> &str {
+        match self {
+            MarketMessage::Trade(t) => &t.exchange,
+            MarketMessage::Book(b) => &b.exchange,
+            MarketMessage::Ticker(t) => &t.exchange,
+            MarketMessage::Funding(f) => &f.exchange,
+        }
+    }
+
+    /// Returns the normalized symbol (BASE/QUOTE) of this message.
+    pub fn symbol(&self) -> &str {
+        match self {
+            MarketMessage::Trade(t) => &t.symbol,
+            MarketMessage::Book(b) => &b.symbol,
+            MarketMessage::Ticker(t) => &t.symbol,
+            MarketMessage::Funding(f) => &f.symbol,
+        }
+    }
+
+    /// Returns the exchange timestamp (ms) of this message.
+    pub fn timestamp(&self) -> i64 {
+        match self {
+            MarketMessage::Trade(t) => t.timestamp,
+            MarketMessage::Book(b) => b.timestamp,
+            MarketMessage::Ticker(t) => t.timestamp,
+            MarketMessage::Funding(f) => f.timestamp,
+        }
+    }
+
+    /// Sets the origin tag of this message.
+    pub fn set_source(&mut self, source: &str) {
+        let field = match self {
+            MarketMessage::Trade(t) => &mut t.source,
+            MarketMessage::Book(b) => &mut b.source,
+            MarketMessage::Ticker(t) => &mut t.source,
+            MarketMessage::Funding(f) => &mut f.source,
+        };
+
+        *field = Some(source.to_string());
+    }
+
+    /// Returns the serialized `type` field of this message.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MarketMessage::Trade(_) => "trade",
+            MarketMessage::Book(_) => "book",
+            MarketMessage::Ticker(_) => "ticker",
+            MarketMessage::Funding(_) => "funding",
+        }
+    }
 }
 
 // ------------------------------------------------------------
@@ -63,6 +123,26 @@ pub struct TradeData {
 
     /// Trade side: "buy" or "sell"
     pub side: String,
+
+    /// Exchange trade id, where provided (Binance, OKX, Kraken)
+    ///
+    /// Used to dedup trades backfilled via REST after a reconnect.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trade_id: Option<String>,
+
+    /// Exchange event time in milliseconds, where it differs from the
+    /// trade time (Binance `E`)
+    ///
+    /// `event_ts - timestamp` is the exchange-internal latency.
+    /// Only forwarded with `event_time` enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_ts: Option<i64>,
+
+    /// Origin tag (`source_tag`, default: exchange name)
+    ///
+    /// Set by the collector runner, not by adapters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
 // ------------------------------------------------------------
@@ -79,7 +159,10 @@ pub struct TradeData {
 // IMPORTANT:
 // - This structure represents the *current view* after update,
 //   not a diff format.
-// - Deduplication is handled downstream (master / Redis).
+// - Delta levels with a zero amount remove the price level.
+// - Duplicate levels within one update are collapsed by the
+//   collector (`BookData::normalize`); deduplication across
+//   updates is handled downstream (master / Redis).
 //
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BookData {
@@ -101,6 +184,50 @@ pub struct BookData {
     ///
     /// Sorted descending by price (best bid first).
     pub bids: Vec<[String; 2]>,
+
+    /// Origin tag, see `TradeData::source`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+
+    /// Full book rather than a delta (OKX books5, Gate.io, Bybit
+    /// snapshot frames, REST snapshots)
+    ///
+    /// Collector-internal: local books replace instead of patch.
+    #[serde(skip)]
+    pub snapshot: bool,
+}
+
+impl BookData {
+    /// Collapses duplicate price levels per side; the last quantity
+    /// of a price wins, at the position of its first occurrence.
+    ///
+    /// Some exchanges repeat a level within one delta. Prices are
+    /// compared as sent (same exchange, same formatting).
+    pub fn normalize(&mut self) {
+        collapse_levels(&mut self.asks);
+        collapse_levels(&mut self.bids);
+    }
+}
+
+fn collapse_levels(levels: &mut Vec<[String; 2]>) {
+    if levels.len() < 2 {
+        return;
+    }
+
+    let mut index: HashMap<String, usize> = HashMap::with_capacity(levels.len());
+    let mut collapsed: Vec<[String; 2]> = Vec::with_capacity(levels.len());
+
+    for [price, amount] in levels.drain(..) {
+        match index.get(&price) {
+            Some(&i) => collapsed[i][1] = amount,
+            None => {
+                index.insert(price.clone(), collapsed.len());
+                collapsed.push([price, amount]);
+            }
+        }
+    }
+
+    *levels = collapsed;
 }
 
 // ------------------------------------------------------------
@@ -140,4 +267,72 @@ pub struct TickerData {
 
     /// 24h traded volume
     pub vol_24h: Option<String>,
+
+    /// Origin tag, see `TradeData::source`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+// ------------------------------------------------------------
+// Mark price / funding rate message (perpetual futures)
+// ------------------------------------------------------------
+//
+// Sources:
+// - Binance USDⓈ-M futures (markPriceUpdate)
+// - Bybit linear (tickers)
+// - OKX swaps (funding-rate)
+//
+// `symbol` is the underlying pair of the perpetual
+// (e.g. "BTC/USDT" for BTCUSDT / BTC-USDT-SWAP).
+//
+// Not every exchange sends every field in every frame
+// (e.g. Bybit deltas, OKX has no mark price on this channel).
+//
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FundingData {
+    /// Exchange identifier
+    pub exchange: String,
+
+    /// Underlying pair in normalized format
+    pub symbol: String,
+
+    /// Timestamp in milliseconds
+    pub timestamp: i64,
+
+    /// Current mark price
+    pub mark_price: Option<String>,
+
+    /// Current / predicted funding rate
+    pub funding_rate: Option<String>,
+
+    /// Next funding settlement in milliseconds
+    pub next_funding_time: Option<i64>,
+
+    /// Origin tag, see `TradeData::source`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(raw: &[(&str, &str)]) -> Vec<[String; 2]> {
+        raw.iter().map(|(p, a)| [p.to_string(), a.to_string()]).collect()
+    }
+
+    #[test]
+    fn duplicate_levels_collapse_to_the_last_amount() {
+        let mut book: BookData = serde_json::from_value(serde_json::json!({
+            "exchange": "okx", "symbol": "BTC/USDT", "timestamp": 1,
+            "asks": [["101", "1"], ["102", "2"], ["101", "0"]],
+            "bids": [["99", "1"], ["98", "1"], ["99", "3"], ["99", "4"]],
+        }))
+        .unwrap();
+
+        book.normalize();
+
+        assert_eq!(book.asks, levels(&[("101", "0"), ("102", "2")]));
+        assert_eq!(book.bids, levels(&[("99", "4"), ("98", "1")]));
+    }
 }
@@ -1,22 +1,30 @@
-/// Utility helpers used by all collectors.
-///
-/// This module contains:
-/// - Symbol normalization helpers
-/// - Time helpers
-/// - Small format conversion utilities
-///
-/// IMPORTANT:
-/// - No exchange-specific business logic should live here.
-/// - This module must remain lightweight and deterministic.
-///
-/// Exchange-specific behavior should be handled in:
-/// - adapters
-/// - exchange configuration
-/// - adapter implementations
-///
+//! Utility helpers used by all collectors.
+//!
+//! This module contains:
+//! - Symbol normalization helpers
+//! - Time helpers
+//! - Small format conversion utilities
+//! - JSON field accessors for adapters (`util::json`)
+//!
+//! IMPORTANT:
+//! - No exchange-specific business logic should live here.
+//! - This module must remain lightweight and deterministic.
+//!
+//! Exchange-specific behavior should be handled in:
+//! - adapters
+//! - exchange configuration
+//! - adapter implementations
+
+pub mod json;
 
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::RwLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use once_cell::sync::Lazy;
+use rust_decimal::{Decimal, RoundingStrategy};
+
 /// Normalize trading symbols into the internal master format.
 ///
 /// Target format:
@@ -38,7 +46,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 ///
 #[allow(dead_code)]
 pub fn normalize_symbol(raw: &str) -> String {
-    raw.replace('_', "/").replace('-', "/")
+    raw.replace(['_', '-'], "/")
 }
 
 /// Returns the current Unix timestamp in milliseconds.
@@ -51,13 +59,55 @@ pub fn normalize_symbol(raw: &str) -> String {
 /// PANIC:
 /// - Panics if system time is before UNIX_EPOCH (should never happen).
 ///
+/// Tests can pin the value per thread with `set_test_clock`.
+///
 pub fn now_ms() -> i64 {
+    #[cfg(test)]
+    if let Some(ms) = TEST_CLOCK.get() {
+        return ms;
+    }
+
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("System time is before UNIX_EPOCH")
         .as_millis() as i64
 }
 
+#[cfg(test)]
+thread_local! {
+    static TEST_CLOCK: std::cell::Cell<Option<i64>> = const { std::cell::Cell::new(None) };
+}
+
+/// Pins `now_ms` of the current thread to `ms`; None restores the
+/// system clock.
+#[cfg(test)]
+pub fn set_test_clock(ms: Option<i64>) {
+    TEST_CLOCK.set(ms);
+}
+
+/// Rounds a decimal string to at most `decimals` fractional digits.
+///
+/// Examples (decimals = 4):
+/// - "0.123456789" -> "0.1235"
+/// - "1.2e-5"      -> "0.0000"
+/// - "42.1"        -> "42.1"  (never padded)
+///
+/// DESIGN NOTES:
+/// - Uses `rust_decimal`, so no float error is introduced.
+/// - Midpoints round away from zero.
+/// - Output is always plain notation (no exponent).
+/// - Unparsable input is returned unchanged.
+///
+pub fn round_decimal_str(value: &str, decimals: u32) -> String {
+    Decimal::from_str(value)
+        .or_else(|_| Decimal::from_scientific(value))
+        .map(|d| {
+            d.round_dp_with_strategy(decimals, RoundingStrategy::MidpointAwayFromZero)
+                .to_string()
+        })
+        .unwrap_or_else(|_| value.to_string())
+}
+
 /// Convert an internal symbol into the exchange-specific format.
 ///
 /// Input:
@@ -71,6 +121,7 @@ pub fn now_ms() -> i64 {
 /// - ("gateio", "BTC/USDT")   -> "BTC_USDT"
 /// - ("binance", "BTC/USDT")  -> "BTCUSDT"
 /// - ("coinbase", "BTC/USDT") -> "BTC-USDT"
+/// - ("kraken", "XBT/USD")    -> "BTC/USD"
 ///
 /// DESIGN NOTES:
 /// - Centralized symbol conversion avoids duplication across adapters.
@@ -81,18 +132,145 @@ pub fn now_ms() -> i64 {
 /// - Add unit tests per exchange.
 ///
 pub fn symbol_to_exchange(exchange: &str, symbol: &str) -> String {
+    if let Some(mapped) = mapped_symbol(exchange, symbol, |map| &map.to_exchange) {
+        return mapped;
+    }
+
     match exchange {
         "gateio" => symbol.replace('/', "_"),
         "bitrue" => symbol.replace('/', "").to_lowercase(),
         "bitstamp" => symbol.replace('/', "").to_lowercase(),
-        "binance" | "binanceus" | "bybit" => symbol.replace('/', ""),
+        "binance" | "binanceus" | "mexcspot"
+        | "bybit" | "bybitlinear" | "bybitinverse" => symbol.replace('/', ""),
         "okx" | "kucoin" | "coinbase" => symbol.replace('/', "-"),
-        "mexc" => symbol.replace('/', "_"),
-        "bitfinex" => format!("t{}", symbol.replace('/', "")),
+        // MEXC futures contracts are upper-case BASE_QUOTE for every
+        // quote asset (BTC_USDT, BTC_USDC, BTC_USD);
+        // MEXC spot uses BTCUSDT (see "mexcspot" above)
+        "mexc" => symbol.replace('/', "_").to_uppercase(),
+        // WS v2 uses the common names (BTC/USD, DOGE/EUR); legacy
+        // aliases in the config are translated to them
+        "kraken" => kraken_symbol(symbol),
+        // Bitfinex: "t" prefix, colon when base or quote is longer
+        // than 3 chars (tBTCUSD, tDOGE:USD), USDT is called UST
+        "bitfinex" => match symbol.split_once('/') {
+            Some((base, quote)) => {
+                let quote = if quote == "USDT" { "UST" } else { quote };
+                if base.len() > 3 || quote.len() > 3 {
+                    format!("t{}:{}", base, quote)
+                } else {
+                    format!("t{}{}", base, quote)
+                }
+            }
+            None => format!("t{}", symbol),
+        },
         _ => symbol.to_string(),
     }
 }
 
+/// Configured symbol overrides of one exchange (`symbol_map`)
+#[derive(Default, PartialEq)]
+struct SymbolMap {
+    /// internal → exchange
+    to_exchange: HashMap<String, String>,
+    /// exchange → internal
+    from_exchange: HashMap<String, String>,
+}
+
+/// exchange → overrides, registered at startup
+static SYMBOL_MAPS: Lazy<RwLock<HashMap<String, SymbolMap>>> = Lazy::new(Default::default);
+
+/// Registers the `symbol_map` overrides of `exchange`.
+///
+/// Consulted by `symbol_to_exchange` / `symbol_from_exchange` before
+/// the generic rules. Overrides are keyed by exchange name, so every
+/// instance of an exchange must use the same map; registering it
+/// again (restart via the admin endpoint) is a no-op.
+///
+/// ERRORS:
+/// - Two internal symbols map to the same exchange symbol
+/// - Different overrides are already registered for `exchange`
+pub fn set_symbol_map(exchange: &str, map: &HashMap<String, String>) -> anyhow::Result<()> {
+    let from_exchange: HashMap<String, String> =
+        map.iter().map(|(k, v)| (v.clone(), k.clone())).collect();
+
+    if from_exchange.len() != map.len() {
+        return Err(anyhow::anyhow!(
+            "symbol_map of {exchange} maps several symbols to the same exchange symbol"
+        ));
+    }
+
+    let entry = SymbolMap {
+        to_exchange: map.clone(),
+        from_exchange,
+    };
+
+    let mut maps = SYMBOL_MAPS.write().unwrap_or_else(|e| e.into_inner());
+
+    match maps.get(exchange) {
+        Some(existing) if *existing != entry => Err(anyhow::anyhow!(
+            "symbol_map of {exchange} differs from the one already in use by another {exchange} instance"
+        )),
+        Some(_) => Ok(()),
+        None => {
+            maps.insert(exchange.to_string(), entry);
+            Ok(())
+        }
+    }
+}
+
+/// Looks up `symbol` in one direction of the overrides of `exchange`.
+fn mapped_symbol(
+    exchange: &str,
+    symbol: &str,
+    direction: fn(&SymbolMap) -> &HashMap<String, String>,
+) -> Option<String> {
+    let maps = SYMBOL_MAPS.read().unwrap_or_else(|e| e.into_inner());
+    maps.get(exchange).and_then(|map| direction(map).get(symbol)).cloned()
+}
+
+/// Kraken legacy asset names and their common names
+const KRAKEN_ALIASES: [(&str, &str); 2] = [("XBT", "BTC"), ("XDG", "DOGE")];
+
+/// Replaces Kraken legacy asset names (XBT/USD → BTC/USD) on both
+/// sides of a BASE/QUOTE symbol.
+fn kraken_symbol(symbol: &str) -> String {
+    let asset = |a: &str| {
+        KRAKEN_ALIASES
+            .iter()
+            .find(|(legacy, _)| *legacy == a)
+            .map_or(a, |(_, common)| common)
+            .to_string()
+    };
+
+    match symbol.split_once('/') {
+        Some((base, quote)) => format!("{}/{}", asset(base), asset(quote)),
+        None => asset(symbol),
+    }
+}
+
+/// Quote assets recognized in symbols without separator
+/// (BTCUSDT, ethbtc).
+///
+/// Longer quotes sharing a suffix must come first
+/// (FDUSD / TUSD / USDT / USDC before USD).
+const KNOWN_QUOTES: [&str; 11] = [
+    "FDUSD", "USDT", "USDC", "TUSD", "BUSD", "USD",
+    "EUR", "TRY", "BTC", "ETH", "BNB",
+];
+
+/// Splits a concatenated upper-case symbol at the first known quote
+/// suffix (BTCUSDT → BTC/USDT). Unknown quotes are returned unchanged.
+fn split_known_quote(symbol: &str) -> String {
+    for quote in KNOWN_QUOTES {
+        if let Some(base) = symbol.strip_suffix(quote)
+            && !base.is_empty()
+        {
+            return format!("{}/{}", base, quote);
+        }
+    }
+    symbol.to_string()
+}
+
 /// Convert an exchange-specific symbol into the internal format.
 ///
 /// Input:
@@ -108,87 +286,129 @@ pub fn symbol_to_exchange(exchange: &str, symbol: &str) -> String {
 ///
 /// IMPORTANT:
 /// - Some exchanges (notably Binance) do not provide explicit
-///   separators in their symbols.
+///   separators in their symbols. These are split at a known
+///   quote asset (`KNOWN_QUOTES`), so
+///   `symbol_from_exchange(ex, symbol_to_exchange(ex, s)) == s`
+///   holds for pairs quoted in any of them.
 ///
 /// TODO:
-/// - Implement proper base/quote detection for Binance symbols
-///   using known quote assets (USDT, USD, BTC, etc.).
 /// - Move complex parsing into dedicated exchange adapters.
 ///
-///
-const BINANCE_QUOTES: [&str; 2] = [
-    "USDT",
-    "USD"
-];
 pub fn symbol_from_exchange(exchange: &str, symbol: &str) -> String {
+    if let Some(mapped) = mapped_symbol(exchange, symbol, |map| &map.from_exchange) {
+        return mapped;
+    }
+
     match exchange {
         "gateio" => symbol.replace('_', "/"),
-        "mexc" => symbol.replace('_', "/"),
-
-        "binance" | "binanceus" => {
-            for quote in BINANCE_QUOTES {
-                if symbol.ends_with(quote) {
-                    let base = &symbol[..symbol.len() - quote.len()];
-                    if !base.is_empty() {
-                        return format!("{}/{}", base, quote);
-                    }
-                }
-            }
-            symbol.to_string()
-        },
+        "mexc" => symbol.replace('_', "/").to_uppercase(),
+
+        "binance" | "binanceus" | "mexcspot"
+        | "bybit" | "bybitlinear" | "bybitinverse" => split_known_quote(symbol),
 
         "okx" | "kucoin" | "coinbase" => symbol.replace('-', "/"),
 
-        "bybit" => {
-            for quote in ["USDT", "USD"] {
-                if symbol.ends_with(quote) {
-                    let base = &symbol[..symbol.len() - quote.len()];
-                    return format!("{}/{}", base, quote);
-                }
-            }
-            symbol.to_string()
-        },
+        // lower-case on the wire (btcusdt)
+        "bitstamp" | "bitrue" => split_known_quote(&symbol.to_uppercase()),
 
-        "bitstamp" => {
-            let s = symbol.to_uppercase();
-            for quote in ["USDT", "USD", "USDC"] {
-                if s.ends_with(quote) {
-                    let base = &s[..s.len() - quote.len()];
-                    if !base.is_empty() {
-                        return format!("{}/{}", base, quote);
-                    }
-                }
-            }
-            s
-        },
+        "kraken" => kraken_symbol(&symbol.replace('-', "/")),
+        "bitfinex" => {
+            let s = symbol.strip_prefix('t').unwrap_or(symbol);
 
-        "bitrue" => {
-            if symbol.ends_with("usdt") {
-                format!("{}/USDT", symbol[..symbol.len() - 4].to_uppercase())
-            } else if symbol.ends_with("usd") {
-                format!("{}/USD", symbol[..symbol.len() - 3].to_uppercase())
-            } else {
-                symbol.to_uppercase()
-            }
-        },
+            // tTESTBTC:TESTUSD → explicit separator
+            // tBTCUSD          → always 3 + 3 chars
+            let (base, quote) = match s.split_once(':') {
+                Some(parts) => parts,
+                None if s.len() == 6 && s.is_char_boundary(3) => s.split_at(3),
+                None => return s.to_string(),
+            };
 
-        "kraken" => {
-            let s = symbol.replace('-', "/");
-            if s.starts_with("XBT/") {
-                s.replacen("XBT/", "BTC/", 1)
-            } else {
-                s
-            }
-        },
-        "bitfinex" => {
-            let s = symbol.trim_start_matches('t');
-            for quote in ["USDT", "USD", "USDC"] {
-                if s.ends_with(quote) {
-                    return format!("{}/{}", &s[..s.len()-quote.len()], quote);
-                }
-            }
-            s.to_string()
+            let quote = if quote == "UST" { "USDT" } else { quote };
+            format!("{}/{}", base, quote)
         },
         _ => symbol.to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    /// `symbol_from_exchange(symbol_to_exchange(pair)) == pair`, via
+    /// the given exchange symbol.
+    fn assert_round_trip(exchange: &str, pair: &str, wire: &str) {
+        assert_eq!(symbol_to_exchange(exchange, pair), wire, "{exchange} {pair}");
+        assert_eq!(symbol_from_exchange(exchange, wire), pair, "{exchange} {wire}");
+    }
+
+    #[test]
+    fn mexc_futures_symbols_round_trip() {
+        for (pair, wire) in [
+            ("BTC/USDT", "BTC_USDT"),
+            ("BTC/USDC", "BTC_USDC"),
+            ("BTC/USD", "BTC_USD"),
+            ("1000PEPE/USDT", "1000PEPE_USDT"),
+        ] {
+            assert_round_trip("mexc", pair, wire);
+        }
+
+        assert_eq!(symbol_to_exchange("mexc", "eth/usdt"), "ETH_USDT");
+    }
+
+    #[test]
+    fn bitfinex_symbols_round_trip() {
+        for (pair, wire) in [
+            ("BTC/USD", "tBTCUSD"),
+            ("BTC/USDT", "tBTCUST"),
+            ("DOGE/USD", "tDOGE:USD"),
+            ("TESTBTC/TESTUSD", "tTESTBTC:TESTUSD"),
+        ] {
+            assert_round_trip("bitfinex", pair, wire);
+        }
+    }
+
+    #[test]
+    fn kraken_legacy_aliases_map_to_common_names() {
+        for (pair, wire) in [
+            ("BTC/USD", "BTC/USD"),
+            ("DOGE/EUR", "DOGE/EUR"),
+            ("ETH/BTC", "ETH/BTC"),
+        ] {
+            assert_round_trip("kraken", pair, wire);
+        }
+
+        assert_eq!(symbol_to_exchange("kraken", "XBT/USD"), "BTC/USD");
+        assert_eq!(symbol_to_exchange("kraken", "XDG/EUR"), "DOGE/EUR");
+        assert_eq!(symbol_from_exchange("kraken", "XBT/USD"), "BTC/USD");
+        assert_eq!(symbol_from_exchange("kraken", "ETH-XBT"), "ETH/BTC");
+    }
+
+    #[test]
+    fn symbol_map_overrides_round_trip() {
+        // Own exchange name: the overrides are process-global
+        set_symbol_map("binance-maptest", &map(&[("MATIC/USDT", "POLUSDT")])).unwrap();
+
+        assert_eq!(symbol_to_exchange("binance-maptest", "MATIC/USDT"), "POLUSDT");
+        assert_eq!(symbol_from_exchange("binance-maptest", "POLUSDT"), "MATIC/USDT");
+
+        // Unmapped symbols and other exchanges keep the generic rules
+        assert_eq!(symbol_to_exchange("binance", "MATIC/USDT"), "MATICUSDT");
+        assert_eq!(symbol_from_exchange("binance", "POLUSDT"), "POL/USDT");
+    }
+
+    #[test]
+    fn conflicting_symbol_maps_are_rejected() {
+        let first = map(&[("MATIC/USDT", "POLUSDT")]);
+
+        set_symbol_map("okx-maptest", &first).unwrap();
+        set_symbol_map("okx-maptest", &first).unwrap();
+        assert!(set_symbol_map("okx-maptest", &map(&[("MATIC/USDT", "POL-USDT")])).is_err());
+
+        let ambiguous = map(&[("MATIC/USDT", "POLUSDT"), ("POL/USDT", "POLUSDT")]);
+        assert!(set_symbol_map("bybit-maptest", &ambiguous).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Resilient JSON field accessors for adapters.
+//!
+//! Exchange payloads are loosely typed: the same field may arrive as a
+//! string on one exchange and as a number on another, or be missing on
+//! some frames. These helpers centralize the fallback handling.
+//!
+//! PATHS:
+//! - Dotted, e.g. "data.0.px"
+//! - Numeric segments index into arrays
+//!
+//! Every fallback to a default increments `missing_fields`, so
+//! silently defaulted values stay visible in the metrics.
+
+use std::sync::atomic::Ordering;
+
+use serde_json::Value;
+
+use crate::metrics::METRICS;
+
+/// Resolves a dotted path. Returns None if any segment is missing.
+pub fn get<'a>(v: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(v, |v, key| match v {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => v.get(key),
+    })
+}
+
+/// String field, or `default` if missing / not a string.
+pub fn str_or(v: &Value, path: &str, default: &str) -> String {
+    match get(v, path).and_then(|v| v.as_str()) {
+        Some(s) => s.to_string(),
+        None => missing(default.to_string()),
+    }
+}
+
+/// Integer field (number or numeric string), or `default`.
+pub fn i64_or(v: &Value, path: &str, default: i64) -> i64 {
+    let value = get(v, path).and_then(|v| match v {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    });
+
+    value.unwrap_or_else(|| missing(default))
+}
+
+/// Decimal field as string (string or number), or "0".
+///
+/// Strings are kept verbatim so no precision is lost; numbers use
+/// their JSON representation.
+pub fn decimal_str(v: &Value, path: &str) -> String {
+    match get(v, path) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => n.to_string(),
+        _ => missing("0".to_string()),
+    }
+}
+
+fn missing<T>(default: T) -> T {
+    METRICS.missing_fields.fetch_add(1, Ordering::Relaxed);
+    default
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn decimal_str_keeps_strings_verbatim() {
+        let v = json!({ "data": [{ "px": "0.00001000", "sz": 2.5, "side": null }] });
+
+        assert_eq!(decimal_str(&v, "data.0.px"), "0.00001000");
+        assert_eq!(decimal_str(&v, "data.0.sz"), "2.5");
+        assert_eq!(decimal_str(&v, "data.0.side"), "0");
+        assert_eq!(decimal_str(&v, "data.1.px"), "0");
+    }
+
+    #[test]
+    fn str_or_falls_back_on_missing_and_mistyped_fields() {
+        let v = json!({ "data": [{ "side": "buy", "id": 7, "note": null }] });
+
+        assert_eq!(str_or(&v, "data.0.side", "?"), "buy");
+        assert_eq!(str_or(&v, "data.0.id", "?"), "?");
+        assert_eq!(str_or(&v, "data.0.note", "?"), "?");
+        assert_eq!(str_or(&v, "data.0.missing", "?"), "?");
+        assert_eq!(str_or(&v, "data.x.side", "?"), "?");
+        assert_eq!(str_or(&v, "data.0.side.deeper", "?"), "?");
+    }
+
+    #[test]
+    fn i64_or_accepts_numbers_and_numeric_strings() {
+        let v = json!({ "ts": 1700000000000_i64, "id": "42", "bad": "4x2", "px": 1.5, "flag": true });
+        let missing_before = METRICS.missing_fields.load(Ordering::Relaxed);
+
+        assert_eq!(i64_or(&v, "ts", -1), 1_700_000_000_000);
+        assert_eq!(i64_or(&v, "id", -1), 42);
+
+        for mistyped in ["bad", "px", "flag", "absent"] {
+            assert_eq!(i64_or(&v, mistyped, -1), -1, "{mistyped}");
+        }
+
+        // Other tests count too, so at least the four fallbacks
+        assert!(METRICS.missing_fields.load(Ordering::Relaxed) >= missing_before + 4);
+    }
+}